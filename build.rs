@@ -7,6 +7,18 @@ use std::process::Command;
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     // Directory containing the helper web project
     let manifest_dir = PathBuf::from(env::var("CARGO_MANIFEST_DIR")?);
+
+    // Expose the version of MicroTeX, e.g. for the key of the formula cache.  The lock file does
+    // not exist if this crate is a dependency or a workspace member, and Cargo would run this
+    // script on every build if we asked it to watch a missing file.
+    let lock_file = manifest_dir.join("Cargo.lock");
+    if lock_file.exists() {
+        println!("cargo:rerun-if-changed={}", lock_file.display());
+    }
+    if let Some(version) = microtex_version(&lock_file) {
+        println!("cargo:rustc-env=MICROTEX_VERSION={}", version);
+    }
+
     // Web helper is now located at the repository root (`./mermaid_pool`). Keep a fallback
     // to the historical `examples/mermaid_pool` path for backwards compatibility.
     let mut web_dir = manifest_dir.join("mermaid_pool");
@@ -37,6 +49,11 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         }
     }
 
+    // Expose the version of the bundled Mermaid runtime, e.g. for the key of the SVG cache
+    if let Some(version) = mermaid_version(&web_dir.join("package-lock.json")) {
+        println!("cargo:rustc-env=MERMAID_VERSION={}", version);
+    }
+
     // Collect files we care about and emit rerun-if-changed for each
     let mut latest_src_mtime = None::<std::time::SystemTime>;
     visit_files(&web_dir, &mut |p| {
@@ -121,6 +138,42 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     Ok(())
 }
 
+/// Reads the version of the `mermaid` package from the given `package-lock.json` file.
+fn mermaid_version(lock_file: &Path) -> Option<String> {
+    let lock = fs::read_to_string(lock_file).ok()?;
+    let package = &lock[lock.find("\"node_modules/mermaid\"")?..];
+    let version = &package[package.find("\"version\"")? + "\"version\"".len()..];
+    let version = version.trim_start().strip_prefix(':')?.trim_start();
+    let version = version.strip_prefix('"')?;
+    Some(version[..version.find('"')?].to_owned())
+}
+
+/// Reads the version and the source, e.g. the git commit, of the `microtex_rs` package from the
+/// given `Cargo.lock` file.
+fn microtex_version(lock_file: &Path) -> Option<String> {
+    let lock = fs::read_to_string(lock_file).ok()?;
+    let package = lock.split("[[package]]").find(|package| {
+        package
+            .lines()
+            .any(|line| line.trim() == "name = \"microtex_rs\"")
+    })?;
+    let value = |key: &str| {
+        package.lines().find_map(|line| {
+            let value = line
+                .trim()
+                .strip_prefix(key)?
+                .trim_start()
+                .strip_prefix('=')?;
+            Some(value.trim().trim_matches('"').to_owned())
+        })
+    };
+    let version = value("version")?;
+    Some(match value("source") {
+        Some(source) => format!("{} ({})", version, source),
+        None => version,
+    })
+}
+
 fn visit_files(dir: &Path, cb: &mut dyn FnMut(&Path) -> io::Result<()>) -> io::Result<()> {
     if dir.is_file() {
         cb(dir)?;
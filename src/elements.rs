@@ -22,10 +22,14 @@
 //!   - [`FramedElement`][]: draws a frame around the wrapped element
 //!   - [`PaddedElement`][]: adds a padding to the wrapped element
 //!   - [`StyledElement`][]: sets a default style for the wrapped element and its children
+//!   - [`KeepTogether`][]: prevents the wrapped element from being split across pages
+//!   - [`KeepWithNext`][]: keeps the wrapped element on the same page as the next element
+//!   - [`Figure`][]: adds a numbered caption to the wrapped element
 //! - Other:
 //!   - [`Image`][]: an image (requires the `images` feature)
 //!   - [`Break`][]: adds forced line breaks as a spacer
 //!   - [`PageBreak`][]: adds a forced page break
+//!   - [`ListOfFigures`][]: lists the figures of the document
 //!
 //! You can create custom elements by implementing the [`Element`][] trait.
 //!
@@ -42,6 +46,10 @@
 //! [`FramedElement`]: struct.FramedElement.html
 //! [`PaddedElement`]: struct.PaddedElement.html
 //! [`StyledElement`]: struct.StyledElement.html
+//! [`KeepTogether`]: struct.KeepTogether.html
+//! [`KeepWithNext`]: struct.KeepWithNext.html
+//! [`Figure`]: struct.Figure.html
+//! [`ListOfFigures`]: struct.ListOfFigures.html
 
 #[cfg(feature = "images")]
 mod images;
@@ -49,10 +57,17 @@ mod images;
 #[cfg(feature = "latex")]
 mod latex;
 
+#[cfg(feature = "latex")]
+mod mathml;
+
 #[cfg(feature = "mermaid")]
 mod mermaid;
 
+#[cfg(feature = "images")]
+mod svg_text;
+
 use std::collections;
+use std::fmt;
 use std::iter;
 use std::mem;
 
@@ -65,13 +80,16 @@ use crate::wrap;
 use crate::{Alignment, Context, Element, Margins, Mm, Position, RenderResult, Size};
 
 #[cfg(feature = "images")]
-pub use images::Image;
+pub use images::{Image, ImageFit};
 
 #[cfg(feature = "latex")]
-pub use latex::Latex;
+pub use latex::{Latex, LatexMacros};
 
 #[cfg(feature = "mermaid")]
-pub use mermaid::Mermaid;
+pub use mermaid::{
+    ChromeRenderer, CommandRenderer, DiagramRenderer, Mermaid, MermaidCache, MermaidConfig,
+    MermaidTheme,
+};
 
 /// Helper trait for creating boxed elements.
 pub trait IntoBoxedElement {
@@ -157,7 +175,22 @@ impl LinearLayout {
         style: Style,
     ) -> Result<RenderResult, Error> {
         let mut result = RenderResult::default();
+        // The index of the last element that is already known to fit into this area
+        let mut fits_until = None;
         while area.size().height > Mm(0.0) && self.render_idx < self.elements.len() {
+            // Elements that are kept together or with the next element are moved to the next page
+            // if they don't fit.  If nothing has been rendered on this page yet, moving them would
+            // not help, so we render them anyway.
+            let is_checked = matches!(fits_until, Some(end) if self.render_idx <= end);
+            if result.size.height > Mm(0.0) && !is_checked {
+                match self.keeps_fit(context, &area, style)? {
+                    Some(end) => fits_until = Some(end),
+                    None => {
+                        result.has_more = true;
+                        return Ok(result);
+                    }
+                }
+            }
             let element_result =
                 self.elements[self.render_idx].render(context, area.clone(), style)?;
             area.add_offset(Position::new(0, element_result.size.height));
@@ -171,6 +204,46 @@ impl LinearLayout {
         result.has_more = self.render_idx < self.elements.len();
         Ok(result)
     }
+
+    /// Checks whether the next element and all elements it is kept with fit into the given area.
+    ///
+    /// All elements of the group except the last one have to fit completely.  The last element has
+    /// to fit completely if it is kept together, otherwise it is sufficient if it can be started.
+    /// If the group fits, the index of its last element is returned so that the group is only
+    /// measured once.
+    fn keeps_fit(
+        &self,
+        context: &Context,
+        area: &render::Area<'_>,
+        style: Style,
+    ) -> Result<Option<usize>, Error> {
+        let start = self.render_idx;
+        let mut end = start;
+        while end + 1 < self.elements.len() && self.elements[end].is_kept_with_next() {
+            end += 1;
+        }
+        if end == start && !self.elements[start].is_kept_together() {
+            return Ok(Some(end));
+        }
+
+        let mut offset = Mm(0.0);
+        for (idx, element) in self.elements[start..=end].iter().enumerate() {
+            let mut remaining = area.size();
+            remaining.height -= offset;
+            if remaining.height <= Mm(0.0) {
+                return Ok(None);
+            }
+            let result = element.measure(context, remaining, style)?;
+            if result.has_more {
+                let is_last = start + idx == end;
+                if !is_last || element.is_kept_together() || result.size.height == Mm(0.0) {
+                    return Ok(None);
+                }
+            }
+            offset += result.size.height;
+        }
+        Ok(Some(end))
+    }
 }
 
 impl Element for LinearLayout {
@@ -183,6 +256,16 @@ impl Element for LinearLayout {
         // TODO: add horizontal layout
         self.render_vertical(context, area, style)
     }
+
+    fn measure(&self, context: &Context, size: Size, style: Style) -> Result<RenderResult, Error> {
+        // A clone starts rendering with the first element, so we have to restore the rendering
+        // state to measure a partially rendered layout.
+        let mut layout = self.clone();
+        layout.render_idx = self.render_idx;
+        let renderer = render::Renderer::new(size, "")?;
+        let area = renderer.first_page().first_layer().area();
+        context.dry_run(|| layout.render(context, area, style))
+    }
 }
 
 impl<E: IntoBoxedElement> iter::Extend<E> for LinearLayout {
@@ -197,6 +280,7 @@ mod tests {
     use super::*;
     use crate::render::Renderer;
     use crate::style::Style;
+    use crate::test_util;
     use crate::Margins;
 
     // Dummy element used for testing constructors and push methods without invoking rendering
@@ -338,6 +422,7 @@ mod tests {
                     has_more: true,
                     size: Size::new(10.0, 5.0),
                     svg: None,
+                    warnings: Vec::new(),
                 })
             }
         }
@@ -733,7 +818,7 @@ mod tests {
         );
 
         // Now attempt to render a paragraph with a long unbreakable word in the same (tiny) area
-        let mut p = Paragraph::default();
+        let mut p = Paragraph::default().with_word_overflow(WordOverflow::Error);
         let long_word = "X".repeat(500);
         p.push(&long_word);
 
@@ -752,6 +837,234 @@ mod tests {
         }
     }
 
+    #[test]
+    fn paragraph_long_word_is_broken_with_warning() {
+        let (r, context) = test_util::renderer(Size::new(50.0, 200.0));
+        let area = r.first_page().first_layer().area();
+
+        let mut p = Paragraph::new("X".repeat(100));
+        let res = p
+            .render(&context, area.clone(), Style::new())
+            .expect("long word should be broken instead of failing");
+        assert!(!res.has_more);
+        assert!(res.size.width <= area.size().width);
+        assert!(res.size.height > Style::new().line_height(&context.font_cache));
+        assert_eq!(context.take_warnings().len(), 1);
+    }
+
+    #[test]
+    fn paragraph_warns_once_for_long_word_on_next_page() {
+        let cache = test_util::font_cache();
+        let style = Style::new();
+        // One word per line and room for exactly two lines on the first page
+        let width = style.str_width(&cache, "aaaa ") * 1.5;
+        let height = style.line_height(&cache) * 2.0 + Mm(0.1);
+        let (mut r, context) = test_util::renderer(Size::new(width, height));
+
+        let mut p = Paragraph::new(format!("aaaa aaaa {}", "X".repeat(20)));
+        let area = r.first_page().first_layer().area();
+        let res = p.render(&context, area, style).expect("render");
+        assert!(res.has_more);
+        assert!(context.take_warnings().is_empty());
+
+        r.add_page(Size::new(width, Mm(200.0)));
+        let area = r.last_page().last_layer().area();
+        let res = p.render(&context, area, style).expect("render");
+        assert!(!res.has_more);
+        assert_eq!(context.take_warnings().len(), 1);
+    }
+
+    #[test]
+    fn paragraph_respects_widows_and_orphans() {
+        let cache = test_util::font_cache();
+        let style = Style::new();
+        // One word per line and room for exactly three lines
+        let width = style.str_width(&cache, "aaaa ") * 1.5;
+        let height = style.line_height(&cache) * 3.0 + Mm(0.1);
+        let text = "aaaa aaaa aaaa aaaa";
+
+        let render = |p: &mut Paragraph| {
+            let (r, context) = test_util::renderer(Size::new(width, height));
+            let area = r.first_page().first_layer().area();
+            let res = p.render(&context, area, style).expect("render");
+            let lines = (res.size.height.0 / style.line_height(&context.font_cache).0).round();
+            (lines as usize, res.has_more)
+        };
+
+        let mut p = Paragraph::new(text);
+        assert_eq!(render(&mut p), (3, true));
+        assert_eq!(render(&mut p), (1, false));
+
+        // Two lines have to be moved to the next page
+        let mut p = Paragraph::new(text).with_widows(2);
+        assert_eq!(render(&mut p), (2, true));
+        assert_eq!(render(&mut p), (2, false));
+
+        // The first four lines have to stay together, so the paragraph is moved completely
+        let mut p = Paragraph::new(text).with_orphans(4);
+        assert_eq!(render(&mut p), (0, true));
+        // On the next page, the limits are ignored so that the paragraph is not moved again
+        assert_eq!(render(&mut p), (3, true));
+        assert_eq!(render(&mut p), (1, false));
+        let mut p = Paragraph::new(text).with_widows(2).with_orphans(3);
+        assert_eq!(render(&mut p), (0, true));
+        assert_eq!(render(&mut p), (3, true));
+        assert_eq!(render(&mut p), (1, false));
+        let mut p = Paragraph::new(text).with_orphans(3);
+        assert_eq!(render(&mut p), (3, true));
+    }
+
+    #[test]
+    fn linear_layout_moves_kept_elements_to_next_page() {
+        let cache = test_util::font_cache();
+        let style = Style::new();
+        // One word per line and room for exactly three lines
+        let width = style.str_width(&cache, "aaaa ") * 1.5;
+        let height = style.line_height(&cache) * 3.0 + Mm(0.1);
+
+        let render = |layout: &mut LinearLayout| {
+            let (r, context) = test_util::renderer(Size::new(width, height));
+            let area = r.first_page().first_layer().area();
+            let res = layout.render(&context, area, style).expect("render");
+            let lines = (res.size.height.0 / style.line_height(&context.font_cache).0).round();
+            (lines as usize, res.has_more)
+        };
+
+        // Without keep flags, the second paragraph is split
+        let mut layout = LinearLayout::vertical()
+            .element(Paragraph::new("aaaa"))
+            .element(Paragraph::new("aaaa aaaa aaaa"));
+        assert_eq!(render(&mut layout), (3, true));
+        assert_eq!(render(&mut layout), (1, false));
+
+        // A paragraph that is kept together is moved to the next page
+        let mut layout = LinearLayout::vertical()
+            .element(Paragraph::new("aaaa"))
+            .element(Paragraph::new("aaaa aaaa aaaa").kept_together());
+        assert_eq!(render(&mut layout), (1, true));
+        assert_eq!(render(&mut layout), (3, false));
+
+        // A heading is moved to the next page if the following paragraph cannot be started
+        let mut layout = LinearLayout::vertical()
+            .element(Paragraph::new("aaaa aaaa"))
+            .element(Paragraph::new("head").kept_with_next())
+            .element(Paragraph::new("aaaa aaaa"));
+        assert_eq!(render(&mut layout), (2, true));
+        assert_eq!(render(&mut layout), (3, false));
+
+        // If the element does not fit on an empty page either, it is split as usual
+        let mut layout =
+            LinearLayout::vertical().element(Paragraph::new("aaaa aaaa aaaa aaaa").kept_together());
+        assert_eq!(render(&mut layout), (3, true));
+        assert_eq!(render(&mut layout), (1, false));
+    }
+
+    #[test]
+    fn linear_layout_measures_kept_group_once() {
+        use crate::Context;
+        use std::cell::Cell;
+        use std::rc::Rc;
+
+        #[derive(Clone)]
+        struct Counted(Rc<Cell<usize>>);
+        impl Element for Counted {
+            fn render(
+                &mut self,
+                _context: &Context,
+                _area: render::Area<'_>,
+                _style: Style,
+            ) -> Result<RenderResult, Error> {
+                self.0.set(self.0.get() + 1);
+                Ok(RenderResult {
+                    size: Size::new(10.0, 1.0),
+                    ..Default::default()
+                })
+            }
+        }
+
+        let context = test_util::context();
+        let r = Renderer::new(Size::new(100.0, 100.0), "t").expect("renderer");
+        let area = r.first_page().first_layer().area();
+
+        let renders = Rc::new(Cell::new(0));
+        let mut layout = LinearLayout::vertical();
+        for _ in 0..9 {
+            layout.push(Counted(renders.clone()).kept_with_next());
+        }
+        layout.push(Counted(renders.clone()));
+        let res = layout.render(&context, area, Style::new()).expect("render");
+        assert!(!res.has_more);
+        // Ten renders and one measurement of the nine elements following the first one
+        assert_eq!(renders.get(), 19);
+    }
+
+    #[test]
+    fn measure_does_not_consume_element_state() {
+        let cache = test_util::font_cache();
+        let style = Style::new();
+        let width = style.str_width(&cache, "aaaa ") * 1.5;
+        let height = style.line_height(&cache) * 3.0 + Mm(0.1);
+        let size = Size::new(width, height);
+        let (mut r, context) = test_util::renderer(size);
+
+        let mut layout = LinearLayout::vertical()
+            .element(Paragraph::new("aaaa aaaa"))
+            .element(Paragraph::new("aaaa aaaa aaaa"));
+        let measured = layout.measure(&context, size, style).expect("measure");
+        assert_eq!(
+            measured,
+            layout.measure(&context, size, style).expect("measure")
+        );
+
+        let area = r.first_page().first_layer().area();
+        let rendered = layout.render(&context, area, style).expect("render");
+        assert_eq!(measured, rendered);
+        assert!(rendered.has_more);
+        // Clones start rendering from the beginning
+        assert_eq!(layout.clone().render_idx, 0);
+
+        // The measurement of a partially rendered layout starts where the rendering stopped
+        let measured = layout.measure(&context, size, style).expect("measure");
+        r.add_page(size);
+        let area = r.last_page().last_layer().area();
+        assert_eq!(
+            measured,
+            layout.render(&context, area, style).expect("render")
+        );
+    }
+
+    #[test]
+    fn measure_keeps_state_of_wrapped_layout() {
+        let cache = test_util::font_cache();
+        let style = Style::new();
+        let width = style.str_width(&cache, "aaaa ") * 1.5 + Mm(1.0);
+        let height = style.line_height(&cache) * 3.0 + Mm(1.0);
+        let size = Size::new(width, height);
+        let (mut r, context) = test_util::renderer(size);
+
+        let layout = LinearLayout::vertical()
+            .element(Paragraph::new("aaaa aaaa"))
+            .element(Paragraph::new("aaaa aaaa aaaa"));
+        let mut framed = layout.framed(LineStyle::new()).kept_together();
+        let area = r.first_page().first_layer().area();
+        assert!(
+            framed
+                .render(&context, area, style)
+                .expect("render")
+                .has_more
+        );
+
+        // The measurement continues with the second paragraph
+        let measured = framed.measure(&context, size, style).expect("measure");
+        assert!(!measured.has_more);
+        r.add_page(size);
+        let area = r.last_page().last_layer().area();
+        assert_eq!(
+            measured,
+            framed.render(&context, area, style).expect("render")
+        );
+    }
+
     #[test]
     #[cfg(feature = "images")]
     fn mermaid_sanitized_svg_should_render_without_page_overflow() {
@@ -817,6 +1130,182 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    #[cfg(feature = "images")]
+    fn test_paragraph_inline_image_expands_line_height() {
+        let (r, context) = test_util::renderer(Size::new(210.0, 297.0));
+        let style = Style::new();
+        let metrics = style.metrics(&context.font_cache);
+
+        // 100 px at 254 DPI = 10 mm
+        let pixels = image::RgbImage::from_pixel(100, 100, image::Rgb([0, 0, 255]));
+        let icon = Image::from_dynamic_image(image::DynamicImage::ImageRgb8(pixels))
+            .expect("image")
+            .with_dpi(254.0);
+        let offset = Mm(1.0);
+        let mut p = Paragraph::new("Before ")
+            .inline_element(InlineElement::new(icon).with_baseline_offset(offset))
+            .string(" after");
+
+        let area = r.first_page().first_layer().area();
+        let result = p.render(&context, area, style).expect("render");
+        assert!(!result.has_more);
+
+        let inline = &p.inlines[0];
+        let height = inline.size().height;
+        assert!(height > metrics.glyph_height);
+        let below_baseline = (metrics.glyph_height - metrics.ascent).max(offset);
+        let expected =
+            metrics.line_height + (height - offset) + below_baseline - metrics.glyph_height;
+        assert!((result.size.height - expected).as_f32().abs() < 0.01);
+        let text_width = style.str_width(&context.font_cache, "Before ")
+            + style.str_width(&context.font_cache, " after");
+        assert!(
+            (result.size.width - text_width - inline.size().width)
+                .as_f32()
+                .abs()
+                < 0.01
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "images")]
+    fn test_paragraph_measures_inline_elements_per_area() {
+        let (r, context) = test_util::renderer(Size::new(210.0, 297.0));
+        let style = Style::new();
+        let metrics = style.metrics(&context.font_cache);
+
+        let pixels = image::RgbImage::from_pixel(100, 10, image::Rgb([0, 0, 255]));
+        let banner = Image::from_dynamic_image(image::DynamicImage::ImageRgb8(pixels))
+            .expect("image")
+            .resizing_page_with(0.5);
+        let mut p = Paragraph::new("one two three four five six seven eight nine ten ".repeat(4))
+            .inline_element(banner);
+
+        // Only the first line fits into the first area.
+        let mut area = r.first_page().first_layer().area();
+        area.set_width(Mm(160.0));
+        area.set_height(metrics.line_height * 1.5);
+        let result = p.render(&context, area, style).expect("render");
+        assert!(result.has_more);
+
+        // The inline element is measured again for the narrower area on the next page.
+        let mut area = r.first_page().first_layer().area();
+        area.set_width(Mm(80.0));
+        let result = p.render(&context, area, style).expect("render");
+        assert!(!result.has_more);
+        assert!((p.inlines[0].size().width - Mm(40.0)).as_f32().abs() < 0.1);
+    }
+
+    #[test]
+    fn test_figures_are_numbered_and_listed() {
+        let size = Size::new(210.0, 297.0);
+        let (r, context) = test_util::renderer(size);
+
+        let mut layout = LinearLayout::vertical()
+            .element(Figure::new(Paragraph::new("first"), "First caption"))
+            .element(Figure::new(Paragraph::new("second"), "Second caption").with_label("Fig."))
+            .element(ListOfFigures::new());
+
+        // Measuring does not register any figures
+        layout
+            .measure(&context, size, Style::new())
+            .expect("measure");
+        assert!(context.figures().is_empty());
+
+        let area = r.first_page().first_layer().area();
+        let result = layout.render(&context, area, Style::new()).expect("render");
+        assert!(!result.has_more);
+
+        let figures = context.figures();
+        assert_eq!(
+            figures,
+            vec![
+                FigureEntry {
+                    number: 1,
+                    caption: "First caption".to_owned(),
+                    page: 1,
+                },
+                FigureEntry {
+                    number: 2,
+                    caption: "Second caption".to_owned(),
+                    page: 1,
+                },
+            ]
+        );
+
+        let mut figure = Figure::new(Paragraph::new("third"), "Third caption").with_label("Fig.");
+        let area = r.first_page().first_layer().area();
+        figure.render(&context, area, Style::new()).expect("render");
+        let caption = figure.caption_paragraph.expect("caption");
+        assert_eq!(caption.text_content(), "Fig. 3: Third caption");
+
+        // A list before the figures of a new rendering process is empty
+        context.reset_registries();
+        let area = r.first_page().first_layer().area();
+        let result = ListOfFigures::new()
+            .render(&context, area, Style::new())
+            .expect("render");
+        assert_eq!(result.size.height, Mm(0.0));
+    }
+
+    #[test]
+    fn test_equations_are_numbered_and_referenced() {
+        let size = Size::new(210.0, 297.0);
+        let (r, context) = test_util::renderer(size);
+
+        assert_eq!(context.register_equation(None).to_string(), "1");
+        let first = context.register_equation(Some("eq:first".to_owned()));
+        assert_eq!(first.to_string(), "2");
+
+        // Measuring does not change the section
+        let mut layout = LinearLayout::vertical().element(EquationSection::new(3));
+        layout
+            .measure(&context, size, Style::new())
+            .expect("measure");
+        assert_eq!(context.register_equation(None).to_string(), "3");
+
+        let area = r.first_page().first_layer().area();
+        layout.render(&context, area, Style::new()).expect("render");
+        assert_eq!(context.register_equation(None).to_string(), "3.1");
+        let second = context.register_equation(Some("eq:second".to_owned()));
+        assert_eq!(
+            second,
+            EquationEntry {
+                section: Some(3),
+                number: 2,
+                label: Some("eq:second".to_owned()),
+                page: 1,
+            }
+        );
+        assert_eq!(context.equations().len(), 5);
+        assert_eq!(context.equation("eq:first"), Some(first));
+        assert!(context.take_warnings().is_empty());
+
+        context.register_equation(Some("eq:second".to_owned()));
+        assert_eq!(context.equation("eq:second"), Some(second));
+        assert_eq!(context.take_warnings().len(), 1);
+
+        let style = Style::new();
+        let mut reference = EquationReference::new("eq:second");
+        let area = r.first_page().first_layer().area();
+        let result = reference.render(&context, area, style).expect("render");
+        assert_eq!(
+            result.size.width,
+            style.str_width(&context.font_cache, "(3.2)")
+        );
+        assert!(context.take_warnings().is_empty());
+
+        let mut reference = EquationReference::new("eq:missing");
+        let area = r.first_page().first_layer().area();
+        let result = reference.render(&context, area, style).expect("render");
+        assert_eq!(
+            result.size.width,
+            style.str_width(&context.font_cache, "(??)")
+        );
+        assert_eq!(context.take_warnings().len(), 1);
+    }
 }
 
 /// A single line of formatted text.}
@@ -876,11 +1365,41 @@ impl Element for Text {
     }
 }
 
+/// The strategy used by a [`Paragraph`][] for words that are wider than a complete line.
+///
+/// Except for [`WordOverflow::Error`][], all strategies report a warning to the render
+/// [`Context`][] (see [`Context::warn`][]) and continue with the rendering process.
+///
+/// [`Paragraph`]: struct.Paragraph.html
+/// [`WordOverflow::Error`]: #variant.Error
+/// [`Context`]: ../struct.Context.html
+/// [`Context::warn`]: ../struct.Context.html#method.warn
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum WordOverflow {
+    /// Abort the rendering process with a [`PageSizeExceeded`][] error.
+    ///
+    /// [`PageSizeExceeded`]: ../error/enum.ErrorKind.html#variant.PageSizeExceeded
+    Error,
+    /// Break the word at the last character boundary that fits into the line and continue on the
+    /// next line.
+    #[default]
+    Break,
+    /// Reduce the font size of the word until it fits into the line.  If the word does not even
+    /// fit with the smallest font size, it is broken like with [`WordOverflow::Break`][].
+    ///
+    /// [`WordOverflow::Break`]: #variant.Break
+    Shrink,
+    /// Truncate the word and append an ellipsis (`…`).
+    Clip,
+}
+
 /// A multi-line wrapped paragraph of formatted text.
 ///
 /// If the text of this paragraph is longer than the page width, the paragraph is wrapped at word
 /// borders (and additionally at string borders if it contains multiple strings).  If a word in the
-/// paragraph is longer than the page width, the text is truncated.
+/// paragraph is longer than the page width, it is handled according to the [`WordOverflow`][]
+/// strategy set with [`set_word_overflow`][] (by default, the word is broken at a character
+/// boundary).
 ///
 /// Use the [`push`][], [`string`][], [`push_styled`][] and [`string_styled`][] methods to add
 /// strings to this paragraph.  Besides the styling of the text (see [`Style`][]), you can also set
@@ -888,6 +1407,15 @@ impl Element for Text {
 ///
 /// The line height and spacing are calculated based on the style of each string.
 ///
+/// Images, formulas and other elements can be placed within the text with [`push_inline`][] and
+/// [`inline_element`][], see [`InlineElement`][].
+///
+/// If the paragraph has to be split across pages, you can use [`set_orphans`][] and
+/// [`set_widows`][] to control how many lines of the paragraph have to stay together at the end
+/// of the page and at the start of the next page.  If the paragraph has already been moved to
+/// the next page because of these limits, they are ignored on that page.  They are also ignored
+/// by [`Document::render`][] if nothing else has been rendered on the current page.
+///
 /// # Examples
 ///
 /// With setters:
@@ -917,12 +1445,27 @@ impl Element for Text {
 /// [`push_styled`]: #method.push_styled
 /// [`string`]: #method.string
 /// [`string_styled`]: #method.string_styled
+/// [`WordOverflow`]: enum.WordOverflow.html
+/// [`set_word_overflow`]: #method.set_word_overflow
+/// [`set_orphans`]: #method.set_orphans
+/// [`set_widows`]: #method.set_widows
+/// [`Document::render`]: ../struct.Document.html#method.render
+/// [`push_inline`]: #method.push_inline
+/// [`inline_element`]: #method.inline_element
+/// [`InlineElement`]: struct.InlineElement.html
 #[derive(Clone, Debug, Default)]
 pub struct Paragraph {
-    text: Vec<StyledString>,
-    words: collections::VecDeque<StyledString>,
+    text: Vec<wrap::Segment<StyledString>>,
+    words: collections::VecDeque<wrap::Segment<StyledString>>,
     style_applied: bool,
     alignment: Alignment,
+    word_overflow: WordOverflow,
+    orphans: usize,
+    widows: usize,
+    // set if the paragraph was moved to the next page without rendering any lines
+    deferred: bool,
+    // referenced by the inline segments in text and words
+    inlines: Vec<InlineElement>,
 }
 
 impl Paragraph {
@@ -937,7 +1480,7 @@ impl Paragraph {
     /// ```
     pub fn new(text: impl Into<StyledString>) -> Paragraph {
         Paragraph {
-            text: vec![text.into()],
+            text: vec![wrap::Segment::Text(text.into())],
             ..Default::default()
         }
     }
@@ -945,7 +1488,7 @@ impl Paragraph {
     /// Returns a simple concatenation of the paragraph strings (useful for instrumentation).
     pub fn text_content(&self) -> String {
         let mut s = String::new();
-        for t in &self.text {
+        for t in self.text.iter().filter_map(wrap::Segment::text) {
             s.push_str(&t.s);
         }
         s
@@ -962,9 +1505,67 @@ impl Paragraph {
         self
     }
 
+    /// Sets the strategy for words that are wider than a complete line.
+    pub fn set_word_overflow(&mut self, word_overflow: WordOverflow) {
+        self.word_overflow = word_overflow;
+    }
+
+    /// Sets the strategy for words that are wider than a complete line and returns the paragraph.
+    ///
+    /// # Example
+    /// ```
+    /// use genpdfi_extended::elements::{Paragraph, WordOverflow};
+    /// let p = Paragraph::new("https://example.com/a/very/long/url/that/does/not/fit")
+    ///     .with_word_overflow(WordOverflow::Clip);
+    /// ```
+    pub fn with_word_overflow(mut self, word_overflow: WordOverflow) -> Self {
+        self.set_word_overflow(word_overflow);
+        self
+    }
+
+    /// Sets the minimum number of lines at the start of this paragraph that have to be rendered
+    /// on the same page (orphan control).
+    ///
+    /// If less lines of the paragraph fit on the current page, the complete paragraph is moved
+    /// to the next page.  The default value is one line.
+    pub fn set_orphans(&mut self, lines: usize) {
+        self.orphans = lines;
+    }
+
+    /// Sets the minimum number of lines at the start of this paragraph that have to be rendered
+    /// on the same page and returns the paragraph.
+    pub fn with_orphans(mut self, lines: usize) -> Self {
+        self.set_orphans(lines);
+        self
+    }
+
+    /// Sets the minimum number of lines at the end of this paragraph that have to be rendered on
+    /// the same page (widow control).
+    ///
+    /// If the paragraph is split so that less lines would be moved to the next page, additional
+    /// lines are moved to the next page.  The default value is one line.
+    pub fn set_widows(&mut self, lines: usize) {
+        self.widows = lines;
+    }
+
+    /// Sets the minimum number of lines at the end of this paragraph that have to be rendered on
+    /// the same page and returns the paragraph.
+    ///
+    /// # Example
+    /// ```
+    /// use genpdfi_extended::elements::Paragraph;
+    /// let p = Paragraph::new("A long paragraph")
+    ///     .with_orphans(2)
+    ///     .with_widows(2);
+    /// ```
+    pub fn with_widows(mut self, lines: usize) -> Self {
+        self.set_widows(lines);
+        self
+    }
+
     /// Adds a string to the end of this paragraph.
     pub fn push(&mut self, s: impl Into<StyledString>) {
-        self.text.push(s.into());
+        self.text.push(wrap::Segment::Text(s.into()));
     }
 
     /// Adds a string to the end of this paragraph and returns the paragraph.
@@ -975,7 +1576,8 @@ impl Paragraph {
 
     /// Adds a string with the given style to the end of this paragraph.
     pub fn push_styled(&mut self, s: impl Into<String>, style: impl Into<Style>) {
-        self.text.push(StyledString::new(s, style, None))
+        self.text
+            .push(wrap::Segment::Text(StyledString::new(s, style, None)))
     }
 
     /// Adds a string with the given style to the end of this paragraph and returns the paragraph.
@@ -996,7 +1598,7 @@ impl Paragraph {
         style: impl Into<Style>,
     ) -> &mut Self {
         let styled = StyledString::new(text, style, Some(url.into()));
-        self.text.push(styled);
+        self.text.push(wrap::Segment::Text(styled));
         self
     }
 
@@ -1006,6 +1608,23 @@ impl Paragraph {
         self
     }
 
+    /// Adds an inline element, for example an icon or a formula, to the end of this paragraph.
+    ///
+    /// The element is wrapped like a word, so add spaces to the surrounding strings to separate
+    /// it from the text.  See [`InlineElement`][] for the vertical alignment.
+    ///
+    /// [`InlineElement`]: struct.InlineElement.html
+    pub fn push_inline(&mut self, element: impl Into<InlineElement>) {
+        self.text.push(wrap::Segment::Inline(self.inlines.len()));
+        self.inlines.push(element.into());
+    }
+
+    /// Adds an inline element to the end of this paragraph and returns the paragraph.
+    pub fn inline_element(mut self, element: impl Into<InlineElement>) -> Self {
+        self.push_inline(element);
+        self
+    }
+
     fn get_offset(&self, width: Mm, max_width: Mm) -> Mm {
         match self.alignment {
             Alignment::Left => Mm::default(),
@@ -1017,11 +1636,55 @@ impl Paragraph {
     fn apply_style(&mut self, style: Style) {
         if !self.style_applied {
             for s in &mut self.text {
-                s.style = style.and(s.style);
+                if let wrap::Segment::Text(s) = s {
+                    s.style = style.and(s.style);
+                }
             }
             self.style_applied = true;
         }
     }
+
+    /// Returns the inline element if the given segment is an inline element.
+    fn inline<S>(&self, s: &wrap::Segment<S>) -> Option<&InlineElement> {
+        match s {
+            wrap::Segment::Text(_) => None,
+            wrap::Segment::Inline(idx) => self.inlines.get(*idx),
+        }
+    }
+
+    fn segment_width(&self, context: &Context, s: &wrap::Segment<style::StyledCow<'_>>) -> Mm {
+        match s {
+            wrap::Segment::Text(s) => s.width(&context.font_cache),
+            wrap::Segment::Inline(_) => self.inline(s).map(|i| i.size().width).unwrap_or_default(),
+        }
+    }
+
+    /// Calculates the metrics of the given line.  Inline elements that are higher or that extend
+    /// further below the baseline than the text increase the line height.  They are at least as
+    /// high as the text of the paragraph style.
+    fn line_metrics(
+        &self,
+        context: &Context,
+        line: &[wrap::Segment<style::StyledCow<'_>>],
+        style: Style,
+    ) -> fonts::Metrics {
+        let mut metrics = line
+            .iter()
+            .map(|s| s.text().map(|s| s.style).unwrap_or(style))
+            .map(|style| style.metrics(&context.font_cache))
+            .fold(fonts::Metrics::default(), |max, m| max.max(&m));
+        let mut ascent = metrics.ascent;
+        let mut below_baseline = metrics.glyph_height - metrics.ascent;
+        for inline in line.iter().filter_map(|s| self.inline(s)) {
+            ascent = ascent.max(inline.size().height - inline.baseline_offset);
+            below_baseline = below_baseline.max(inline.baseline_offset);
+        }
+        let extra = ascent + below_baseline - metrics.glyph_height;
+        metrics.ascent = ascent;
+        metrics.glyph_height += extra;
+        metrics.line_height += extra;
+        metrics
+    }
 }
 
 impl Element for Paragraph {
@@ -1035,6 +1698,7 @@ impl Element for Paragraph {
 
         self.apply_style(style);
 
+        let is_continued = !self.words.is_empty();
         if self.words.is_empty() {
             if self.text.is_empty() {
                 return Ok(result);
@@ -1042,55 +1706,135 @@ impl Element for Paragraph {
             self.words = wrap::Words::new(mem::take(&mut self.text)).collect();
         }
 
-        let words = self
-            .words
-            .iter()
-            .map(|s| style::StyledStr::new(&s.s, s.style, s.link.as_deref()));
-        let mut rendered_len = 0;
-        let mut wrapper = wrap::Wrapper::new(words, context, area.size().width);
-        for (line, delta) in &mut wrapper {
-            let width = line.iter().map(|s| s.width(&context.font_cache)).sum();
-            let metrics = line
-                .iter()
-                .map(|s| s.style.metrics(&context.font_cache))
-                .fold(fonts::Metrics::default(), |max, m| max.max(&m));
-            let position = Position::new(self.get_offset(width, area.size().width), 0);
+        // The size of inline elements can depend on the available width, so we measure them again
+        // for every area.
+        let available = Size::new(area.size().width, INLINE_MEASURE_HEIGHT);
+        for inline in &mut self.inlines {
+            inline.size = Some(inline.element.measure(context, available, style)?.size);
+        }
+        let inline_widths: Vec<_> = self.inlines.iter().map(|i| i.size().width).collect();
+
+        let words = self.words.iter().map(|s| match s {
+            wrap::Segment::Text(s) => {
+                wrap::Segment::Text(style::StyledStr::new(&s.s, s.style, s.link.as_deref()))
+            }
+            wrap::Segment::Inline(idx) => wrap::Segment::Inline(*idx),
+        });
+        let mut wrapper = wrap::Wrapper::new(words, context, area.size().width, self.word_overflow)
+            .with_inline_widths(&inline_widths);
+
+        // Wrap the lines before rendering them so that we know how many lines fit into the area
+        // and how many lines are left for the next page.  We also record the number of warnings
+        // before each line so that we can discard the warnings for lines that are not rendered,
+        // as they are wrapped again on the next page.
+        let mut lines = Vec::new();
+        let mut warnings = vec![context.warning_count()];
+        let mut height = Mm(0.0);
+        let mut fitting = None;
+        for (line, len) in &mut wrapper {
+            let metrics = self.line_metrics(context, &line, style);
+            if fitting.is_none() && height + metrics.glyph_height > area.size().height {
+                fitting = Some(lines.len());
+            }
+            height += metrics.line_height;
+            lines.push((line, len, metrics));
+            warnings.push(context.warning_count());
+            if let Some(fitting) = fitting {
+                if lines.len() >= fitting + self.widows.max(1) {
+                    break;
+                }
+            }
+        }
+
+        if wrapper.has_overflowed() {
+            return Err(Error::new(
+                "Page overflowed while trying to wrap a string",
+                ErrorKind::PageSizeExceeded,
+            ));
+        }
+
+        let mut count = lines.len();
+        if let Some(fitting) = fitting {
+            // The paragraph has to be split.  Move more lines to the next page if there would be
+            // less than `widows` lines on the next page, and move the complete paragraph if there
+            // would be less than `orphans` lines at the start of the paragraph on this page.
+            // If the paragraph has already been moved to this page, we ignore these limits so
+            // that we don't move it again.
+            count = fitting;
+            if !self.deferred {
+                let remaining = lines.len() - fitting;
+                if remaining < self.widows {
+                    count = count.saturating_sub(self.widows - remaining);
+                }
+                if !is_continued && count < self.orphans {
+                    count = 0;
+                }
+            }
+            self.deferred = count == 0;
+            result.has_more = true;
+            context.truncate_warnings(warnings[count]);
+        }
 
-            if let Some(mut section) = area.text_section(&context.font_cache, position, metrics) {
-                for s in line {
+        let mut rendered_len = 0;
+        for (line, len, metrics) in lines.into_iter().take(count) {
+            if metrics.glyph_height > area.size().height {
+                result.has_more = true;
+                break;
+            }
+            let width = line.iter().map(|s| self.segment_width(context, s)).sum();
+            let mut x = self.get_offset(width, area.size().width);
+
+            // Inline elements cannot be drawn within a text section, so we start a new text
+            // section for each run of strings.
+            let mut section = None;
+            for s in &line {
+                let s = match s {
+                    wrap::Segment::Text(s) => s,
+                    wrap::Segment::Inline(idx) => {
+                        section = None;
+                        let inline = &mut self.inlines[*idx];
+                        let size = inline.size();
+                        let mut inline_area = area.clone();
+                        inline_area.add_offset(Position::new(
+                            x,
+                            metrics.ascent - size.height + inline.baseline_offset,
+                        ));
+                        inline_area.set_width(size.width);
+                        inline.element.render(context, inline_area, style)?;
+                        x += size.width;
+                        continue;
+                    }
+                };
+                if section.is_none() {
+                    section = area.text_section(&context.font_cache, Position::new(x, 0), metrics);
+                }
+                if let Some(section) = &mut section {
                     if let Some(url) = &s.link {
                         section.add_link(&s.s, url.clone(), s.style)?;
                     } else {
                         section.print_str(&s.s, s.style)?;
                     }
-                    rendered_len += s.s.len();
                 }
-                rendered_len -= delta;
-            } else {
-                result.has_more = true;
-                break;
+                x += s.width(&context.font_cache);
             }
+            rendered_len += len;
             result.size = result
                 .size
                 .stack_vertical(Size::new(width, metrics.line_height));
             area.add_offset(Position::new(0, metrics.line_height));
         }
 
-        if wrapper.has_overflowed() {
-            return Err(Error::new(
-                "Page overflowed while trying to wrap a string",
-                ErrorKind::PageSizeExceeded,
-            ));
-        }
-
         // Remove the rendered data from self.words so that we don't render it again on the next
         // call to render.
         while rendered_len > 0 && !self.words.is_empty() {
-            if self.words[0].s.len() <= rendered_len {
-                rendered_len -= self.words[0].s.len();
+            let len = self.words[0].len();
+            if len <= rendered_len {
+                rendered_len -= len;
                 self.words.pop_front();
             } else {
-                self.words[0].s.replace_range(..rendered_len, "");
+                if let wrap::Segment::Text(s) = &mut self.words[0] {
+                    s.s.replace_range(..rendered_len, "");
+                }
                 rendered_len = 0;
             }
         }
@@ -1102,7 +1846,7 @@ impl Element for Paragraph {
 impl From<Vec<StyledString>> for Paragraph {
     fn from(text: Vec<StyledString>) -> Paragraph {
         Paragraph {
-            text,
+            text: text.into_iter().map(wrap::Segment::Text).collect(),
             ..Default::default()
         }
     }
@@ -1124,6 +1868,97 @@ impl<T: Into<StyledString>> iter::FromIterator<T> for Paragraph {
     }
 }
 
+/// The height of the area that is used to measure inline elements.
+const INLINE_MEASURE_HEIGHT: Mm = Mm(1000.0);
+
+/// An element that is placed within the text of a [`Paragraph`][], for example an icon or an
+/// inline formula.
+///
+/// Inline elements are wrapped like words but are never split.  By default, the bottom of the
+/// element is placed on the baseline of the text.  Use [`set_baseline_offset`][] to move the
+/// element below the baseline, for example for formulas with subscripts.  If the element is higher
+/// than the text, the line height is increased accordingly.
+///
+/// Images and formulas can be converted into inline elements directly, all other elements can be
+/// wrapped with [`InlineElement::new`][].
+///
+/// # Example
+///
+/// ```
+/// # #[cfg(feature = "images")]
+/// # {
+/// use genpdfi_extended::elements;
+/// let svg = r#"<svg width="4mm" height="4mm" xmlns="http://www.w3.org/2000/svg">
+///   <circle cx="2" cy="2" r="2" fill="green"/>
+/// </svg>"#;
+/// let icon = elements::Image::from_svg_string(svg).expect("parse SVG");
+/// let p = elements::Paragraph::new("Status: ")
+///     .inline_element(elements::InlineElement::new(icon).with_baseline_offset(0.5))
+///     .string(" online");
+/// # }
+/// ```
+///
+/// [`Paragraph`]: struct.Paragraph.html
+/// [`set_baseline_offset`]: #method.set_baseline_offset
+/// [`InlineElement::new`]: #method.new
+#[derive(Clone)]
+pub struct InlineElement {
+    element: Box<dyn Element>,
+    baseline_offset: Mm,
+    // measured for the current area whenever the paragraph is rendered
+    size: Option<Size>,
+}
+
+impl InlineElement {
+    /// Creates a new inline element that wraps the given element.
+    pub fn new<E: Element + 'static>(element: E) -> InlineElement {
+        InlineElement {
+            element: Box::new(element),
+            baseline_offset: Mm(0.0),
+            size: None,
+        }
+    }
+
+    /// Sets the distance between the bottom of the element and the baseline of the text.
+    pub fn set_baseline_offset(&mut self, offset: impl Into<Mm>) {
+        self.baseline_offset = offset.into();
+    }
+
+    /// Sets the distance between the bottom of the element and the baseline of the text and
+    /// returns the inline element.
+    pub fn with_baseline_offset(mut self, offset: impl Into<Mm>) -> Self {
+        self.set_baseline_offset(offset);
+        self
+    }
+
+    fn size(&self) -> Size {
+        self.size.unwrap_or_default()
+    }
+}
+
+impl fmt::Debug for InlineElement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("InlineElement")
+            .field("baseline_offset", &self.baseline_offset)
+            .field("size", &self.size)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(feature = "images")]
+impl From<Image> for InlineElement {
+    fn from(image: Image) -> InlineElement {
+        InlineElement::new(image)
+    }
+}
+
+#[cfg(feature = "latex")]
+impl From<Latex> for InlineElement {
+    fn from(latex: Latex) -> InlineElement {
+        InlineElement::new(latex.inline())
+    }
+}
+
 /// A line break.
 ///
 /// This element inserts a given number of empty lines.
@@ -1216,6 +2051,7 @@ impl Element for PageBreak {
                 size: Size::new(1, 0),
                 has_more: true,
                 svg: None,
+                warnings: Vec::new(),
             })
         }
     }
@@ -1274,7 +2110,26 @@ impl<E: Element + Clone + 'static> Element for PaddedElement<E> {
         result.size.height += self.padding.top + self.padding.bottom;
         Ok(result)
     }
-}
+
+    fn measure(&self, context: &Context, size: Size, style: Style) -> Result<RenderResult, Error> {
+        let size = Size::new(
+            size.width - self.padding.left - self.padding.right,
+            size.height - self.padding.top,
+        );
+        let mut result = self.element.measure(context, size, style)?;
+        result.size.width += self.padding.left + self.padding.right;
+        result.size.height += self.padding.top + self.padding.bottom;
+        Ok(result)
+    }
+
+    fn is_kept_together(&self) -> bool {
+        self.element.is_kept_together()
+    }
+
+    fn is_kept_with_next(&self) -> bool {
+        self.element.is_kept_with_next()
+    }
+}
 
 /// Adds a default style to the wrapped element and its children.
 ///
@@ -1323,6 +2178,493 @@ impl<E: Element + Clone + 'static> Element for StyledElement<E> {
         style.merge(self.style);
         self.element.render(context, area, style)
     }
+
+    fn measure(
+        &self,
+        context: &Context,
+        size: Size,
+        mut style: Style,
+    ) -> Result<RenderResult, Error> {
+        style.merge(self.style);
+        self.element.measure(context, size, style)
+    }
+
+    fn is_kept_together(&self) -> bool {
+        self.element.is_kept_together()
+    }
+
+    fn is_kept_with_next(&self) -> bool {
+        self.element.is_kept_with_next()
+    }
+}
+
+/// Prevents the wrapped element from being split across pages.
+///
+/// If the wrapped element is part of a [`LinearLayout`][] and does not fit completely into the
+/// remaining space of the current page, it is moved to the next page.  If it does not fit on an
+/// empty page either, it is split as usual.
+///
+/// # Examples
+///
+/// Direct usage:
+/// ```
+/// use genpdfi_extended::elements;
+/// let p = elements::KeepTogether::new(elements::Paragraph::new("text"));
+/// ```
+///
+/// Using [`Element::kept_together`][]:
+/// ```
+/// use genpdfi_extended::{elements, Element as _};
+/// let p = elements::Paragraph::new("text").kept_together();
+/// ```
+///
+/// [`LinearLayout`]: struct.LinearLayout.html
+/// [`Element::kept_together`]: ../trait.Element.html#method.kept_together
+#[derive(Clone, Debug, Default)]
+pub struct KeepTogether<E: Element> {
+    element: E,
+}
+
+impl<E: Element> KeepTogether<E> {
+    /// Creates a new element that keeps the given element together.
+    pub fn new(element: E) -> KeepTogether<E> {
+        KeepTogether { element }
+    }
+}
+
+impl<E: Element + Clone + 'static> Element for KeepTogether<E> {
+    fn render(
+        &mut self,
+        context: &Context,
+        area: render::Area<'_>,
+        style: Style,
+    ) -> Result<RenderResult, Error> {
+        self.element.render(context, area, style)
+    }
+
+    fn measure(&self, context: &Context, size: Size, style: Style) -> Result<RenderResult, Error> {
+        self.element.measure(context, size, style)
+    }
+
+    fn is_kept_together(&self) -> bool {
+        true
+    }
+
+    fn is_kept_with_next(&self) -> bool {
+        self.element.is_kept_with_next()
+    }
+}
+
+/// Keeps the wrapped element on the same page as the start of the following element.
+///
+/// If the wrapped element is part of a [`LinearLayout`][] and the following element cannot be
+/// started on the current page, both elements are moved to the next page.  This is typically used
+/// for headings.
+///
+/// # Examples
+///
+/// Direct usage:
+/// ```
+/// use genpdfi_extended::elements;
+/// let heading = elements::KeepWithNext::new(elements::Paragraph::new("Heading"));
+/// ```
+///
+/// Using [`Element::kept_with_next`][]:
+/// ```
+/// use genpdfi_extended::{elements, Element as _};
+/// let heading = elements::Paragraph::new("Heading").kept_with_next();
+/// ```
+///
+/// [`LinearLayout`]: struct.LinearLayout.html
+/// [`Element::kept_with_next`]: ../trait.Element.html#method.kept_with_next
+#[derive(Clone, Debug, Default)]
+pub struct KeepWithNext<E: Element> {
+    element: E,
+}
+
+impl<E: Element> KeepWithNext<E> {
+    /// Creates a new element that keeps the given element with the following element.
+    pub fn new(element: E) -> KeepWithNext<E> {
+        KeepWithNext { element }
+    }
+}
+
+impl<E: Element + Clone + 'static> Element for KeepWithNext<E> {
+    fn render(
+        &mut self,
+        context: &Context,
+        area: render::Area<'_>,
+        style: Style,
+    ) -> Result<RenderResult, Error> {
+        self.element.render(context, area, style)
+    }
+
+    fn measure(&self, context: &Context, size: Size, style: Style) -> Result<RenderResult, Error> {
+        self.element.measure(context, size, style)
+    }
+
+    fn is_kept_together(&self) -> bool {
+        self.element.is_kept_together()
+    }
+
+    fn is_kept_with_next(&self) -> bool {
+        true
+    }
+}
+
+/// Adds a numbered caption below the wrapped element, for example an image, a formula or a
+/// diagram.
+///
+/// When the wrapped element is placed on a page, the figure is assigned the next figure number
+/// with [`Context::register_figure`][] and the caption is printed below the element, prefixed with
+/// the label and the number, e.g. “Figure 1: ”.  Figures are kept together, so a
+/// [`LinearLayout`][] moves the figure to the next page instead of separating the caption from the
+/// element.  The registered figures can be listed with [`ListOfFigures`][].
+///
+/// # Examples
+///
+/// ```
+/// use genpdfi_extended::{elements, Alignment};
+/// let figure = elements::Figure::new(elements::Paragraph::new("Content"), "A caption")
+///     .with_label("Fig.")
+///     .with_caption_alignment(Alignment::Left);
+/// ```
+///
+/// [`Context::register_figure`]: ../struct.Context.html#method.register_figure
+/// [`LinearLayout`]: struct.LinearLayout.html
+/// [`ListOfFigures`]: struct.ListOfFigures.html
+#[derive(Clone, Debug)]
+pub struct Figure<E: Element> {
+    element: E,
+    caption: StyledString,
+    label: String,
+    alignment: Alignment,
+    // set once the figure number has been assigned
+    caption_paragraph: Option<Paragraph>,
+    element_done: bool,
+}
+
+impl<E: Element> Figure<E> {
+    /// Creates a new figure with the given element and caption.
+    pub fn new(element: E, caption: impl Into<StyledString>) -> Figure<E> {
+        Figure {
+            element,
+            caption: caption.into(),
+            label: "Figure".to_owned(),
+            alignment: Alignment::Center,
+            caption_paragraph: None,
+            element_done: false,
+        }
+    }
+
+    /// Sets the label that is printed before the figure number (default: `Figure`).
+    pub fn set_label(&mut self, label: impl Into<String>) {
+        self.label = label.into();
+    }
+
+    /// Sets the label that is printed before the figure number and returns the figure.
+    pub fn with_label(mut self, label: impl Into<String>) -> Self {
+        self.set_label(label);
+        self
+    }
+
+    /// Sets the alignment of the caption (default: centered).
+    pub fn set_caption_alignment(&mut self, alignment: Alignment) {
+        self.alignment = alignment;
+    }
+
+    /// Sets the alignment of the caption and returns the figure.
+    pub fn with_caption_alignment(mut self, alignment: Alignment) -> Self {
+        self.set_caption_alignment(alignment);
+        self
+    }
+
+    /// Creates the caption paragraph for the given figure number.
+    fn caption_paragraph(&self, number: usize) -> Paragraph {
+        let prefix = format!("{} {}: ", self.label, number);
+        let mut paragraph = Paragraph::new(StyledString::new(prefix, self.caption.style, None));
+        paragraph.push(self.caption.clone());
+        paragraph.set_alignment(self.alignment);
+        paragraph
+    }
+}
+
+impl<E: Element + Clone + 'static> Element for Figure<E> {
+    fn render(
+        &mut self,
+        context: &Context,
+        mut area: render::Area<'_>,
+        style: Style,
+    ) -> Result<RenderResult, Error> {
+        let mut result = RenderResult::default();
+        if !self.element_done {
+            let element_result = self.element.render(context, area.clone(), style)?;
+            result.size = element_result.size;
+            let placed = element_result.size.height > Mm::from(0) || !element_result.has_more;
+            if self.caption_paragraph.is_none() && placed {
+                let number = context.register_figure(self.caption.s.clone());
+                self.caption_paragraph = Some(self.caption_paragraph(number));
+            }
+            if element_result.has_more {
+                result.has_more = true;
+                return Ok(result);
+            }
+            self.element_done = true;
+            area.add_offset(Position::new(0, element_result.size.height));
+        }
+
+        if let Some(paragraph) = &mut self.caption_paragraph {
+            let caption_result = paragraph.render(context, area, style)?;
+            result.size = result.size.stack_vertical(caption_result.size);
+            result.has_more = caption_result.has_more;
+        }
+        Ok(result)
+    }
+
+    fn measure(
+        &self,
+        context: &Context,
+        mut size: Size,
+        style: Style,
+    ) -> Result<RenderResult, Error> {
+        let mut result = RenderResult::default();
+        let mut caption_paragraph = self.caption_paragraph.clone();
+        if !self.element_done {
+            let element_result = self.element.measure(context, size, style)?;
+            result.size = element_result.size;
+            let placed = element_result.size.height > Mm::from(0) || !element_result.has_more;
+            if caption_paragraph.is_none() && placed {
+                // The figure would be registered with the next number
+                caption_paragraph = Some(self.caption_paragraph(context.figures().len() + 1));
+            }
+            if element_result.has_more {
+                result.has_more = true;
+                return Ok(result);
+            }
+            size.height -= element_result.size.height;
+        }
+
+        if let Some(paragraph) = &caption_paragraph {
+            let caption_result = paragraph.measure(context, size, style)?;
+            result.size = result.size.stack_vertical(caption_result.size);
+            result.has_more = caption_result.has_more;
+        }
+        Ok(result)
+    }
+
+    fn is_kept_together(&self) -> bool {
+        true
+    }
+
+    fn is_kept_with_next(&self) -> bool {
+        self.element.is_kept_with_next()
+    }
+}
+
+/// An entry of the figure registry, see [`Context::figures`][].
+///
+/// [`Context::figures`]: ../struct.Context.html#method.figures
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FigureEntry {
+    /// The number of the figure, starting at 1.
+    pub number: usize,
+    /// The caption text of the figure.
+    pub caption: String,
+    /// The number of the page the figure has been placed on, starting at 1.
+    pub page: usize,
+}
+
+/// Lists the figures of the document with their captions and page numbers.
+///
+/// The list is generated from the figures that have been registered in the [`Context`][] when it
+/// is rendered, so it has to be placed after the [`Figure`][]s, for example in an appendix.  The
+/// document is rendered in a single pass, so a list that is placed before its figures is empty.
+///
+/// # Examples
+///
+/// ```
+/// use genpdfi_extended::elements;
+/// let mut layout = elements::LinearLayout::vertical();
+/// layout.push(elements::Figure::new(elements::Paragraph::new("Content"), "A caption"));
+/// layout.push(elements::ListOfFigures::new());
+/// ```
+///
+/// [`Context`]: ../struct.Context.html
+/// [`Figure`]: struct.Figure.html
+#[derive(Clone, Default)]
+pub struct ListOfFigures {
+    label: Option<String>,
+    table: Option<TableLayout>,
+}
+
+impl ListOfFigures {
+    /// Creates a new list of figures.
+    pub fn new() -> ListOfFigures {
+        ListOfFigures::default()
+    }
+
+    /// Sets the label that is printed before the figure numbers (default: `Figure`).
+    pub fn set_label(&mut self, label: impl Into<String>) {
+        self.label = Some(label.into());
+    }
+
+    /// Sets the label that is printed before the figure numbers and returns the list.
+    pub fn with_label(mut self, label: impl Into<String>) -> Self {
+        self.set_label(label);
+        self
+    }
+}
+
+impl Element for ListOfFigures {
+    fn render(
+        &mut self,
+        context: &Context,
+        area: render::Area<'_>,
+        style: Style,
+    ) -> Result<RenderResult, Error> {
+        if self.table.is_none() {
+            let label = self.label.as_deref().unwrap_or("Figure");
+            let mut table = TableLayout::new(vec![6, 1]);
+            for figure in context.figures() {
+                table
+                    .row()
+                    .element(Paragraph::new(format!(
+                        "{} {}: {}",
+                        label, figure.number, figure.caption
+                    )))
+                    .element(Paragraph::new(figure.page.to_string()).aligned(Alignment::Right))
+                    .push()?;
+            }
+            self.table = Some(table);
+        }
+        self.table
+            .as_mut()
+            .expect("table is initialized")
+            .render(context, area, style)
+    }
+}
+
+/// An entry of the equation registry, see [`Context::equations`][].
+///
+/// The entry is formatted as the equation number without parentheses, e.g. `3.2` for the second
+/// equation of section 3 or `2` for the second equation of a document without sections.
+///
+/// [`Context::equations`]: ../struct.Context.html#method.equations
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EquationEntry {
+    /// The section of the equation, see [`EquationSection`][].
+    ///
+    /// [`EquationSection`]: struct.EquationSection.html
+    pub section: Option<usize>,
+    /// The number of the equation within its section, starting at 1.
+    pub number: usize,
+    /// The label that can be used to reference the equation.
+    pub label: Option<String>,
+    /// The number of the page the equation has been placed on, starting at 1.
+    pub page: usize,
+}
+
+impl fmt::Display for EquationEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(section) = self.section {
+            write!(f, "{}.{}", section, self.number)
+        } else {
+            write!(f, "{}", self.number)
+        }
+    }
+}
+
+/// Starts a new section for the numbering of equations.
+///
+/// This element does not produce any output.  The numbered equations that are rendered after it
+/// are numbered per section, e.g. “(3.1)”, “(3.2)” in section 3, see
+/// [`Context::set_equation_section`][].
+///
+/// # Examples
+///
+/// ```
+/// use genpdfi_extended::elements;
+/// let mut layout = elements::LinearLayout::vertical();
+/// layout.push(elements::Paragraph::new("3 Results"));
+/// layout.push(elements::EquationSection::new(3));
+/// ```
+///
+/// [`Context::set_equation_section`]: ../struct.Context.html#method.set_equation_section
+#[derive(Clone, Copy, Debug)]
+pub struct EquationSection {
+    section: usize,
+}
+
+impl EquationSection {
+    /// Creates a new equation section with the given number.
+    pub fn new(section: usize) -> EquationSection {
+        EquationSection { section }
+    }
+}
+
+impl Element for EquationSection {
+    fn render(
+        &mut self,
+        context: &Context,
+        _area: render::Area<'_>,
+        _style: Style,
+    ) -> Result<RenderResult, Error> {
+        context.set_equation_section(Some(self.section));
+        Ok(RenderResult::default())
+    }
+}
+
+/// Prints the number of a labeled equation, e.g. “(3.2)”.
+///
+/// The reference is resolved with [`Context::equation`][] when it is rendered, so the labeled
+/// equation has to be placed before the reference.  Unresolved references are printed as “(??)”
+/// and reported as a warning.  References can be placed within the text of a [`Paragraph`][] with
+/// an [`InlineElement`][].
+///
+/// # Examples
+///
+/// ```
+/// use genpdfi_extended::elements;
+/// let p = elements::Paragraph::new("Inserting ")
+///     .inline_element(elements::InlineElement::new(
+///         elements::EquationReference::new("eq:energy"),
+///     ))
+///     .string(" yields the result.");
+/// ```
+///
+/// [`Context::equation`]: ../struct.Context.html#method.equation
+/// [`Paragraph`]: struct.Paragraph.html
+/// [`InlineElement`]: struct.InlineElement.html
+#[derive(Clone, Debug)]
+pub struct EquationReference {
+    label: String,
+}
+
+impl EquationReference {
+    /// Creates a new reference to the equation with the given label.
+    pub fn new(label: impl Into<String>) -> EquationReference {
+        EquationReference {
+            label: label.into(),
+        }
+    }
+}
+
+impl Element for EquationReference {
+    fn render(
+        &mut self,
+        context: &Context,
+        area: render::Area<'_>,
+        style: Style,
+    ) -> Result<RenderResult, Error> {
+        let text = match context.equation(&self.label) {
+            Some(equation) => format!("({})", equation),
+            None => {
+                context.warn(format!("Unresolved equation reference: {}", self.label));
+                "(??)".to_owned()
+            }
+        };
+        Text::new(text).render(context, area, style)
+    }
 }
 
 /// Adds a frame around the wrapped element.
@@ -1366,6 +2708,18 @@ impl<E: Element> FramedElement<E> {
             line_style: line_style.into(),
         }
     }
+
+    /// Returns the margins of the wrapped element within the area of this element, taking into
+    /// account the full line thickness.
+    fn element_margins(&self) -> Margins {
+        let line_thickness = self.line_style.thickness();
+        let top = if self.is_first {
+            line_thickness
+        } else {
+            Mm(0.0)
+        };
+        Margins::trbl(top, line_thickness, line_thickness, line_thickness)
+    }
 }
 
 impl<E: Element + Clone + 'static> Element for FramedElement<E> {
@@ -1383,15 +2737,9 @@ impl<E: Element + Clone + 'static> Element for FramedElement<E> {
         // Calculate the areas in which to draw the element and the frame.
         let mut element_area = area.clone();
         let mut frame_area = area.clone();
-        element_area.add_margins(Margins::trbl(
-            0,
-            line_thickness,
-            line_thickness,
-            line_thickness,
-        ));
+        element_area.add_margins(self.element_margins());
         frame_area.add_margins(Margins::trbl(0, line_offset, 0, line_offset));
         if self.is_first {
-            element_area.add_margins(Margins::trbl(line_thickness, 0, 0, 0));
             frame_area.add_margins(Margins::trbl(line_offset, 0, 0, 0));
         }
 
@@ -1432,6 +2780,110 @@ impl<E: Element + Clone + 'static> Element for FramedElement<E> {
 
         Ok(result)
     }
+
+    fn measure(&self, context: &Context, size: Size, style: Style) -> Result<RenderResult, Error> {
+        let line_thickness = self.line_style.thickness();
+        let margins = self.element_margins();
+        let element_size = Size::new(
+            size.width - margins.left - margins.right,
+            size.height - margins.top - margins.bottom,
+        );
+        let mut result = self.element.measure(context, element_size, style)?;
+        result.size.width = size.width;
+        if self.is_first {
+            result.size.height += line_thickness;
+        }
+        if !result.has_more {
+            result.size.height += line_thickness;
+        }
+        Ok(result)
+    }
+
+    fn is_kept_together(&self) -> bool {
+        self.element.is_kept_together()
+    }
+
+    fn is_kept_with_next(&self) -> bool {
+        self.element.is_kept_with_next()
+    }
+}
+
+/// The fallback of a formula or a diagram that could not be rendered: its source in a red frame,
+/// optionally below a note with the error.
+///
+/// Empty lines of the source are kept, and the source is printed with the given font family or
+/// with the built-in Courier font family.
+#[cfg(any(feature = "latex", feature = "mermaid"))]
+#[derive(Clone)]
+struct SourceFallback(FramedElement<PaddedElement<LinearLayout>>);
+
+#[cfg(any(feature = "latex", feature = "mermaid"))]
+impl SourceFallback {
+    fn new(
+        context: &Context,
+        source: &str,
+        note: Option<String>,
+        font_family: Option<fonts::FontFamily<fonts::Font>>,
+    ) -> SourceFallback {
+        let red = style::Color::Rgb(255, 0, 0);
+        let mut source_style = Style::new();
+        source_style.set_font_override(
+            font_family.unwrap_or_else(|| context.font_cache.source_font_family()),
+        );
+        let mut layout = LinearLayout::vertical();
+        if let Some(note) = note {
+            layout.push(Paragraph::new(StyledString::new(
+                note,
+                Style::new().with_color(red).italic(),
+                None,
+            )));
+        }
+        for line in source.lines() {
+            if line.trim().is_empty() {
+                layout.push(Break::new(1.0));
+            } else {
+                layout.push(Paragraph::new(StyledString::new(
+                    line.to_owned(),
+                    source_style,
+                    None,
+                )));
+            }
+        }
+        SourceFallback(FramedElement::with_line_style(
+            PaddedElement::new(layout, Margins::all(1)),
+            LineStyle::new().with_color(red),
+        ))
+    }
+}
+
+#[cfg(any(feature = "latex", feature = "mermaid"))]
+impl Element for SourceFallback {
+    fn render(
+        &mut self,
+        context: &Context,
+        area: render::Area<'_>,
+        style: Style,
+    ) -> Result<RenderResult, Error> {
+        self.0.render(context, area, style)
+    }
+
+    fn measure(&self, context: &Context, size: Size, style: Style) -> Result<RenderResult, Error> {
+        self.0.measure(context, size, style)
+    }
+}
+
+#[cfg(any(feature = "latex", feature = "mermaid"))]
+impl fmt::Debug for SourceFallback {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("SourceFallback")
+    }
+}
+
+/// Locks the given mutex, ignoring poisoning as the guarded caches and settings are never left in
+/// an inconsistent state.
+#[cfg(any(feature = "latex", feature = "mermaid"))]
+fn lock<T>(mutex: &std::sync::Mutex<T>) -> std::sync::MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|err| err.into_inner())
 }
 
 /// An unordered list of elements with bullet points.
@@ -1533,6 +2985,10 @@ impl Element for UnorderedList {
     ) -> Result<RenderResult, Error> {
         self.layout.render(context, area, style)
     }
+
+    fn measure(&self, context: &Context, size: Size, style: Style) -> Result<RenderResult, Error> {
+        self.layout.measure(context, size, style)
+    }
 }
 
 impl Default for UnorderedList {
@@ -1652,6 +3108,10 @@ impl Element for OrderedList {
     ) -> Result<RenderResult, Error> {
         self.layout.render(context, area, style)
     }
+
+    fn measure(&self, context: &Context, size: Size, style: Style) -> Result<RenderResult, Error> {
+        self.layout.measure(context, size, style)
+    }
 }
 
 impl Default for OrderedList {
@@ -1750,6 +3210,13 @@ impl<E: Element + Clone + 'static> Element for BulletPoint<E> {
         }
         Ok(result)
     }
+
+    fn measure(&self, context: &Context, size: Size, style: Style) -> Result<RenderResult, Error> {
+        let size = Size::new(size.width - self.indent, size.height);
+        let mut result = self.element.measure(context, size, style)?;
+        result.size.width += self.indent;
+        Ok(result)
+    }
 }
 
 /// A decorator for table cells.
@@ -9,10 +9,10 @@
 use std::io::Read;
 use std::path;
 
-use image::GenericImageView;
+use image::{GenericImageView, ImageDecoder};
 
 use crate::error::{Context as _, Error, ErrorKind};
-use crate::{render, style};
+use crate::{fonts, render, style};
 use crate::{Alignment, Context, Element, Mm, Position, RenderResult, Rotation, Scale, Size};
 
 /// Enum representing either a raster image or a vector SVG image.
@@ -23,8 +23,12 @@ use crate::{Alignment, Context, Element, Mm, Position, RenderResult, Rotation, S
 enum ImageSource {
     /// A raster image loaded from formats supported by the `image` crate.
     Raster(image::DynamicImage),
-    /// A vector SVG image parsed by printpdf as an ExternalXObject.
-    Svg(printpdf::ExternalXObject),
+    /// A JPEG image that is embedded without decoding it.
+    Jpeg(render::JpegImage),
+    /// Raw CMYK pixel data that is embedded as a `/DeviceCMYK` image.
+    Cmyk(render::CmykImage),
+    /// A vector SVG image parsed by printpdf as an ExternalXObject, and the digest of its source.
+    Svg(printpdf::ExternalXObject, render::ContentDigest),
 }
 
 impl ImageSource {
@@ -51,7 +55,23 @@ impl ImageSource {
                     mmpi * ((px_height as f32) / dpi_val),
                 )
             }
-            ImageSource::Svg(svg) => {
+            ImageSource::Jpeg(jpeg) => {
+                let mmpi: f32 = 25.4;
+                let dpi_val: f32 = dpi.unwrap_or(300.0);
+                Size::new(
+                    mmpi * ((jpeg.width() as f32) / dpi_val),
+                    mmpi * ((jpeg.height() as f32) / dpi_val),
+                )
+            }
+            ImageSource::Cmyk(cmyk) => {
+                let mmpi: f32 = 25.4;
+                let dpi_val: f32 = dpi.unwrap_or(300.0);
+                Size::new(
+                    mmpi * ((cmyk.width() as f32) / dpi_val),
+                    mmpi * ((cmyk.height() as f32) / dpi_val),
+                )
+            }
+            ImageSource::Svg(svg, _) => {
                 // SVG dimensions in pixels from the ExternalXObject
                 // Convert to mm using 300 DPI (standard for SVG)
                 let mmpi: f32 = 25.4;
@@ -63,16 +83,53 @@ impl ImageSource {
         }
     }
 
+    /// Returns the dimensions of the image in pixels.
+    ///
+    /// For SVG images, the width and height of the ExternalXObject are used.
+    fn pixel_size(&self) -> (f32, f32) {
+        match self {
+            ImageSource::Raster(img) => {
+                let (width, height) = img.dimensions();
+                (width as f32, height as f32)
+            }
+            ImageSource::Jpeg(jpeg) => (jpeg.width() as f32, jpeg.height() as f32),
+            ImageSource::Cmyk(cmyk) => (cmyk.width() as f32, cmyk.height() as f32),
+            ImageSource::Svg(svg, _) => (
+                svg.width.map(|px| px.0 as f32).unwrap_or(100.0),
+                svg.height.map(|px| px.0 as f32).unwrap_or(100.0),
+            ),
+        }
+    }
+
     /// Checks if this image source is an SVG.
     ///
     /// # Returns
     ///
     /// `true` if this is an SVG image, `false` otherwise.
     fn is_svg(&self) -> bool {
-        matches!(self, ImageSource::Svg(_))
+        matches!(self, ImageSource::Svg(..))
     }
 }
 
+/// Determines how an [`Image`][] is scaled to fit into a target box, see [`Image::set_fit`][].
+///
+/// In all modes, the element occupies exactly the size of the target box and the image is
+/// centered within the box.
+///
+/// [`Image`]: struct.Image.html
+/// [`Image::set_fit`]: struct.Image.html#method.set_fit
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ImageFit {
+    /// Scale the image proportionally so that it fits into the box.  Parts of the box may stay
+    /// empty.
+    Contain,
+    /// Scale the image proportionally so that it covers the box.  The parts of the image outside
+    /// of the box are clipped.
+    Cover,
+    /// Scale the image to the size of the box, ignoring its aspect ratio.
+    Fill,
+}
+
 /// An image to embed in the PDF.
 ///
 /// *Only available if the `images` feature is enabled.*
@@ -136,15 +193,38 @@ pub struct Image {
     /// The number of degrees of clockwise rotation.
     rotation: Rotation,
 
+    /// Optional target box and the mode used to fit the image into it.
+    fit: Option<(Size, ImageFit)>,
+
+    /// Optional mode used to fit the image into the complete available area if no target box
+    /// is set, see `from_tiff_pages`.
+    fit_to_area: Option<ImageFit>,
+
+    /// Optional part of the image to display, in source pixels (x, y, width, height).
+    crop: Option<(u32, u32, u32, u32)>,
+
     /// Optional background color used to composite away an alpha channel when rendering.
     /// If `None` the page background (white) is used.
     background_color: Option<crate::style::Color>,
 
+    /// Whether to composite the alpha channel over the background color instead of embedding
+    /// it as a soft mask.
+    flatten_alpha: bool,
+
     /// DPI override if you know better. Defaults to `printpdf`’s default of 300 dpi.
     dpi: Option<f32>,
 
+    /// Optional ICC profile of the source image that is embedded as an `/ICCBased` color space.
+    icc_profile: Option<render::IccProfile>,
+
     /// Optional hyperlink URI for the image. When set, clicking the image opens this URL.
     link: Option<String>,
+
+    /// Optional alternative text that describes the image for accessibility tools.
+    alt_text: Option<String>,
+
+    /// Files that are embedded and associated with the image, e.g. the MathML of a formula.
+    associated_files: Vec<render::AssociatedFile>,
 }
 
 impl Image {
@@ -176,18 +256,68 @@ impl Image {
     pub fn from_dynamic_image(data: image::DynamicImage) -> Result<Self, Error> {
         // Accept images with alpha; we'll composite them at render time using the
         // page/background color so that they visually match a flattened image.
-        Ok(Image {
-            source: ImageSource::Raster(data),
+        Ok(Self::from_source(ImageSource::Raster(data)))
+    }
+
+    fn from_source(source: ImageSource) -> Self {
+        Image {
+            source,
             alignment: Alignment::default(),
             position: None,
             scale: Scale::default(),
             fit_to_page_width: None,
             fit_to_page_height: None,
             rotation: Rotation::default(),
+            fit: None,
+            fit_to_area: None,
+            crop: None,
             background_color: None,
+            flatten_alpha: false,
             dpi: None,
+            icc_profile: None,
             link: None,
-        })
+            alt_text: None,
+            associated_files: Vec::new(),
+        }
+    }
+
+    /// Creates a new image from raw CMYK pixel data with one byte per component.
+    ///
+    /// The pixel data is embedded as is using the `/DeviceCMYK` color space, so that print-ready
+    /// CMYK assets keep their color values.  The data must contain `width * height * 4` bytes,
+    /// ordered row by row.
+    ///
+    /// Note that CMYK JPEG files can be loaded with [`from_path`][] and [`from_reader`][] and are
+    /// embedded without conversion, but other CMYK formats (e.g. TIFF) are converted to RGB by the
+    /// `image` crate when they are decoded.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the length of the data does not match the dimensions of the image.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use genpdfi_extended::elements::Image;
+    /// // 2x1 pixels: pure cyan and rich black
+    /// let data = vec![255, 0, 0, 0, 60, 40, 40, 255];
+    /// let image = Image::from_cmyk8(2, 1, data).expect("create image");
+    /// ```
+    ///
+    /// [`from_path`]: #method.from_path
+    /// [`from_reader`]: #method.from_reader
+    pub fn from_cmyk8(width: u32, height: u32, data: Vec<u8>) -> Result<Self, Error> {
+        let cmyk =
+            render::CmykImage::new(width as usize, height as usize, data).ok_or_else(|| {
+                Error::new(
+                    format!(
+                        "CMYK data does not match the image dimensions {}x{}",
+                        width, height
+                    ),
+                    ErrorKind::InvalidData,
+                )
+            })?;
+        Ok(Self::from_source(ImageSource::Cmyk(cmyk)))
     }
 
     /// Creates a new image from an SVG string.
@@ -235,18 +365,73 @@ impl Image {
             )
         })?;
 
-        Ok(Image {
-            source: ImageSource::Svg(svg_xobj),
-            alignment: Alignment::default(),
-            position: None,
-            scale: Scale::default(),
-            fit_to_page_width: None,
-            fit_to_page_height: None,
-            rotation: Rotation::default(),
-            background_color: None,
-            dpi: dpi_from_svg,
-            link: None,
-        })
+        let digest = render::svg_digest(&cleaned_svg);
+        let mut image = Self::from_source(ImageSource::Svg(svg_xobj, digest));
+        image.dpi = dpi_from_svg;
+        Ok(image)
+    }
+
+    /// Creates a new image from an SVG string and converts its text to outlines using the default
+    /// font family of the given font cache.
+    ///
+    /// [`from_svg_string`][] renders `<text>` elements with the fonts that `printpdf`'s SVG
+    /// converter finds on the system, so labels of charts or diagrams look different on every
+    /// machine.  This method replaces all `<text>` elements with the outlines of the glyphs of
+    /// the document fonts instead, so that the result is deterministic and does not depend on
+    /// any installed fonts.  Bold and italic text uses the corresponding variant of the font
+    /// family.
+    ///
+    /// Only basic text layout is supported: the first `x`, `y`, `dx` and `dy` values, `<tspan>`
+    /// elements, the font size, weight and style and the `text-anchor` property set as attributes
+    /// or in `style` attributes.  CSS rules in `<style>` elements and the `font-family` property
+    /// are ignored, and HTML content in `<foreignObject>` elements, like the default labels of
+    /// Mermaid diagrams, is not converted (see `Mermaid::with_text_outlines`).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use genpdfi_extended::elements::Image;
+    /// use genpdfi_extended::fonts::{FontCache, FontData, FontFamily};
+    /// let data = include_bytes!(concat!(env!("CARGO_MANIFEST_DIR"), "/fonts/NotoSans-Regular.ttf")).to_vec();
+    /// let fd = FontData::new(data, None).expect("font data");
+    /// let family = FontFamily { regular: fd.clone(), bold: fd.clone(), italic: fd.clone(), bold_italic: fd };
+    /// let cache = FontCache::new(family);
+    /// let svg = r#"<svg width="100" height="50" xmlns="http://www.w3.org/2000/svg">
+    ///     <text x="50" y="30" font-size="12" text-anchor="middle">Label</text>
+    ///   </svg>"#;
+    /// let image = Image::from_svg_string_with_fonts(svg, &cache).expect("parse SVG");
+    /// ```
+    ///
+    /// [`from_svg_string`]: #method.from_svg_string
+    pub fn from_svg_string_with_fonts(
+        svg_content: &str,
+        font_cache: &fonts::FontCache,
+    ) -> Result<Self, Error> {
+        Self::from_svg_string_with_font_family(
+            svg_content,
+            font_cache,
+            font_cache.default_font_family(),
+        )
+    }
+
+    /// Creates a new image from an SVG string and converts its text to outlines using the given
+    /// font family.
+    ///
+    /// The font family must have been loaded by the given font cache, e. g. with
+    /// [`FontCache::add_font_family`][].  See [`from_svg_string_with_fonts`][] for details.
+    ///
+    /// [`FontCache::add_font_family`]: ../fonts/struct.FontCache.html#method.add_font_family
+    /// [`from_svg_string_with_fonts`]: #method.from_svg_string_with_fonts
+    pub fn from_svg_string_with_font_family(
+        svg_content: &str,
+        font_cache: &fonts::FontCache,
+        font_family: fonts::FontFamily<fonts::Font>,
+    ) -> Result<Self, Error> {
+        Self::from_svg_string(&super::svg_text::text_to_paths(
+            svg_content,
+            font_cache,
+            font_family,
+        ))
     }
 
     /// Extracts DPI from the `data-dpi` attribute in SVG content.
@@ -433,36 +618,178 @@ impl Image {
         Self::from_svg_string(&content)
     }
 
-    fn from_image_reader<R>(reader: image::ImageReader<R>) -> Result<Self, Error>
-    where
-        R: std::io::BufRead,
-        R: std::io::Read,
-        R: std::io::Seek,
-    {
-        let image = reader
+    /// Creates a new image from encoded image data.
+    ///
+    /// JPEG files are embedded as they are, all other formats are decoded.  Embedded ICC profiles
+    /// are kept.
+    fn from_encoded(data: Vec<u8>) -> Result<Self, Error> {
+        let data = match render::JpegImage::parse(data) {
+            Ok(jpeg) => {
+                let icc_profile = jpeg.icc_profile();
+                let mut image = Self::from_source(ImageSource::Jpeg(jpeg));
+                image.icc_profile = icc_profile;
+                return Ok(image);
+            }
+            Err(data) => data,
+        };
+        let mut decoder = image::ImageReader::new(std::io::Cursor::new(data))
             .with_guessed_format()
             .context("Could not determine image format")?
-            .decode()
+            .into_decoder()
             .context("Could not decode image")?;
-        Self::from_dynamic_image(image)
+        let icc_profile = decoder.icc_profile().ok().flatten();
+        let image = image::DynamicImage::from_decoder(decoder).context("Could not decode image")?;
+        let mut image = Self::from_dynamic_image(image)?;
+        image.icc_profile = icc_profile.and_then(render::IccProfile::parse);
+        Ok(image)
     }
 
     /// Creates a new image from the given reader.
-    pub fn from_reader<R>(reader: R) -> Result<Self, Error>
+    ///
+    /// JPEG images are embedded without decoding and re-encoding them (using the `DCTDecode`
+    /// filter), also if they use the CMYK color space.  JPEG files that cannot be embedded
+    /// directly and all other formats are decoded.
+    pub fn from_reader<R>(mut reader: R) -> Result<Self, Error>
     where
         R: std::io::BufRead,
         R: std::io::Read,
         R: std::io::Seek,
     {
-        Self::from_image_reader(image::ImageReader::new(reader))
+        let mut data = Vec::new();
+        reader
+            .read_to_end(&mut data)
+            .context("Failed to read image from reader")?;
+        Self::from_encoded(data)
     }
 
     /// Creates a new image by reading from the given path.
+    ///
+    /// JPEG files are embedded as they are, see [`from_reader`](#method.from_reader).
     pub fn from_path(path: impl AsRef<path::Path>) -> Result<Self, Error> {
         let path = path.as_ref();
-        let reader = image::ImageReader::open(path)
+        let data = std::fs::read(path)
             .with_context(|| format!("Could not read image from path {}", path.display()))?;
-        Self::from_image_reader(reader)
+        Self::from_encoded(data)
+    }
+
+    /// Creates a new image from the frame with the given index of encoded image data.
+    ///
+    /// TIFF pages are selected by rewriting the pointer to the first image file directory, so
+    /// that the selected page is decoded as a single-page TIFF file.  GIF frames are composited
+    /// onto the full canvas as they would be displayed in an animation.  All other formats only
+    /// have a single frame with the index 0.
+    fn from_encoded_frame(data: Vec<u8>, index: usize) -> Result<Self, Error> {
+        match image::guess_format(&data) {
+            Ok(image::ImageFormat::Tiff) => Self::from_encoded(select_tiff_page(data, index)?),
+            Ok(image::ImageFormat::Gif) => {
+                let frame = gif_frames(&data)?
+                    .nth(index)
+                    .ok_or_else(|| frame_out_of_range(index))?
+                    .context("Could not decode GIF frame")?;
+                Self::from_dynamic_image(image::DynamicImage::ImageRgba8(frame.into_buffer()))
+            }
+            _ if index == 0 => Self::from_encoded(data),
+            _ => Err(frame_out_of_range(index)),
+        }
+    }
+
+    /// Returns the number of frames (GIF) or pages (TIFF) of the image stored at the given path.
+    ///
+    /// All other image formats and SVG files have a single frame.
+    pub fn frame_count(path: impl AsRef<path::Path>) -> Result<usize, Error> {
+        let path = path.as_ref();
+        let data = std::fs::read(path)
+            .with_context(|| format!("Could not read image from path {}", path.display()))?;
+        count_frames(&data)
+    }
+
+    /// Creates a new image from the frame or page with the given index of the image read from
+    /// the given reader.
+    ///
+    /// Use this method to select a page of a multi-page TIFF file or a frame of an animated GIF
+    /// file.  The index starts at zero, see [`frame_count`][] for the number of available
+    /// frames.  For all other formats, only the index 0 is valid.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the image cannot be decoded or if there is no frame with the given
+    /// index.
+    ///
+    /// [`frame_count`]: #method.frame_count
+    pub fn from_reader_frame<R>(mut reader: R, index: usize) -> Result<Self, Error>
+    where
+        R: std::io::BufRead,
+        R: std::io::Read,
+        R: std::io::Seek,
+    {
+        let mut data = Vec::new();
+        reader
+            .read_to_end(&mut data)
+            .context("Failed to read image from reader")?;
+        Self::from_encoded_frame(data, index)
+    }
+
+    /// Creates a new image from the frame or page with the given index of the image stored at
+    /// the given path.
+    ///
+    /// See [`from_reader_frame`](#method.from_reader_frame) for details.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use genpdfi_extended::elements::Image;
+    /// let second_page = Image::from_path_frame("scan.tiff", 1).expect("load page");
+    /// ```
+    pub fn from_path_frame(path: impl AsRef<path::Path>, index: usize) -> Result<Self, Error> {
+        let path = path.as_ref();
+        let data = std::fs::read(path)
+            .with_context(|| format!("Could not read image from path {}", path.display()))?;
+        Self::from_encoded_frame(data, index)
+    }
+
+    /// Creates one image per page of the multi-page TIFF file (e. g. a scanned document) stored
+    /// at the given path.
+    ///
+    /// The images are scaled to fill the available area while keeping their aspect ratio, like
+    /// with [`ImageFit::Contain`][] for a box with the size of the area.  As the available area
+    /// is determined when the image is rendered, each image should be placed on its own page, for
+    /// example by adding a [`PageBreak`][] between the images.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use genpdfi_extended::{elements, fonts, Document};
+    /// let font_family = fonts::from_files(concat!(env!("CARGO_MANIFEST_DIR"), "/fonts"), "NotoSans", None)
+    ///     .expect("Failed to load font family");
+    /// let mut doc = Document::new(font_family);
+    /// let pages = elements::Image::from_tiff_pages("scan.tiff").expect("load scan");
+    /// for (i, page) in pages.into_iter().enumerate() {
+    ///     if i > 0 {
+    ///         doc.push(elements::PageBreak::new());
+    ///     }
+    ///     doc.push(page);
+    /// }
+    /// ```
+    ///
+    /// [`ImageFit::Contain`]: enum.ImageFit.html#variant.Contain
+    /// [`PageBreak`]: struct.PageBreak.html
+    pub fn from_tiff_pages(path: impl AsRef<path::Path>) -> Result<Vec<Self>, Error> {
+        let path = path.as_ref();
+        let data = std::fs::read(path)
+            .with_context(|| format!("Could not read image from path {}", path.display()))?;
+        let pages = tiff_directories(&data).ok_or_else(|| {
+            Error::new(
+                format!("{} is not a TIFF file", path.display()),
+                ErrorKind::InvalidData,
+            )
+        })?;
+        (0..pages.len())
+            .map(|index| {
+                let mut image = Self::from_encoded_frame(data.clone(), index)?;
+                image.fit_to_area = Some(ImageFit::Contain);
+                Ok(image)
+            })
+            .collect()
     }
 
     /// Translates the image over to position.
@@ -479,7 +806,7 @@ impl Image {
     fn raster_data(&self) -> Option<&image::DynamicImage> {
         match &self.source {
             ImageSource::Raster(img) => Some(img),
-            ImageSource::Svg(_) => None,
+            ImageSource::Jpeg(_) | ImageSource::Cmyk(_) | ImageSource::Svg(..) => None,
         }
     }
 
@@ -545,13 +872,42 @@ impl Image {
         self.source.intrinsic_size(self.dpi)
     }
 
+    /// Returns the offset and the size of the displayed part of the image in mm (without scale),
+    /// taking into account the crop rectangle.
+    fn content_rect(&self) -> (Position, Size) {
+        let intrinsic = self.intrinsic_size();
+        let (px_width, px_height) = self.source.pixel_size();
+        match self.crop {
+            Some((x, y, width, height)) => {
+                let mm_x = intrinsic.width.0 / px_width;
+                let mm_y = intrinsic.height.0 / px_height;
+                let x = (x as f32).min(px_width);
+                let y = (y as f32).min(px_height);
+                let width = (width as f32).min(px_width - x);
+                let height = (height as f32).min(px_height - y);
+                (
+                    Position::new(x * mm_x, y * mm_y),
+                    Size::new(width * mm_x, height * mm_y),
+                )
+            }
+            None => (Position::default(), intrinsic),
+        }
+    }
+
     /// Computes size in mm for a given explicit scale (without modifying `self.scale`).
     fn size_with_scale(&self, scale: Scale) -> Size {
-        let intrinsic = self.intrinsic_size();
-        Size::new(scale.x * intrinsic.width.0, scale.y * intrinsic.height.0)
+        let content = self.content_rect().1;
+        Size::new(scale.x * content.width.0, scale.y * content.height.0)
     }
 
     /// Sets the clockwise rotation of the image around the bottom left corner.
+    ///
+    /// Rotation is not supported for images with a box or a crop rectangle, see [`set_fit`][]
+    /// and [`set_crop`][].  For these images, the rotation is ignored and a warning is reported
+    /// when rendering.
+    ///
+    /// [`set_fit`]: #method.set_fit
+    /// [`set_crop`]: #method.set_crop
     pub fn set_clockwise_rotation(&mut self, rotation: impl Into<Rotation>) {
         self.rotation = rotation.into();
     }
@@ -563,6 +919,114 @@ impl Image {
         self
     }
 
+    /// Fits the image into a box of the given size using the given mode.
+    ///
+    /// The box takes precedence over the scale and the page-relative resizing.  The element
+    /// always has the size of the box, so that images with different dimensions, for example
+    /// thumbnails in a product catalog, are laid out identically.  The rotation is ignored if a
+    /// box is set.
+    pub fn set_fit(&mut self, size: impl Into<Size>, fit: ImageFit) {
+        self.fit = Some((size.into(), fit));
+    }
+
+    /// Fits the image into a box of the given size using the given mode and returns it.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[cfg(feature = "images")]
+    /// # {
+    /// use genpdfi_extended::elements::{Image, ImageFit};
+    /// // a 40x30 mm thumbnail that is completely covered by the image
+    /// let img = Image::from_dynamic_image(image::DynamicImage::new_rgb8(400, 100))
+    ///     .expect("create")
+    ///     .with_fit((40, 30), ImageFit::Cover);
+    /// # }
+    /// ```
+    pub fn with_fit(mut self, size: impl Into<Size>, fit: ImageFit) -> Self {
+        self.set_fit(size, fit);
+        self
+    }
+
+    /// Only displays the given rectangle of the image, given in source pixels.
+    ///
+    /// The rectangle is clamped to the image dimensions and the rest of the image is clipped.
+    /// For SVG images, the pixel size of the SVG document is used.  The rotation is ignored if a
+    /// crop rectangle is set.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the rectangle is empty or if it starts outside of the image.
+    pub fn set_crop(&mut self, x: u32, y: u32, width: u32, height: u32) -> Result<(), Error> {
+        let (px_width, px_height) = self.source.pixel_size();
+        if width == 0 || height == 0 || x as f32 >= px_width || y as f32 >= px_height {
+            return Err(Error::new(
+                format!(
+                    "Crop rectangle {}x{}+{}+{} does not contain any pixels of the {}x{} image",
+                    width, height, x, y, px_width, px_height
+                ),
+                ErrorKind::InvalidData,
+            ));
+        }
+        self.crop = Some((x, y, width, height));
+        Ok(())
+    }
+
+    /// Only displays the given rectangle of the image, given in source pixels, and returns the
+    /// image.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the rectangle is empty or if it starts outside of the image.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[cfg(feature = "images")]
+    /// # {
+    /// use genpdfi_extended::elements::Image;
+    /// // show the left half of the image
+    /// let img = Image::from_dynamic_image(image::DynamicImage::new_rgb8(400, 100))
+    ///     .expect("create")
+    ///     .with_crop(0, 0, 200, 100)
+    ///     .expect("crop");
+    /// # }
+    /// ```
+    pub fn with_crop(mut self, x: u32, y: u32, width: u32, height: u32) -> Result<Self, Error> {
+        self.set_crop(x, y, width, height)?;
+        Ok(self)
+    }
+
+    /// Sets the ICC profile that describes the colors of this image.
+    ///
+    /// The profile is embedded as an `/ICCBased` color space.  Profiles embedded in PNG and JPEG
+    /// files are used automatically, so this method is only required to override them or to
+    /// assign a profile to raw pixel data, e.g. from [`from_cmyk8`][].  The profile is ignored when
+    /// rendering if its color space does not match the image (gray, RGB or CMYK).
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the data is not an ICC profile for a gray, RGB or CMYK color space.
+    ///
+    /// [`from_cmyk8`]: #method.from_cmyk8
+    pub fn set_icc_profile(&mut self, profile: Vec<u8>) -> Result<(), Error> {
+        let profile = render::IccProfile::parse(profile).ok_or_else(|| {
+            Error::new("Invalid or unsupported ICC profile", ErrorKind::InvalidData)
+        })?;
+        self.icc_profile = Some(profile);
+        Ok(())
+    }
+
+    /// Sets the ICC profile that describes the colors of this image and returns the image.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the data is not an ICC profile for a gray, RGB or CMYK color space.
+    pub fn with_icc_profile(mut self, profile: Vec<u8>) -> Result<Self, Error> {
+        self.set_icc_profile(profile)?;
+        Ok(self)
+    }
+
     /// Sets the expected DPI of the encoded image.
     pub fn set_dpi(&mut self, dpi: f32) {
         self.dpi = Some(dpi);
@@ -576,8 +1040,13 @@ impl Image {
 
     /// Set the background color used to composite away an alpha channel when rendering.
     /// If not set, white is used.
+    ///
+    /// Setting a background color implies [`set_flatten_alpha(true)`][].
+    ///
+    /// [`set_flatten_alpha(true)`]: #method.set_flatten_alpha
     pub fn set_background_color(&mut self, color: crate::style::Color) {
         self.background_color = Some(color);
+        self.flatten_alpha = true;
     }
 
     /// Set the background color used to composite away an alpha channel and return the image.
@@ -601,6 +1070,37 @@ impl Image {
         self
     }
 
+    /// Sets whether the alpha channel of a raster image is composited over the background color
+    /// (see [`set_background_color`][]) instead of being embedded as a soft mask.
+    ///
+    /// By default, the alpha channel is kept and the image is drawn with real transparency.
+    /// Flattening is required for PDF/A-1 documents, which do not allow transparency.
+    ///
+    /// [`set_background_color`]: #method.set_background_color
+    pub fn set_flatten_alpha(&mut self, flatten: bool) {
+        self.flatten_alpha = flatten;
+    }
+
+    /// Composites the alpha channel of a raster image over the background color and returns the
+    /// image.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[cfg(feature = "images")]
+    /// # {
+    /// use genpdfi_extended::elements::Image;
+    /// // flatten the transparency, e.g. for a PDF/A-1 document
+    /// let img = Image::from_dynamic_image(image::DynamicImage::new_rgba8(10, 10)).unwrap()
+    ///     .with_flattened_alpha();
+    /// let _ = img;
+    /// # }
+    /// ```
+    pub fn with_flattened_alpha(mut self) -> Self {
+        self.set_flatten_alpha(true);
+        self
+    }
+
     /// Sets a hyperlink URI for this image.
     /// When set, clicking the image will open this URL in a PDF viewer.
     pub fn set_link(&mut self, uri: impl Into<String>) {
@@ -627,6 +1127,40 @@ impl Image {
         self
     }
 
+    /// Sets the alternative text of the image.
+    ///
+    /// The alternative text describes the content of the image for screen readers and other
+    /// accessibility tools.  It is stored as the description (`dc:description`) in the XMP
+    /// metadata of the image XObject.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[cfg(feature = "images")]
+    /// # {
+    /// use genpdfi_extended::elements::Image;
+    /// let image = Image::from_dynamic_image(image::DynamicImage::new_rgb8(10, 10))
+    ///     .expect("create image")
+    ///     .with_alt_text("A black square");
+    /// # }
+    /// ```
+    pub fn set_alt_text(&mut self, alt_text: impl Into<String>) {
+        self.alt_text = Some(alt_text.into());
+    }
+
+    /// Sets the alternative text of the image and returns it.
+    ///
+    /// See [`set_alt_text`](#method.set_alt_text) for details.
+    pub fn with_alt_text(mut self, alt_text: impl Into<String>) -> Self {
+        self.set_alt_text(alt_text);
+        self
+    }
+
+    /// Embeds the given file in the document and associates it with the image (`/AF`).
+    pub(crate) fn add_associated_file(&mut self, file: render::AssociatedFile) {
+        self.associated_files.push(file);
+    }
+
     /// Resize proportionally so the image width becomes exactly `fraction * available_page_width`.
     /// `fraction` is in the range (0.0, 1.0]. This is applied at render-time using the actual
     /// available area width — no page width argument is required at call site.
@@ -693,37 +1227,146 @@ impl Image {
         self.fit_to_page_height = Some(fraction);
         self
     }
+
+    /// Adds the image to the area with the lower left corner at the given position.
+    fn draw(&self, area: &render::Area<'_>, position: Position, scale: Scale, rotation: Rotation) {
+        match &self.source {
+            ImageSource::Svg(svg, digest) => {
+                // SVG rendering - no alpha compositing needed, printpdf handles it
+                area.add_svg_with_digest(svg, *digest, position, scale, rotation);
+            }
+            ImageSource::Raster(raster) => {
+                // Raster rendering - handle alpha channel if present
+                // If flattening is requested, composite the alpha channel on-the-fly over the
+                // background color (default white) so that rendering works with PDFs that don't
+                // support transparency.  Otherwise it is embedded as a soft mask.
+                if raster.color().has_alpha() && self.flatten_alpha {
+                    // Determine background color (default white)
+                    let bg = self
+                        .background_color
+                        .unwrap_or(crate::style::Color::Rgb(255, 255, 255));
+
+                    let bg_rgb = match bg {
+                        crate::style::Color::Rgb(r, g, b) => (r, g, b),
+                        crate::style::Color::Greyscale(v) => (v, v, v),
+                        crate::style::Color::Cmyk(c, m, y, k) => {
+                            // Simple conversion by inverting CMYK to RGB (approximation)
+                            let cf = 1.0 - (c as f32 / 255.0);
+                            let mf = 1.0 - (m as f32 / 255.0);
+                            let yf = 1.0 - (y as f32 / 255.0);
+                            let kf = 1.0 - (k as f32 / 255.0);
+                            let r = ((1.0 - cf * kf) * 255.0).clamp(0.0, 255.0) as u8;
+                            let g = ((1.0 - mf * kf) * 255.0).clamp(0.0, 255.0) as u8;
+                            let b = ((1.0 - yf * kf) * 255.0).clamp(0.0, 255.0) as u8;
+                            (r, g, b)
+                        }
+                    };
+
+                    let rgba = raster.to_rgba8();
+                    let (w, h) = rgba.dimensions();
+                    let mut rgb = image::RgbImage::new(w, h);
+
+                    for (x, y, px) in rgba.enumerate_pixels() {
+                        let image::Rgba([sr, sg, sb, sa]) = *px;
+                        let af = sa as f32 / 255.0;
+                        let r = (sr as f32 * af + bg_rgb.0 as f32 * (1.0 - af)).round() as u8;
+                        let g = (sg as f32 * af + bg_rgb.1 as f32 * (1.0 - af)).round() as u8;
+                        let b = (sb as f32 * af + bg_rgb.2 as f32 * (1.0 - af)).round() as u8;
+                        rgb.put_pixel(x, y, image::Rgb([r, g, b]));
+                    }
+
+                    let composite = image::DynamicImage::ImageRgb8(rgb);
+                    area.add_image_with_profile(
+                        &composite,
+                        position,
+                        scale,
+                        rotation,
+                        self.dpi,
+                        self.icc_profile.as_ref(),
+                    );
+                } else {
+                    area.add_image_with_profile(
+                        raster,
+                        position,
+                        scale,
+                        rotation,
+                        self.dpi,
+                        self.icc_profile.as_ref(),
+                    );
+                }
+            }
+            ImageSource::Jpeg(jpeg) => {
+                // JPEG data is embedded as is, so no decoding is necessary
+                let icc_profile = self.icc_profile.as_ref();
+                area.add_jpeg(jpeg, position, scale, rotation, self.dpi, icc_profile);
+            }
+            ImageSource::Cmyk(cmyk) => {
+                let icc_profile = self.icc_profile.as_ref();
+                area.add_cmyk(cmyk, position, scale, rotation, self.dpi, icc_profile);
+            }
+        }
+    }
 }
 
 impl Element for Image {
     fn render(
         &mut self,
-        _context: &Context,
+        context: &Context,
         area: render::Area<'_>,
         _style: style::Style,
     ) -> Result<RenderResult, Error> {
         let mut result = RenderResult::default();
-
-        // Determine effective scale to use: priority is explicit fit-to-page settings,
-        // then explicit scale set by user.
-        let effective_scale = if let Some(fraction) = self.fit_to_page_width {
+        let (crop_offset, content_size) = self.content_rect();
+
+        // Determine effective scale to use: priority is the target box, then explicit
+        // fit-to-page settings, then explicit scale set by user.
+        let fit = self
+            .fit
+            .or_else(|| self.fit_to_area.map(|fit| (area.size(), fit)));
+        let effective_scale = if let Some((size, fit)) = fit {
+            let sx = size.width.as_f32() / content_size.width.as_f32();
+            let sy = size.height.as_f32() / content_size.height.as_f32();
+            match fit {
+                ImageFit::Contain => Scale::new(sx.min(sy), sx.min(sy)),
+                ImageFit::Cover => Scale::new(sx.max(sy), sx.max(sy)),
+                ImageFit::Fill => Scale::new(sx, sy),
+            }
+        } else if let Some(fraction) = self.fit_to_page_width {
             // target width in mm
             let target_width = area.size().width.as_f32() * fraction;
-            let intrinsic_width = self.intrinsic_size().width.as_f32();
+            let intrinsic_width = content_size.width.as_f32();
             let sf = target_width / intrinsic_width;
             Scale::new(sf, sf)
         } else if let Some(fraction) = self.fit_to_page_height {
             let target_height = area.size().height.as_f32() * fraction;
-            let intrinsic_height = self.intrinsic_size().height.as_f32();
+            let intrinsic_height = content_size.height.as_f32();
             let sf = target_height / intrinsic_height;
             Scale::new(sf, sf)
         } else {
             self.scale
         };
 
-        // Compute the true size based on effective_scale and bounding-box information
-        let true_size = self.size_with_scale(effective_scale);
-        let (bb_origin, bb_size) = bounding_box_offset_and_size(&self.rotation, &true_size);
+        // Boxed and cropped images are drawn within a frame that clips the image if necessary.
+        let framed = fit.is_some() || self.crop.is_some();
+        let rotation = if framed {
+            if self.rotation.degrees != 0.0 {
+                context.warn("The rotation of an image with a box or a crop rectangle is ignored");
+            }
+            Rotation::default()
+        } else {
+            self.rotation
+        };
+        let scaled_content = self.size_with_scale(effective_scale);
+        let true_size = fit.map(|(size, _)| size).unwrap_or(scaled_content);
+        // Offset of the image's top-left corner relative to the frame: center the displayed
+        // part in the box and move the cropped part out of the frame.
+        let image_offset = Position::new(
+            (true_size.width - scaled_content.width) / 2.0 - crop_offset.x * effective_scale.x,
+            (true_size.height - scaled_content.height) / 2.0 - crop_offset.y * effective_scale.y,
+        );
+        let clip = self.crop.is_some() || matches!(fit, Some((_, ImageFit::Cover)));
+
+        let (_, bb_size) = bounding_box_offset_and_size(&rotation, &true_size);
 
         // Compute two different positions:
         // * `top_left` is the logical top-left corner of the image's bounding box (used for
@@ -752,6 +1395,11 @@ impl Element for Image {
             // Update the result size to be based on the bounding-box size/offset.
             result.size = bb_size;
         }
+        if framed {
+            // The lower-left corner of the complete (uncropped) image
+            let image_height = self.intrinsic_size().height * effective_scale.y;
+            render_position = top_left + image_offset + Position::new(0, image_height);
+        }
 
         // CHECK FOR OVERFLOW BEFORE RENDERING
         // If the image (with bounding box) doesn't fit in the available area,
@@ -775,73 +1423,19 @@ impl Element for Image {
         }
 
         // Only reach here if image FITS - now render it
-        match &self.source {
-            ImageSource::Svg(svg) => {
-                // SVG rendering - no alpha compositing needed, printpdf handles it
-                area.add_svg(svg, render_position, effective_scale, self.rotation);
-            }
-            ImageSource::Raster(raster) => {
-                // Raster rendering - handle alpha channel if present
-                // If the image has an alpha channel, composite it on-the-fly over the background color
-                // (default white) so that rendering works with PDFs that don't support alpha.
-                if raster.color().has_alpha() {
-                    // Determine background color (default white)
-                    let bg = self
-                        .background_color
-                        .unwrap_or(crate::style::Color::Rgb(255, 255, 255));
-
-                    let bg_rgb = match bg {
-                        crate::style::Color::Rgb(r, g, b) => (r, g, b),
-                        crate::style::Color::Greyscale(v) => (v, v, v),
-                        crate::style::Color::Cmyk(c, m, y, k) => {
-                            // Simple conversion by inverting CMYK to RGB (approximation)
-                            let cf = 1.0 - (c as f32 / 255.0);
-                            let mf = 1.0 - (m as f32 / 255.0);
-                            let yf = 1.0 - (y as f32 / 255.0);
-                            let kf = 1.0 - (k as f32 / 255.0);
-                            let r = ((1.0 - cf * kf) * 255.0).clamp(0.0, 255.0) as u8;
-                            let g = ((1.0 - mf * kf) * 255.0).clamp(0.0, 255.0) as u8;
-                            let b = ((1.0 - yf * kf) * 255.0).clamp(0.0, 255.0) as u8;
-                            (r, g, b)
-                        }
-                    };
-
-                    let rgba = raster.to_rgba8();
-                    let (w, h) = rgba.dimensions();
-                    let mut rgb = image::RgbImage::new(w, h);
-
-                    for (x, y, px) in rgba.enumerate_pixels() {
-                        let image::Rgba([sr, sg, sb, sa]) = *px;
-                        let af = sa as f32 / 255.0;
-                        let r = (sr as f32 * af + bg_rgb.0 as f32 * (1.0 - af)).round() as u8;
-                        let g = (sg as f32 * af + bg_rgb.1 as f32 * (1.0 - af)).round() as u8;
-                        let b = (sb as f32 * af + bg_rgb.2 as f32 * (1.0 - af)).round() as u8;
-                        rgb.put_pixel(x, y, image::Rgb([r, g, b]));
-                    }
-
-                    let composite = image::DynamicImage::ImageRgb8(rgb);
-                    area.add_image(
-                        &composite,
-                        render_position,
-                        effective_scale,
-                        self.rotation,
-                        self.dpi,
-                    );
-                } else {
-                    area.add_image(
-                        raster,
-                        render_position,
-                        effective_scale,
-                        self.rotation,
-                        self.dpi,
-                    );
-                }
+        area.described(self.alt_text.as_deref(), &self.associated_files, || {
+            if clip {
+                area.clipped(top_left, true_size, || {
+                    self.draw(&area, render_position, effective_scale, rotation)
+                });
+            } else {
+                self.draw(&area, render_position, effective_scale, rotation);
             }
-        }
+        });
 
         // Add link annotation after image is rendered (for both raster and SVG)
         if let Some(url) = &self.link {
-            area.add_image_link(top_left, true_size, self.rotation, url);
+            area.add_image_link(top_left, true_size, rotation, url);
         }
 
         // Image fits and was successfully rendered
@@ -851,6 +1445,98 @@ impl Element for Image {
     }
 }
 
+/// Returns the number of frames (GIF) or pages (TIFF) of the encoded image.
+fn count_frames(data: &[u8]) -> Result<usize, Error> {
+    match image::guess_format(data) {
+        Ok(image::ImageFormat::Tiff) => tiff_directories(data)
+            .map(|pages| pages.len())
+            .ok_or_else(|| Error::new("Invalid TIFF header", ErrorKind::InvalidData)),
+        Ok(image::ImageFormat::Gif) => Ok(gif_frames(data)?.count()),
+        _ => Ok(1),
+    }
+}
+
+fn gif_frames(data: &[u8]) -> Result<image::Frames<'_>, Error> {
+    use image::AnimationDecoder as _;
+
+    let decoder = image::codecs::gif::GifDecoder::new(std::io::Cursor::new(data))
+        .context("Could not decode GIF image")?;
+    Ok(decoder.into_frames())
+}
+
+fn frame_out_of_range(index: usize) -> Error {
+    Error::new(
+        format!("The image does not have a frame with the index {}", index),
+        ErrorKind::InvalidData,
+    )
+}
+
+/// Returns the offsets of the image file directories, i. e. the pages, of a TIFF or BigTIFF
+/// file, or `None` if the data does not start with a valid TIFF header.
+///
+/// A truncated or cyclic chain of directories ends the list.
+fn tiff_directories(data: &[u8]) -> Option<Vec<u64>> {
+    let little_endian = match data.get(..2)? {
+        b"II" => true,
+        b"MM" => false,
+        _ => return None,
+    };
+    let read = |pos: u64, len: usize| -> Option<u64> {
+        let start = usize::try_from(pos).ok()?;
+        let bytes = data.get(start..start.checked_add(len)?)?;
+        let fold = |acc: u64, b: &u8| (acc << 8) | u64::from(*b);
+        Some(if little_endian {
+            bytes.iter().rev().fold(0, fold)
+        } else {
+            bytes.iter().fold(0, fold)
+        })
+    };
+    // (size of offsets, size of the entry count, size of an entry, position of the first offset)
+    let (offset_len, count_len, entry_len, first) = match read(2, 2)? {
+        42 => (4, 2, 12, 4),
+        43 => (8, 8, 20, 8),
+        _ => return None,
+    };
+
+    let mut directories = Vec::new();
+    let mut offset = read(first, offset_len)?;
+    while offset != 0 && !directories.contains(&offset) {
+        let next = read(offset, count_len)
+            .and_then(|count| count.checked_mul(entry_len))
+            .and_then(|len| len.checked_add(offset + count_len as u64))
+            .and_then(|pos| read(pos, offset_len));
+        directories.push(offset);
+        match next {
+            Some(next) => offset = next,
+            None => break,
+        }
+    }
+    Some(directories)
+}
+
+/// Turns the TIFF file into a file that starts with the page with the given index by replacing
+/// the offset of the first image file directory.
+fn select_tiff_page(mut data: Vec<u8>, index: usize) -> Result<Vec<u8>, Error> {
+    let directories = tiff_directories(&data)
+        .ok_or_else(|| Error::new("Invalid TIFF header", ErrorKind::InvalidData))?;
+    let offset = *directories
+        .get(index)
+        .ok_or_else(|| frame_out_of_range(index))?;
+    let little_endian = data.starts_with(b"II");
+    let (pos, len) = if data[2..4] == [42, 0] || data[2..4] == [0, 42] {
+        (4, 4)
+    } else {
+        (8, 8)
+    };
+    let bytes = offset.to_le_bytes();
+    let field = &mut data[pos..pos + len];
+    field.copy_from_slice(&bytes[..len]);
+    if !little_endian {
+        field.reverse();
+    }
+    Ok(data)
+}
+
 /// Given the Size of a box (width/height), compute the bounding-box size and offset when
 /// rotated some degrees.  The offset is the distance from the top-left corner of the bounding box
 /// to the (originally) lower-left corner of the image.
@@ -1300,6 +1986,152 @@ mod tests {
         assert_approx_eq!(Size, expected, res.size);
     }
 
+    #[cfg(feature = "images")]
+    #[test]
+    fn test_render_alpha_image_uses_soft_mask_unless_flattened() {
+        use crate::style::Style;
+
+        let context = crate::test_util::context();
+
+        let has_soft_mask = |flatten: bool| {
+            let rgba = image::DynamicImage::ImageRgba8(image::RgbaImage::from_pixel(
+                4,
+                4,
+                image::Rgba([255, 0, 0, 128]),
+            ));
+            let mut img = Image::from_dynamic_image(rgba).expect("image");
+            img.set_flatten_alpha(flatten);
+            let r = Renderer::new(Size::new(200.0, 200.0), "t").expect("renderer");
+            let area = r.first_page().first_layer().area();
+            img.render(&context, area, Style::new()).expect("render");
+            let mut buf = Vec::new();
+            r.write(&mut buf).expect("write");
+            let doc = lopdf::Document::load_mem(&buf).expect("parse");
+            doc.objects
+                .values()
+                .filter_map(|obj| obj.as_stream().ok())
+                .any(|stream| stream.dict.has(b"SMask"))
+        };
+
+        assert!(has_soft_mask(false));
+        assert!(!has_soft_mask(true));
+    }
+
+    #[cfg(feature = "images")]
+    #[test]
+    fn test_alt_text_is_stored_in_xobject_metadata() {
+        use crate::style::Style;
+
+        let context = crate::test_util::context();
+
+        let rgb = image::DynamicImage::ImageRgb8(image::RgbImage::from_pixel(
+            4,
+            4,
+            image::Rgb([255, 0, 0]),
+        ));
+        let mut described = Image::from_dynamic_image(rgb.clone())
+            .expect("image")
+            .with_alt_text("Red <square> & more");
+        let mut plain = Image::from_dynamic_image(rgb).expect("image");
+        let r = Renderer::new(Size::new(200.0, 200.0), "t").expect("renderer");
+        let area = r.first_page().first_layer().area();
+        described
+            .render(&context, area.clone(), Style::new())
+            .expect("render");
+        plain.render(&context, area, Style::new()).expect("render");
+        let mut buf = Vec::new();
+        r.write(&mut buf).expect("write");
+
+        let doc = lopdf::Document::load_mem(&buf).expect("parse");
+        let streams: Vec<_> = doc
+            .objects
+            .values()
+            .filter_map(|obj| obj.as_stream().ok())
+            .collect();
+        // The image without alternative text uses a separate XObject without metadata
+        let described_xobjects: Vec<_> = streams
+            .iter()
+            .filter_map(|stream| stream.dict.get(b"Metadata").ok())
+            .collect();
+        assert_eq!(described_xobjects.len(), 1);
+        let metadata_id = described_xobjects[0].as_reference().expect("reference");
+        let metadata = doc
+            .get_object(metadata_id)
+            .and_then(|obj| obj.as_stream())
+            .expect("metadata stream");
+        let xmp = String::from_utf8_lossy(&metadata.content);
+        assert!(
+            xmp.contains("<rdf:li xml:lang=\"x-default\">Red &lt;square&gt; &amp; more</rdf:li>")
+        );
+    }
+
+    #[cfg(feature = "images")]
+    #[test]
+    fn test_associated_file_is_embedded_in_xobject() {
+        use crate::style::Style;
+
+        let context = crate::test_util::context();
+
+        let rgb = image::DynamicImage::ImageRgb8(image::RgbImage::from_pixel(
+            4,
+            4,
+            image::Rgb([0, 0, 255]),
+        ));
+        let mathml = "<math><mi>x</mi></math>";
+        let mut img = Image::from_dynamic_image(rgb).expect("image");
+        img.add_associated_file(crate::render::AssociatedFile {
+            name: "formula.mml".to_owned(),
+            mime_type: "application/mathml+xml".to_owned(),
+            description: "MathML".to_owned(),
+            data: mathml.as_bytes().to_vec(),
+        });
+        let r = Renderer::new(Size::new(200.0, 200.0), "t").expect("renderer");
+        let area = r.first_page().first_layer().area();
+        img.render(&context, area, Style::new()).expect("render");
+        let mut buf = Vec::new();
+        r.write(&mut buf).expect("write");
+
+        let doc = lopdf::Document::load_mem(&buf).expect("parse");
+        let files: Vec<_> = doc
+            .objects
+            .values()
+            .filter_map(|obj| obj.as_stream().ok())
+            .filter_map(|stream| stream.dict.get(b"AF").ok())
+            .collect();
+        assert_eq!(files.len(), 1);
+        let files = files[0].as_array().expect("array");
+        assert_eq!(files.len(), 1);
+        let filespec = doc
+            .get_object(files[0].as_reference().expect("reference"))
+            .and_then(|obj| obj.as_dict())
+            .expect("filespec");
+        assert_eq!(
+            filespec
+                .get(b"AFRelationship")
+                .and_then(|obj| obj.as_name())
+                .ok(),
+            Some(&b"Supplement"[..])
+        );
+        let file_id = filespec
+            .get(b"EF")
+            .and_then(|obj| obj.as_dict())
+            .and_then(|ef| ef.get(b"F"))
+            .and_then(|obj| obj.as_reference())
+            .expect("embedded file");
+        let file = doc
+            .get_object(file_id)
+            .and_then(|obj| obj.as_stream())
+            .expect("embedded file stream");
+        assert_eq!(
+            file.dict.get(b"Subtype").and_then(|obj| obj.as_name()).ok(),
+            Some(&b"application/mathml+xml"[..])
+        );
+        let content = file
+            .decompressed_content()
+            .unwrap_or_else(|_| file.content.clone());
+        assert_eq!(content, mathml.as_bytes());
+    }
+
     #[cfg(feature = "images")]
     #[test]
     fn test_render_image_with_position_does_not_set_result_size() {
@@ -1342,6 +2174,193 @@ mod tests {
         assert!(img.is_ok());
     }
 
+    #[cfg(feature = "images")]
+    #[test]
+    fn test_fit_modes_and_crop() {
+        use crate::style::Style;
+
+        let context = crate::test_util::context();
+
+        // Renders the image and returns its size and whether a clipping path was used.
+        let render = |mut img: Image| {
+            let r = Renderer::new(Size::new(200.0, 200.0), "t").expect("renderer");
+            let area = r.first_page().first_layer().area();
+            let res = img.render(&context, area, Style::new()).expect("render");
+            let mut buf = Vec::new();
+            r.write(&mut buf).expect("write");
+            let doc = lopdf::Document::load_mem(&buf).expect("parse");
+            let page_id = *doc.get_pages().values().next().expect("page");
+            let content = doc.get_and_decode_page_content(page_id).expect("content");
+            let clipped = content.operations.iter().any(|op| op.operator == "W");
+            (res.size, clipped)
+        };
+
+        let wide =
+            || Image::from_dynamic_image(image::DynamicImage::new_rgb8(400, 100)).expect("image");
+        let tall =
+            || Image::from_dynamic_image(image::DynamicImage::new_rgb8(100, 400)).expect("image");
+        let thumbnail = Size::new(40, 30);
+        for fit in [ImageFit::Contain, ImageFit::Cover, ImageFit::Fill] {
+            for img in [wide(), tall()] {
+                let (size, clipped) = render(img.with_fit(thumbnail, fit));
+                assert_approx_eq!(Size, size, thumbnail);
+                assert_eq!(clipped, fit == ImageFit::Cover);
+            }
+        }
+
+        // 200x100 px at 300 dpi
+        let expected = Size::new(25.4_f32 * (200.0 / 300.0), 25.4_f32 * (100.0 / 300.0));
+        let cropped = wide().with_crop(100, 0, 200, 100).expect("crop");
+        assert_approx_eq!(Size, cropped.get_size(), expected);
+        let (size, clipped) = render(cropped);
+        assert_approx_eq!(Size, size, expected);
+        assert!(clipped);
+
+        // the crop rectangle is clamped to the image
+        let cropped = wide().with_crop(300, 50, 200, 100).expect("crop");
+        assert_approx_eq!(
+            Size,
+            cropped.get_size(),
+            Size::new(25.4_f32 * (100.0 / 300.0), 25.4_f32 * (50.0 / 300.0))
+        );
+
+        // empty crop rectangles are rejected
+        assert!(wide().with_crop(0, 0, 0, 100).is_err());
+        assert!(wide().with_crop(0, 0, 200, 0).is_err());
+        assert!(wide().with_crop(400, 0, 200, 100).is_err());
+        assert!(wide().with_crop(0, 100, 200, 100).is_err());
+
+        // the rotation is ignored with a warning
+        let mut img = wide()
+            .with_fit(thumbnail, ImageFit::Contain)
+            .with_clockwise_rotation(90.0);
+        let r = Renderer::new(Size::new(200.0, 200.0), "t").expect("renderer");
+        let area = r.first_page().first_layer().area();
+        img.render(&context, area, Style::new()).expect("render");
+        assert_eq!(context.take_warnings().len(), 1);
+    }
+
+    #[cfg(feature = "images")]
+    #[test]
+    fn test_jpeg_is_embedded_without_reencoding() {
+        use crate::style::Style;
+
+        let path = concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/examples/images/test_image.jpg"
+        );
+        let mut img = Image::from_path(path).expect("image");
+        assert!(matches!(img.source, ImageSource::Jpeg(_)));
+        // 180x100 px at 300 dpi
+        assert_approx_eq!(
+            Size,
+            img.intrinsic_size(),
+            Size::new(25.4_f32 * (180.0 / 300.0), 25.4_f32 * (100.0 / 300.0))
+        );
+
+        let context = crate::test_util::context();
+
+        let r = Renderer::new(Size::new(200.0, 200.0), "t").expect("renderer");
+        let area = r.first_page().first_layer().area();
+        img.render(&context, area, Style::new()).expect("render");
+        let mut buf = Vec::new();
+        r.write(&mut buf).expect("write");
+
+        let doc = lopdf::Document::load_mem(&buf).expect("parse");
+        let jpeg = std::fs::read(path).expect("read");
+        let stream = doc
+            .objects
+            .values()
+            .filter_map(|obj| obj.as_stream().ok())
+            .find(|stream| {
+                stream
+                    .dict
+                    .get(b"Filter")
+                    .and_then(|obj| obj.as_name())
+                    .ok()
+                    == Some(&b"DCTDecode"[..])
+            })
+            .expect("DCTDecode image");
+        assert_eq!(stream.content, jpeg);
+        assert_eq!(
+            stream.dict.get(b"Width").and_then(|w| w.as_i64()).ok(),
+            Some(180)
+        );
+        assert_eq!(
+            stream.dict.get(b"Height").and_then(|h| h.as_i64()).ok(),
+            Some(100)
+        );
+        assert_eq!(
+            stream
+                .dict
+                .get(b"ColorSpace")
+                .and_then(|obj| obj.as_name())
+                .ok(),
+            Some(&b"DeviceRGB"[..])
+        );
+    }
+
+    #[cfg(feature = "images")]
+    #[test]
+    fn test_cmyk_image_with_icc_profile() {
+        use crate::style::Style;
+
+        let mut profile = vec![0u8; 128];
+        profile[16..20].copy_from_slice(b"CMYK");
+        profile[36..40].copy_from_slice(b"acsp");
+        let pixels: Vec<u8> = (0..4 * 3 * 4).map(|i| i as u8).collect();
+        assert!(Image::from_cmyk8(4, 4, pixels.clone()).is_err());
+        let mut img = Image::from_cmyk8(4, 3, pixels.clone())
+            .expect("image")
+            .with_icc_profile(profile.clone())
+            .expect("profile");
+        assert!(img.clone().with_icc_profile(vec![0; 64]).is_err());
+
+        let context = crate::test_util::context();
+
+        let r = Renderer::new(Size::new(200.0, 200.0), "t").expect("renderer");
+        let area = r.first_page().first_layer().area();
+        img.render(&context, area, Style::new()).expect("render");
+        let mut buf = Vec::new();
+        r.write(&mut buf).expect("write");
+
+        let doc = lopdf::Document::load_mem(&buf).expect("parse");
+        let image = doc
+            .objects
+            .values()
+            .filter_map(|obj| obj.as_stream().ok())
+            .find(|stream| {
+                stream
+                    .dict
+                    .get(b"Subtype")
+                    .and_then(|obj| obj.as_name())
+                    .ok()
+                    == Some(&b"Image"[..])
+            })
+            .expect("image");
+        assert_eq!(image.get_plain_content().expect("content"), pixels);
+        assert_eq!(
+            image.dict.get(b"Width").and_then(|w| w.as_i64()).ok(),
+            Some(4)
+        );
+        assert_eq!(
+            image.dict.get(b"Height").and_then(|h| h.as_i64()).ok(),
+            Some(3)
+        );
+        let color_space = image
+            .dict
+            .get(b"ColorSpace")
+            .and_then(|obj| obj.as_array())
+            .expect("ICCBased color space");
+        assert_eq!(color_space[0].as_name().ok(), Some(&b"ICCBased"[..]));
+        let icc = doc
+            .get_object(color_space[1].as_reference().expect("reference"))
+            .and_then(|obj| obj.as_stream())
+            .expect("ICC stream");
+        assert_eq!(icc.dict.get(b"N").and_then(|n| n.as_i64()).ok(), Some(4));
+        assert_eq!(icc.get_plain_content().expect("content"), profile);
+    }
+
     #[cfg(feature = "images")]
     #[test]
     fn test_get_size_with_dpi_override() {
@@ -1456,4 +2475,113 @@ mod tests {
             "Raster should have raster data"
         );
     }
+
+    /// Creates an uncompressed little-endian TIFF file with one 8-bit grayscale page per size.
+    #[cfg(feature = "images")]
+    fn multi_page_tiff(sizes: &[(u32, u32)]) -> Vec<u8> {
+        let mut data = b"II\x2a\x00\0\0\0\0".to_vec();
+        let mut next_pointer = 4;
+        for &(width, height) in sizes {
+            let strip = data.len() as u32;
+            data.resize(data.len() + (width * height) as usize, 128);
+            if data.len() % 2 == 1 {
+                data.push(0);
+            }
+            let ifd = data.len() as u32;
+            data[next_pointer..next_pointer + 4].copy_from_slice(&ifd.to_le_bytes());
+            // (tag, type, value) with the types SHORT (3) and LONG (4)
+            let entries: [(u16, u16, u32); 9] = [
+                (256, 4, width),
+                (257, 4, height),
+                (258, 3, 8),
+                (259, 3, 1),
+                (262, 3, 1),
+                (273, 4, strip),
+                (277, 3, 1),
+                (278, 4, height),
+                (279, 4, width * height),
+            ];
+            data.extend_from_slice(&(entries.len() as u16).to_le_bytes());
+            for (tag, kind, value) in entries {
+                data.extend_from_slice(&tag.to_le_bytes());
+                data.extend_from_slice(&kind.to_le_bytes());
+                data.extend_from_slice(&1u32.to_le_bytes());
+                data.extend_from_slice(&value.to_le_bytes());
+            }
+            next_pointer = data.len();
+            data.extend_from_slice(&0u32.to_le_bytes());
+        }
+        data
+    }
+
+    #[cfg(feature = "images")]
+    #[test]
+    fn test_tiff_page_selection() {
+        use crate::style::Style;
+        use image::GenericImageView;
+        use std::io::Cursor;
+
+        let tiff = multi_page_tiff(&[(4, 2), (3, 5), (6, 6)]);
+        assert_eq!(super::tiff_directories(&tiff).map(|d| d.len()), Some(3));
+        assert_eq!(super::count_frames(&tiff).expect("count"), 3);
+
+        let page = Image::from_reader_frame(Cursor::new(tiff.clone()), 1).expect("page");
+        assert_eq!(page.raster_data().expect("raster").dimensions(), (3, 5));
+        assert!(Image::from_reader_frame(Cursor::new(tiff.clone()), 3).is_err());
+
+        let dir = tempfile::tempdir().expect("tempdir");
+        let path = dir.path().join("scan.tiff");
+        std::fs::write(&path, &tiff).expect("write");
+        assert_eq!(Image::frame_count(&path).expect("count"), 3);
+        let mut pages = Image::from_tiff_pages(&path).expect("pages");
+        assert_eq!(pages.len(), 3);
+
+        // The pages are fitted into the complete area
+        let context = crate::test_util::context();
+        let r = Renderer::new(Size::new(200.0, 200.0), "t").expect("renderer");
+        let area = r.first_page().first_layer().area();
+        for page in &mut pages {
+            let res = page
+                .render(&context, area.clone(), Style::new())
+                .expect("render");
+            assert_approx_eq!(Size, res.size, Size::new(200.0, 200.0));
+        }
+
+        // Without a box, resizing to the page width takes precedence over the page height
+        let mut page = Image::from_reader_frame(Cursor::new(tiff), 1)
+            .expect("page")
+            .resizing_page_with(0.3)
+            .resizing_page_height(1.0);
+        let res = page.render(&context, area, Style::new()).expect("render");
+        assert_approx_eq!(Size, res.size, Size::new(60.0, 100.0));
+    }
+
+    #[cfg(feature = "images")]
+    #[test]
+    fn test_gif_frame_selection() {
+        use image::codecs::gif::GifEncoder;
+        use image::{Frame, GenericImageView, Rgba, RgbaImage};
+        use std::io::Cursor;
+
+        let mut gif = Vec::new();
+        {
+            let mut encoder = GifEncoder::new(&mut gif);
+            encoder
+                .encode_frames(vec![
+                    Frame::new(RgbaImage::from_pixel(4, 4, Rgba([255, 0, 0, 255]))),
+                    Frame::new(RgbaImage::from_pixel(4, 4, Rgba([0, 0, 255, 255]))),
+                ])
+                .expect("encode");
+        }
+        assert_eq!(super::count_frames(&gif).expect("count"), 2);
+
+        let frame = Image::from_reader_frame(Cursor::new(gif.clone()), 1).expect("frame");
+        let pixel = frame.raster_data().expect("raster").get_pixel(0, 0);
+        assert!(
+            pixel[2] > 200 && pixel[0] < 50,
+            "unexpected pixel {:?}",
+            pixel
+        );
+        assert!(Image::from_reader_frame(Cursor::new(gif), 2).is_err());
+    }
 }
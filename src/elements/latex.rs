@@ -10,9 +10,16 @@
 //!
 //! Only available if the `latex` feature is enabled.
 
+use super::{lock, SourceFallback};
 use crate::error::{Error, ErrorKind};
-use crate::{render, style, Alignment, Context, Element, Position, RenderResult};
-use std::sync::OnceLock;
+use crate::{
+    fonts, render, style, Alignment, Context, Element, Margins, Mm, Position, RenderResult, Size,
+};
+use sha2::Digest as _;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, MutexGuard, OnceLock};
+use std::{fs, io};
 
 /// Helper constants for LaTeX rendering at 720 DPI
 const MICROTEX_DPI: i32 = 720;
@@ -21,6 +28,143 @@ const EMPIRICAL_ADJUSTMENT_FACTOR: f32 = 4.5;
 /// Global MicroTeX renderer instance - initialized only once
 static MICROTEX_RENDERER: OnceLock<microtex_rs::MicroTex> = OnceLock::new();
 
+/// The reference formula "m" - rendered only once
+static REFERENCE: OnceLock<Reference> = OnceLock::new();
+
+/// Key of the rendered-formula cache: the formula, the bits of the font size and the ARGB color
+type CacheKey = (String, u32, u32);
+
+/// The version of MicroTeX, see `build.rs`
+const MICROTEX_VERSION: &str = match option_env!("MICROTEX_VERSION") {
+    Some(version) => version,
+    None => "unknown",
+};
+
+/// Scaled SVGs of the formulas that have been rendered so far
+static FORMULA_CACHE: OnceLock<Mutex<FormulaCache>> = OnceLock::new();
+
+fn formula_cache() -> MutexGuard<'static, FormulaCache> {
+    lock(FORMULA_CACHE.get_or_init(Default::default))
+}
+
+/// The scaled SVGs of rendered formulas, kept in memory and optionally persisted in a directory.
+#[derive(Debug, Default)]
+struct FormulaCache {
+    formulas: HashMap<CacheKey, String>,
+    directory: Option<PathBuf>,
+    // The digest of the reference formula, set when the cache directory is first used
+    reference_digest: Option<Vec<u8>>,
+}
+
+impl FormulaCache {
+    /// Returns the path of the file in the cache directory for the given key, if a cache
+    /// directory is set.
+    ///
+    /// The file is named after a SHA-256 digest of the key and of the versions and constants the
+    /// scaled SVG depends on, so that files written by other versions are not used.  As the
+    /// version of MicroTeX is not known in all builds, the digest also covers the SVG of the
+    /// reference formula.  If it cannot be rendered, the cache directory is not used.
+    fn file(&mut self, key: &CacheKey) -> Option<PathBuf> {
+        self.directory.as_ref()?;
+        if self.reference_digest.is_none() {
+            self.reference_digest = Some(reference().ok()?.digest.clone());
+        }
+        let directory = self.directory.as_ref()?;
+        let (formula, size, color) = key;
+        let mut hasher = sha2::Sha256::new();
+        hasher.update(format!(
+            "genpdfi {}, MicroTeX {}, {} dpi, adjustment {}\n",
+            env!("CARGO_PKG_VERSION"),
+            MICROTEX_VERSION,
+            MICROTEX_DPI,
+            EMPIRICAL_ADJUSTMENT_FACTOR
+        ));
+        hasher.update(self.reference_digest.as_deref().unwrap_or_default());
+        hasher.update(size.to_le_bytes());
+        hasher.update(color.to_le_bytes());
+        hasher.update(formula.as_bytes());
+        let name: String = hasher
+            .finalize()
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect();
+        Some(directory.join(format!("{}.svg", name)))
+    }
+
+    /// Returns the scaled SVG for the given key from memory or from the cache directory.
+    fn get(&mut self, key: &CacheKey) -> Option<String> {
+        if let Some(svg) = self.formulas.get(key) {
+            return Some(svg.clone());
+        }
+        let svg = fs::read_to_string(self.file(key)?).ok()?;
+        self.formulas.insert(key.clone(), svg.clone());
+        Some(svg)
+    }
+
+    /// Stores the scaled SVG for the given key, and in the cache directory if it is set.  Errors
+    /// when writing to the cache directory are ignored.
+    fn insert(&mut self, key: CacheKey, svg: String) {
+        if let Some(file) = self.file(&key) {
+            let _ = write_cache_file(&file, &svg);
+        }
+        self.formulas.insert(key, svg);
+    }
+}
+
+/// Writes the file to a temporary file first so that concurrent readers never see partial files.
+fn write_cache_file(path: &Path, svg: &str) -> io::Result<()> {
+    if let Some(directory) = path.parent() {
+        fs::create_dir_all(directory)?;
+    }
+    let tmp = path.with_extension(format!("{}.tmp", std::process::id()));
+    fs::write(&tmp, svg)
+        .and_then(|_| fs::rename(&tmp, path))
+        .map_err(|err| {
+            let _ = fs::remove_file(&tmp);
+            err
+        })
+}
+
+/// The reference formula "m" that is used to scale the formulas to the font size.
+#[derive(Debug)]
+struct Reference {
+    /// The height of the formula in pixels
+    height_px: f32,
+    /// The SHA-256 digest of the SVG of the formula
+    digest: Vec<u8>,
+}
+
+/// Returns the reference formula, rendering it with MicroTeX on the first call.
+fn reference() -> Result<&'static Reference, Error> {
+    if let Some(reference) = REFERENCE.get() {
+        return Ok(reference);
+    }
+    let svg = get_microtex_renderer()?
+        .render("m", &render_config(0xff000000))
+        .map_err(|_| {
+            Error::new(
+                "Failed to render reference formula 'm'",
+                ErrorKind::Internal,
+            )
+        })?;
+    let (_width_px, height_px) = extract_svg_dimensions(&svg)?;
+    let digest = sha2::Sha256::digest(svg.as_bytes()).to_vec();
+    Ok(REFERENCE.get_or_init(|| Reference { height_px, digest }))
+}
+
+/// Returns the MicroTeX configuration for formulas with the given ARGB color.
+fn render_config(color: u32) -> microtex_rs::RenderConfig {
+    microtex_rs::RenderConfig {
+        dpi: MICROTEX_DPI,
+        line_width: 20.0,
+        line_height: 20.0 / 3.0,
+        text_color: color,
+        has_background: false,
+        render_glyph_use_path: true,
+        ..Default::default()
+    }
+}
+
 /// Get or initialize the MicroTeX renderer (thread-safe singleton).
 /// MicroTeX must only be initialized once - multiple initializations crash the engine.
 fn get_microtex_renderer() -> Result<&'static microtex_rs::MicroTex, Error> {
@@ -54,6 +198,24 @@ fn get_microtex_renderer() -> Result<&'static microtex_rs::MicroTex, Error> {
 /// # }
 /// ```
 ///
+/// Numbered display equation with a label that can be referenced:
+/// ```
+/// # #[cfg(feature = "latex")]
+/// # {
+/// use genpdfi_extended::elements;
+///
+/// let mut layout = elements::LinearLayout::vertical();
+/// layout.push(elements::EquationSection::new(3));
+/// layout.push(elements::Latex::new(r#"E = mc^2"#, 12.0).with_label("eq:energy"));
+/// layout.push(
+///     elements::Paragraph::new("See equation ")
+///         .inline_element(elements::InlineElement::new(
+///             elements::EquationReference::new("eq:energy"),
+///         )),
+/// );
+/// # }
+/// ```
+///
 /// With alignment:
 /// ```
 /// # #[cfg(feature = "latex")]
@@ -69,14 +231,33 @@ fn get_microtex_renderer() -> Result<&'static microtex_rs::MicroTex, Error> {
 pub struct Latex {
     /// The LaTeX formula source code
     formula: String,
-    /// Font size in "pseudo" points (equivalent to text font size)
-    size_pt: f32,
+    /// Font size in "pseudo" points (equivalent to text font size), or `None` to use the font
+    /// size of the style
+    size_pt: Option<f32>,
+    /// Color of the formula, or `None` to use the color of the style
+    color: Option<style::Color>,
     /// Optional explicit position (overrides alignment)
     position: Option<Position>,
     /// Horizontal alignment when not positioned explicitly
     alignment: Alignment,
     /// Whether to render inline (within text flow) or as a block
     inline: bool,
+    /// Alternative text for accessibility tools, defaults to the formula
+    alt_text: Option<String>,
+    /// Whether to print an equation number next to a block formula
+    numbered: bool,
+    /// Label of a numbered formula that can be referenced
+    label: Option<String>,
+    /// The equation number, set once the formula has been placed
+    equation: Option<super::EquationEntry>,
+    /// Whether to print the source of the formula instead of failing if it cannot be rendered
+    fallback: bool,
+    /// Font family of the fallback, e.g. a monospace font
+    fallback_font_family: Option<fonts::FontFamily<fonts::Font>>,
+    /// The fallback element, set once rendering the formula has failed
+    fallback_element: Option<SourceFallback>,
+    /// Whether to embed the MathML of the formula as an associated file
+    mathml: bool,
 }
 
 impl Latex {
@@ -99,13 +280,57 @@ impl Latex {
     pub fn new(formula: impl Into<String>, size_pt: f32) -> Self {
         Self {
             formula: formula.into(),
-            size_pt,
+            size_pt: Some(size_pt),
+            color: None,
             position: None,
             alignment: Alignment::Left,
             inline: false,
+            alt_text: None,
+            numbered: false,
+            label: None,
+            equation: None,
+            fallback: false,
+            fallback_font_family: None,
+            fallback_element: None,
+            mathml: false,
+        }
+    }
+
+    /// Creates a new LaTeX formula element that uses the font size of the style it is rendered
+    /// with.
+    ///
+    /// Like the color, the font size is then inherited from the surrounding elements, e. g. a
+    /// [`StyledElement`][].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # #[cfg(feature = "latex")]
+    /// # {
+    /// use genpdfi_extended::{elements, style};
+    /// let formula = elements::StyledElement::new(
+    ///     elements::Latex::new_styled(r#"\sqrt{2}"#),
+    ///     style::Style::new().with_font_size(16),
+    /// );
+    /// # }
+    /// ```
+    ///
+    /// [`StyledElement`]: struct.StyledElement.html
+    pub fn new_styled(formula: impl Into<String>) -> Self {
+        Self {
+            size_pt: None,
+            ..Self::new(formula, 0.0)
         }
     }
 
+    /// Sets the color of the formula.
+    ///
+    /// If no color is set, the color of the style is used, or black if the style has no color.
+    pub fn with_color(mut self, color: style::Color) -> Self {
+        self.color = Some(color);
+        self
+    }
+
     /// Sets explicit positioning, overriding alignment.
     pub fn with_position(mut self, position: Position) -> Self {
         self.position = Some(position);
@@ -118,6 +343,35 @@ impl Latex {
         self
     }
 
+    /// Sets the alternative text of the formula for accessibility tools.
+    ///
+    /// If no alternative text is set, the LaTeX source of the formula is used.  See
+    /// [`Image::set_alt_text`][] for details.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # #[cfg(feature = "latex")]
+    /// # {
+    /// use genpdfi_extended::elements;
+    /// let formula = elements::Latex::new(r#"E = mc^2"#, 12.0)
+    ///     .with_alt_text("Energy equals mass times the speed of light squared");
+    /// # }
+    /// ```
+    ///
+    /// [`Image::set_alt_text`]: struct.Image.html#method.set_alt_text
+    pub fn set_alt_text(&mut self, alt_text: impl Into<String>) {
+        self.alt_text = Some(alt_text.into());
+    }
+
+    /// Sets the alternative text of the formula and returns it.
+    ///
+    /// See [`set_alt_text`](#method.set_alt_text) for details.
+    pub fn with_alt_text(mut self, alt_text: impl Into<String>) -> Self {
+        self.set_alt_text(alt_text);
+        self
+    }
+
     /// Marks this formula for inline rendering (integrated into text flow).
     pub fn inline(mut self) -> Self {
         self.inline = true;
@@ -130,54 +384,199 @@ impl Latex {
         self
     }
 
-    /// Renders the LaTeX formula to SVG using MicroTeX and applies scaling.
-    /// Uses a global singleton MicroTeX instance (initialized only once).
-    fn render_to_scaled_svg(&self) -> Result<String, Error> {
-        // Get or initialize the global MicroTeX renderer (only happens once)
-        let renderer = get_microtex_renderer()?;
+    /// Prints an equation number, e.g. “(3.2)”, at the right edge of a block formula.
+    ///
+    /// A column at the right edge of the area is reserved for the number and the number is
+    /// vertically centered next to the formula.  When the formula is placed on a page, it is
+    /// assigned the next equation number with [`Context::register_equation`][].  Equations are
+    /// numbered throughout the document unless an [`EquationSection`][] is used.
+    ///
+    /// The number is not printed for inline formulas and formulas with an explicit position.
+    ///
+    /// [`Context::register_equation`]: ../struct.Context.html#method.register_equation
+    /// [`EquationSection`]: struct.EquationSection.html
+    pub fn numbered(mut self) -> Self {
+        self.numbered = true;
+        self
+    }
 
-        let config = microtex_rs::RenderConfig {
-            dpi: MICROTEX_DPI,
-            line_width: 20.0,
-            line_height: 20.0 / 3.0,
-            text_color: 0xff000000,
-            has_background: false,
-            render_glyph_use_path: true,
-            ..Default::default()
-        };
+    /// Numbers this formula and sets the label that can be used to reference it.
+    ///
+    /// The number of the formula can be printed with an [`EquationReference`][] or looked up with
+    /// [`Context::equation`][] after the formula has been rendered.  See [`numbered`][] for
+    /// details.
+    ///
+    /// [`EquationReference`]: struct.EquationReference.html
+    /// [`Context::equation`]: ../struct.Context.html#method.equation
+    /// [`numbered`]: #method.numbered
+    pub fn with_label(mut self, label: impl Into<String>) -> Self {
+        self.label = Some(label.into());
+        self.numbered()
+    }
 
-        // Render reference formula "m" to calculate scale factor
-        let reference_svg = renderer.render("m", &config).map_err(|_| {
-            Error::new(
-                "Failed to render reference formula 'm'",
-                ErrorKind::Internal,
-            )
-        })?;
+    /// Prints the LaTeX source in a red frame instead of aborting the rendering process if the
+    /// formula cannot be rendered.
+    ///
+    /// The error is reported as a warning to the render [`Context`][], see [`Context::warn`][].
+    /// The source is printed with the font family set with [`with_fallback_font_family`][] or
+    /// with the built-in Courier font family.  Numbered formulas keep their number so that the
+    /// references to them and the following numbers are not affected.  Without a fallback, an
+    /// error with the [`ErrorKind::LatexError`][] kind is returned.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # #[cfg(feature = "latex")]
+    /// # {
+    /// use genpdfi_extended::elements;
+    /// let formula = elements::Latex::new(r#"\frac{1}{"#, 12.0).with_fallback();
+    /// # }
+    /// ```
+    ///
+    /// [`Context`]: ../struct.Context.html
+    /// [`Context::warn`]: ../struct.Context.html#method.warn
+    /// [`with_fallback_font_family`]: #method.with_fallback_font_family
+    /// [`ErrorKind::LatexError`]: ../error/enum.ErrorKind.html#variant.LatexError
+    pub fn with_fallback(mut self) -> Self {
+        self.fallback = true;
+        self
+    }
+
+    /// Enables the fallback, see [`with_fallback`][], and sets the font family that is used to
+    /// print the LaTeX source, typically a monospace font that has been added to the
+    /// [`FontCache`][].
+    ///
+    /// [`with_fallback`]: #method.with_fallback
+    /// [`FontCache`]: ../fonts/struct.FontCache.html
+    pub fn with_fallback_font_family(
+        mut self,
+        font_family: fonts::FontFamily<fonts::Font>,
+    ) -> Self {
+        self.fallback_font_family = Some(font_family);
+        self.with_fallback()
+    }
 
-        let (_ref_width_px, ref_height_px) = extract_svg_dimensions(&reference_svg)?;
+    /// Sets whether the MathML of the formula is embedded in the PDF (default: `false`).
+    ///
+    /// The MathML is generated from the LaTeX source, after expanding the macros of the
+    /// document, and attached to the image of the formula as a PDF 2.0 associated file with the
+    /// `application/mathml+xml` type.  Screen readers and extraction tools can use it to recover
+    /// the math that is otherwise only available as vector graphics.
+    ///
+    /// Associated files (`/AF`) are a PDF 2.0 feature while the document is still written with a
+    /// PDF 1.x header, so readers that only implement PDF 1.7 ignore them.  Each formula also
+    /// adds an embedded file to the document.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # #[cfg(feature = "latex")]
+    /// # {
+    /// use genpdfi_extended::elements;
+    /// let formula = elements::Latex::new(r#"x^2"#, 12.0).with_mathml(true);
+    /// # }
+    /// ```
+    pub fn with_mathml(mut self, mathml: bool) -> Self {
+        self.mathml = mathml;
+        self
+    }
 
-        // Target height in pixels at 720 DPI
-        let target_height_px = self.size_pt * 10.0;
+    /// Creates the element that prints the source of the formula in a red frame, below the given
+    /// equation number.
+    fn fallback_element(&self, context: &Context, number: Option<String>) -> SourceFallback {
+        SourceFallback::new(context, &self.formula, number, self.fallback_font_family)
+    }
 
-        // Calculate scale factor with empirical adjustment
-        let mut scale_factor = target_height_px / ref_height_px;
-        scale_factor = scale_factor / EMPIRICAL_ADJUSTMENT_FACTOR;
+    /// Sets the directory in which rendered formulas are persisted, or `None` to only keep them
+    /// in memory (default).
+    ///
+    /// Rendered formulas are cached by their source, font size and color, so a formula that is
+    /// used several times in a document is only rendered once by MicroTeX.  If a cache directory
+    /// is set, the formulas are also stored as SVG files in this directory and reused by later
+    /// processes, unless they have been rendered with another version of this crate or of
+    /// MicroTeX.  Errors when writing to the cache directory are ignored.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # #[cfg(feature = "latex")]
+    /// # {
+    /// use genpdfi_extended::elements;
+    /// elements::Latex::set_cache_directory(Some(std::env::temp_dir().join("formulas")));
+    /// # elements::Latex::set_cache_directory(None);
+    /// # }
+    /// ```
+    pub fn set_cache_directory(directory: Option<PathBuf>) {
+        formula_cache().directory = directory;
+    }
 
-        // Render the actual formula
-        let mut svg = renderer.render(&self.formula, &config).map_err(|_| {
-            Error::new(
-                format!("Failed to render LaTeX formula: {}", self.formula),
-                ErrorKind::Internal,
-            )
-        })?;
+    /// Removes all rendered formulas from the in-memory cache.
+    ///
+    /// The files in the cache directory, see [`set_cache_directory`][], are not removed.
+    ///
+    /// [`set_cache_directory`]: #method.set_cache_directory
+    pub fn clear_cache() {
+        formula_cache().formulas.clear();
+    }
 
-        // Apply scaling
-        svg = apply_svg_scale(&svg, scale_factor)?;
+    /// Returns the scaled SVG of the given formula, i. e. the formula of this element with the
+    /// macros expanded, from the cache or renders it with MicroTeX.
+    ///
+    /// The font size and the color default to those of the given style.
+    fn render_to_scaled_svg(&self, formula: &str, style: style::Style) -> Result<String, Error> {
+        let size_pt = self.size_pt.unwrap_or_else(|| f32::from(style.font_size()));
+        let color = argb(
+            self.color
+                .or(style.color())
+                .unwrap_or(style::Color::Rgb(0, 0, 0)),
+        );
+        let key = (formula.to_owned(), size_pt.to_bits(), color);
+        if let Some(svg) = formula_cache().get(&key) {
+            return Ok(svg);
+        }
 
+        let svg = render_formula(&key.0, size_pt, color)?;
+        formula_cache().insert(key, svg.clone());
         Ok(svg)
     }
 }
 
+/// Renders the LaTeX formula to SVG using MicroTeX and applies scaling.
+/// Uses a global singleton MicroTeX instance (initialized only once).
+fn render_formula(formula: &str, size_pt: f32, color: u32) -> Result<String, Error> {
+    // Get or initialize the global MicroTeX renderer (only happens once)
+    let renderer = get_microtex_renderer()?;
+
+    let config = render_config(color);
+
+    // Render reference formula "m" to calculate scale factor (only happens once)
+    let ref_height_px = reference()?.height_px;
+
+    // Target height in pixels at 720 DPI
+    let target_height_px = size_pt * 10.0;
+
+    // Calculate scale factor with empirical adjustment
+    let mut scale_factor = target_height_px / ref_height_px;
+    scale_factor = scale_factor / EMPIRICAL_ADJUSTMENT_FACTOR;
+
+    // Render the actual formula
+    let mut svg = renderer.render(formula, &config).map_err(|err| {
+        let message = err.to_string();
+        Error::new(
+            format!("Failed to render LaTeX formula {}: {}", formula, message),
+            ErrorKind::LatexError {
+                formula: formula.to_owned(),
+                message,
+            },
+        )
+    })?;
+
+    // Apply scaling
+    svg = apply_svg_scale(&svg, scale_factor)?;
+
+    Ok(svg)
+}
+
 impl Element for Latex {
     fn render(
         &mut self,
@@ -185,16 +584,45 @@ impl Element for Latex {
         area: render::Area<'_>,
         style: style::Style,
     ) -> Result<RenderResult, Error> {
-        // Render to scaled SVG
-        let scaled_svg = self.render_to_scaled_svg()?;
+        if let Some(element) = &mut self.fallback_element {
+            return element.render(context, area, style);
+        }
 
-        // Create an Image element from the SVG
-        let mut image = super::Image::from_svg_string(&scaled_svg).map_err(|_| {
-            Error::new(
-                "Failed to convert LaTeX formula SVG to image",
-                ErrorKind::Internal,
-            )
-        })?;
+        let numbered = self.numbered && !self.inline && self.position.is_none();
+
+        // Expand the macros, render to scaled SVG and create an Image element from the SVG
+        let rendered = context
+            .latex_macros
+            .expand(&self.formula)
+            .and_then(|formula| {
+                let scaled_svg = self.render_to_scaled_svg(&formula, style)?;
+                let image = super::Image::from_svg_string(&scaled_svg).map_err(|err| {
+                    Error::new(
+                        format!("Failed to convert LaTeX formula SVG to image: {}", err),
+                        ErrorKind::Internal,
+                    )
+                })?;
+                Ok((image, scaled_svg, formula))
+            });
+        let (mut image, scaled_svg, formula) = match rendered {
+            Ok(rendered) => rendered,
+            Err(err) if self.fallback => {
+                context.warn(err.to_string());
+                // Keep the number so that references and later equations are not affected
+                let number = numbered.then(|| {
+                    let equation = self
+                        .equation
+                        .get_or_insert_with(|| context.register_equation(self.label.clone()));
+                    format!("({})", equation)
+                });
+                let element = self.fallback_element(context, number);
+                return self
+                    .fallback_element
+                    .insert(element)
+                    .render(context, area, style);
+            }
+            Err(err) => return Err(err),
+        };
 
         // Apply positioning
         if let Some(pos) = self.position {
@@ -202,16 +630,440 @@ impl Element for Latex {
         } else {
             image = image.with_alignment(self.alignment);
         }
+        image.set_alt_text(self.alt_text.as_deref().unwrap_or(&self.formula));
+        if self.mathml {
+            image.add_associated_file(render::AssociatedFile {
+                name: "formula.mml".to_owned(),
+                mime_type: "application/mathml+xml".to_owned(),
+                description: "MathML representation of the formula".to_owned(),
+                data: super::mathml::latex_to_mathml(&formula, !self.inline).into_bytes(),
+            });
+        }
 
-        // Render the image
-        let result = image.render(context, area, style);
-        // Add SVG source to RenderResult for reference
-        let mut res = result?;
+        if !numbered {
+            // Render the image
+            let result = image.render(context, area, style);
+            // Add SVG source to RenderResult for reference
+            let mut res = result?;
+            res.svg = Some(scaled_svg);
+            return Ok(res);
+        }
+
+        // Reserve the number column, and the same width on the left for centered formulas so that
+        // they stay centered on the page
+        let column_width = style.str_width(&context.font_cache, NUMBER_COLUMN_TEMPLATE);
+        let mut formula_area = area.clone();
+        if self.alignment == Alignment::Center {
+            formula_area.add_margins(Margins::trbl(0, column_width, 0, column_width));
+        } else {
+            formula_area.set_width(area.size().width - column_width);
+        }
+        let mut res = image.render(context, formula_area, style)?;
         res.svg = Some(scaled_svg);
+        if res.has_more {
+            return Ok(res);
+        }
+
+        let equation = self
+            .equation
+            .get_or_insert_with(|| context.register_equation(self.label.clone()));
+        let number = format!("({})", equation);
+        let line_height = style.line_height(&context.font_cache);
+        let number_width = style.str_width(&context.font_cache, &number);
+        let offset = Position::new(
+            area.size().width - number_width,
+            ((res.size.height - line_height) / 2.0).max(Mm::from(0)),
+        );
+        let mut number_area = area.clone();
+        number_area.add_offset(offset);
+        if !super::Text::new(number)
+            .render(context, number_area, style)?
+            .has_more
+        {
+            res.size = Size::new(
+                area.size().width,
+                res.size.height.max(offset.y + line_height),
+            );
+        }
         Ok(res)
     }
 }
 
+/// The text that determines the width of the column reserved for equation numbers.
+const NUMBER_COLUMN_TEMPLATE: &str = "(00.00)";
+
+/// The maximum nesting depth of macro expansions, to detect recursive macros
+const MAX_MACRO_DEPTH: usize = 64;
+
+/// A registry of user-defined LaTeX macros that are expanded before a formula is rendered.
+///
+/// The macros of a document are registered with [`Document::add_latex_macro`][] and
+/// [`Document::load_latex_preamble`][] and stored in the render [`Context`][].  They are expanded
+/// in all [`Latex`][] formulas of the document before they are sent to MicroTeX.
+///
+/// Macros are expanded like in TeX, but their arguments are always taken from the formula, not
+/// from the expansion of the macro.
+///
+/// # Example
+///
+/// ```
+/// # #[cfg(feature = "latex")]
+/// # {
+/// use genpdfi_extended::elements::LatexMacros;
+///
+/// let macros = LatexMacros::new()
+///     .with_macro("R", 0, r"\mathbb{R}")
+///     .with_macro("norm", 1, r"\left\lVert #1 \right\rVert");
+/// assert_eq!(
+///     macros.expand(r"\norm{x} \in \R").expect("expand"),
+///     r"\left\lVert x \right\rVert \in \mathbb{R}",
+/// );
+/// # }
+/// ```
+///
+/// [`Document::add_latex_macro`]: ../struct.Document.html#method.add_latex_macro
+/// [`Document::load_latex_preamble`]: ../struct.Document.html#method.load_latex_preamble
+/// [`Context`]: ../struct.Context.html
+/// [`Latex`]: struct.Latex.html
+#[derive(Clone, Debug, Default)]
+pub struct LatexMacros {
+    macros: HashMap<String, LatexMacro>,
+}
+
+#[derive(Clone, Debug)]
+struct LatexMacro {
+    arguments: usize,
+    definition: String,
+}
+
+impl LatexMacros {
+    /// Creates a new registry without macros.
+    pub fn new() -> LatexMacros {
+        LatexMacros::default()
+    }
+
+    /// Defines a macro with the given name, number of arguments and definition.
+    ///
+    /// The name consists of letters and may start with a backslash, e.g. `R` or `\R`.  The
+    /// arguments are referenced with `#1` to `#9` in the definition and are taken from the
+    /// formula either as a group in braces or as a single token.  An existing macro with the same
+    /// name is replaced.
+    pub fn define(&mut self, name: &str, arguments: usize, definition: impl Into<String>) {
+        self.macros.insert(
+            name.trim_start_matches('\\').to_owned(),
+            LatexMacro {
+                arguments: arguments.min(9),
+                definition: definition.into(),
+            },
+        );
+    }
+
+    /// Defines a macro, see [`define`][], and returns the registry.
+    ///
+    /// [`define`]: #method.define
+    pub fn with_macro(
+        mut self,
+        name: &str,
+        arguments: usize,
+        definition: impl Into<String>,
+    ) -> LatexMacros {
+        self.define(name, arguments, definition);
+        self
+    }
+
+    /// Defines the macros of the given LaTeX preamble.
+    ///
+    /// The preamble may contain `\newcommand`, `\renewcommand`, `\providecommand`, `\def` and
+    /// `\DeclareMathOperator` definitions and comments.  Optional arguments with default values
+    /// are not supported.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # #[cfg(feature = "latex")]
+    /// # {
+    /// use genpdfi_extended::elements::LatexMacros;
+    ///
+    /// let mut macros = LatexMacros::new();
+    /// macros
+    ///     .load_preamble(
+    ///         r"\newcommand{\E}{\mathbb{E}}
+    ///           \newcommand{\norm}[1]{\left\lVert #1 \right\rVert}
+    ///           \DeclareMathOperator{\tr}{tr}",
+    ///     )
+    ///     .expect("parse preamble");
+    /// assert_eq!(macros.expand(r"\tr A").expect("expand"), r"\operatorname{tr} A");
+    /// # }
+    /// ```
+    pub fn load_preamble(&mut self, preamble: &str) -> Result<(), Error> {
+        let invalid = |msg: &str| {
+            Error::new(
+                format!("Invalid LaTeX preamble: {}", msg),
+                ErrorKind::InvalidData,
+            )
+        };
+
+        let mut rest = skip_space_and_comments(preamble);
+        while !rest.is_empty() {
+            if !rest.starts_with('\\') {
+                return Err(invalid("expected a macro definition"));
+            }
+            let (command, after) = control_sequence(rest);
+            rest = after;
+            match command {
+                "\\newcommand" | "\\renewcommand" | "\\providecommand" => {
+                    rest = rest.strip_prefix('*').unwrap_or(rest);
+                    let (name, after) =
+                        macro_name(rest).ok_or_else(|| invalid("missing macro name"))?;
+                    rest = skip_space_and_comments(after);
+                    let mut arguments = 0;
+                    if let Some(after) = rest.strip_prefix('[') {
+                        let end = after
+                            .find(']')
+                            .ok_or_else(|| invalid("unterminated number of arguments"))?;
+                        arguments = after[..end]
+                            .trim()
+                            .parse()
+                            .ok()
+                            .filter(|n| *n <= 9)
+                            .ok_or_else(|| invalid("invalid number of arguments"))?;
+                        rest = skip_space_and_comments(&after[end + 1..]);
+                        if rest.starts_with('[') {
+                            return Err(invalid("optional arguments are not supported"));
+                        }
+                    }
+                    let (definition, after) =
+                        group(rest).ok_or_else(|| invalid("missing macro definition"))?;
+                    rest = after;
+                    if command != "\\providecommand" || !self.macros.contains_key(name) {
+                        self.define(name, arguments, definition);
+                    }
+                }
+                "\\def" => {
+                    let (name, after) =
+                        macro_name(rest).ok_or_else(|| invalid("missing macro name"))?;
+                    let start = after
+                        .find('{')
+                        .ok_or_else(|| invalid("missing macro definition"))?;
+                    let arguments = after[..start].matches('#').count();
+                    if arguments > 9 {
+                        return Err(invalid("invalid number of arguments"));
+                    }
+                    let (definition, after) = group(&after[start..])
+                        .ok_or_else(|| invalid("missing macro definition"))?;
+                    rest = after;
+                    self.define(name, arguments, definition);
+                }
+                "\\DeclareMathOperator" => {
+                    let star = if let Some(after) = rest.strip_prefix('*') {
+                        rest = after;
+                        "*"
+                    } else {
+                        ""
+                    };
+                    let (name, after) =
+                        macro_name(rest).ok_or_else(|| invalid("missing operator name"))?;
+                    let (text, after) = group(skip_space_and_comments(after))
+                        .ok_or_else(|| invalid("missing operator text"))?;
+                    rest = after;
+                    self.define(name, 0, format!("\\operatorname{}{{{}}}", star, text));
+                }
+                _ => return Err(invalid(&format!("unsupported command {}", command))),
+            }
+            rest = skip_space_and_comments(rest);
+        }
+        Ok(())
+    }
+
+    /// Returns whether no macros have been defined.
+    pub fn is_empty(&self) -> bool {
+        self.macros.is_empty()
+    }
+
+    /// Expands all macros in the given formula.
+    ///
+    /// If a macro is used with missing arguments or if the expansion is nested too deeply, e.g.
+    /// because of a recursive macro, an error with the [`ErrorKind::LatexError`][] kind is
+    /// returned.
+    ///
+    /// [`ErrorKind::LatexError`]: ../error/enum.ErrorKind.html#variant.LatexError
+    pub fn expand(&self, formula: &str) -> Result<String, Error> {
+        if self.macros.is_empty() {
+            return Ok(formula.to_owned());
+        }
+        let mut expanded = String::with_capacity(formula.len());
+        self.expand_into(formula, &mut expanded, 0)
+            .map_err(|message| {
+                Error::new(
+                    format!("Failed to expand LaTeX formula {}: {}", formula, message),
+                    ErrorKind::LatexError {
+                        formula: formula.to_owned(),
+                        message,
+                    },
+                )
+            })?;
+        Ok(expanded)
+    }
+
+    fn expand_into(&self, input: &str, out: &mut String, depth: usize) -> Result<(), String> {
+        if depth > MAX_MACRO_DEPTH {
+            return Err("macro expansion is nested too deeply".to_owned());
+        }
+        let mut rest = input;
+        while let Some(start) = rest.find('\\') {
+            out.push_str(&rest[..start]);
+            let (name, after) = control_sequence(&rest[start..]);
+            rest = after;
+            let definition = match self.macros.get(&name[1..]) {
+                Some(definition) => definition,
+                None => {
+                    out.push_str(name);
+                    continue;
+                }
+            };
+            let mut arguments = Vec::with_capacity(definition.arguments);
+            for _ in 0..definition.arguments {
+                let (argument, after) =
+                    argument(rest).ok_or_else(|| format!("missing argument for macro {}", name))?;
+                arguments.push(argument);
+                rest = after;
+            }
+            self.expand_into(
+                &substitute(&definition.definition, &arguments),
+                out,
+                depth + 1,
+            )?;
+            // keep a control word at the end of the expansion separate from following letters
+            if rest.starts_with(|c: char| c.is_ascii_alphabetic()) && ends_with_control_word(out) {
+                out.push(' ');
+            }
+        }
+        out.push_str(rest);
+        Ok(())
+    }
+}
+
+/// Splits the control sequence, e.g. `\alpha` or `\{`, from the start of the given string that
+/// starts with a backslash.
+pub(super) fn control_sequence(s: &str) -> (&str, &str) {
+    let len = match s[1..].chars().next() {
+        Some(c) if c.is_ascii_alphabetic() => {
+            1 + s[1..]
+                .find(|c: char| !c.is_ascii_alphabetic())
+                .unwrap_or(s.len() - 1)
+        }
+        Some(c) => 1 + c.len_utf8(),
+        None => 1,
+    };
+    s.split_at(len)
+}
+
+fn ends_with_control_word(s: &str) -> bool {
+    let start = s.trim_end_matches(|c: char| c.is_ascii_alphabetic());
+    let backslashes = start.len() - start.trim_end_matches('\\').len();
+    start.len() < s.len() && backslashes % 2 == 1
+}
+
+/// Splits a group in braces from the start of the given string and returns its content.
+pub(super) fn group(s: &str) -> Option<(&str, &str)> {
+    let s = s.strip_prefix('{')?;
+    let mut depth = 0;
+    let mut chars = s.char_indices();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '\\' => {
+                chars.next();
+            }
+            '{' => depth += 1,
+            '}' if depth == 0 => return Some((&s[..i], &s[i + 1..])),
+            '}' => depth -= 1,
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Splits a macro argument, i. e. a group or a single token, from the start of the given
+/// string.
+fn argument(s: &str) -> Option<(&str, &str)> {
+    let s = s.trim_start();
+    match s.chars().next()? {
+        '{' => group(s),
+        '\\' => Some(control_sequence(s)),
+        c => Some(s.split_at(c.len_utf8())),
+    }
+}
+
+/// Splits a macro name, either as a control word or in braces, from the start of the given
+/// string and returns it without the backslash.
+fn macro_name(s: &str) -> Option<(&str, &str)> {
+    let s = skip_space_and_comments(s);
+    let (name, rest) = match group(s) {
+        Some((name, rest)) => (name.trim(), rest),
+        None if s.starts_with('\\') => control_sequence(s),
+        None => return None,
+    };
+    let name = name.strip_prefix('\\')?;
+    if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphabetic()) {
+        return None;
+    }
+    Some((name, rest))
+}
+
+fn skip_space_and_comments(mut s: &str) -> &str {
+    loop {
+        s = s.trim_start();
+        match s.strip_prefix('%') {
+            Some(comment) => s = comment.find('\n').map_or("", |i| &comment[i + 1..]),
+            None => return s,
+        }
+    }
+}
+
+/// Replaces the parameters `#1` to `#9` and `##` in the definition of a macro.
+fn substitute(definition: &str, arguments: &[&str]) -> String {
+    let mut result = String::with_capacity(definition.len());
+    let mut chars = definition.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '#' {
+            result.push(c);
+            continue;
+        }
+        match chars.peek() {
+            Some('#') => {
+                chars.next();
+                result.push('#');
+            }
+            Some(d) if d.is_ascii_digit() && *d != '0' => {
+                let index = d.to_digit(10).unwrap_or_default() as usize - 1;
+                match arguments.get(index) {
+                    Some(argument) => result.push_str(argument),
+                    None => {
+                        result.push('#');
+                        result.push(*d);
+                    }
+                }
+                chars.next();
+            }
+            _ => result.push('#'),
+        }
+    }
+    result
+}
+
+/// Converts a color to the opaque ARGB value used by MicroTeX.
+fn argb(color: style::Color) -> u32 {
+    let (r, g, b) = match color {
+        style::Color::Rgb(r, g, b) => (r, g, b),
+        style::Color::Greyscale(v) => (v, v, v),
+        style::Color::Cmyk(c, m, y, k) => {
+            let channel = |v: u8| (u32::from(255 - v) * u32::from(255 - k) / 255) as u8;
+            (channel(c), channel(m), channel(y))
+        }
+    };
+    0xff00_0000 | (u32::from(r) << 16) | (u32::from(g) << 8) | u32::from(b)
+}
+
 /// Extracts width and height from SVG attributes in pixels.
 fn extract_svg_dimensions(svg: &str) -> Result<(f32, f32), Error> {
     let mut width = None;
@@ -272,7 +1124,7 @@ mod tests {
     #[test]
     fn test_latex_new() {
         let latex = Latex::new(r#"E = mc^2"#, 12.0);
-        assert_eq!(latex.size_pt, 12.0);
+        assert_eq!(latex.size_pt, Some(12.0));
         assert_eq!(latex.formula, r#"E = mc^2"#);
         assert!(!latex.inline);
     }
@@ -289,6 +1141,225 @@ mod tests {
         assert!(latex.inline);
     }
 
+    #[test]
+    fn test_latex_style() {
+        let latex = Latex::new_styled(r#"a + b"#);
+        assert_eq!(latex.size_pt, None);
+        assert_eq!(latex.color, None);
+        let latex = latex.with_color(style::Color::Rgb(255, 0, 0));
+        assert_eq!(latex.color, Some(style::Color::Rgb(255, 0, 0)));
+
+        assert_eq!(argb(style::Color::Rgb(0, 0, 0)), 0xff000000);
+        assert_eq!(argb(style::Color::Rgb(0x12, 0x34, 0x56)), 0xff123456);
+        assert_eq!(argb(style::Color::Greyscale(0x80)), 0xff808080);
+        assert_eq!(argb(style::Color::Cmyk(255, 0, 0, 0)), 0xff00ffff);
+        assert_eq!(argb(style::Color::Cmyk(0, 0, 0, 255)), 0xff000000);
+    }
+
+    #[test]
+    fn test_latex_numbered() {
+        let latex = Latex::new(r#"a + b"#, 10.0);
+        assert!(!latex.numbered);
+        assert!(Latex::new(r#"a + b"#, 10.0).numbered().numbered);
+        let latex = latex.with_label("eq:sum");
+        assert!(latex.numbered);
+        assert_eq!(latex.label.as_deref(), Some("eq:sum"));
+        assert_eq!(latex.equation, None);
+    }
+
+    #[test]
+    fn test_latex_mathml() {
+        assert!(!Latex::new(r#"a + b"#, 10.0).mathml);
+        assert!(Latex::new(r#"a + b"#, 10.0).with_mathml(true).mathml);
+    }
+
+    #[test]
+    fn test_latex_alt_text() {
+        let mut latex = Latex::new(r#"a + b"#, 10.0);
+        assert_eq!(latex.alt_text, None);
+        latex.set_alt_text("a plus b");
+        assert_eq!(latex.alt_text.as_deref(), Some("a plus b"));
+        let latex = latex.with_alt_text("sum of a and b");
+        assert_eq!(latex.alt_text.as_deref(), Some("sum of a and b"));
+    }
+
+    #[test]
+    fn test_latex_cache() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let mut cache = FormulaCache {
+            directory: Some(dir.path().join("formulas")),
+            reference_digest: Some(vec![1; 32]),
+            ..Default::default()
+        };
+        let key = ("x_{cached}".to_owned(), 11.0f32.to_bits(), 0xff000000);
+        let file = cache.file(&key).expect("cache file");
+        assert_eq!(cache.file(&key), Some(file.clone()));
+        let other = ("x_{cached}".to_owned(), 11.0f32.to_bits(), 0xffff0000);
+        assert_ne!(cache.file(&other), Some(file.clone()));
+        assert_eq!(FormulaCache::default().file(&key), None);
+
+        // Files written with another MicroTeX version are not used
+        let mut upgraded = FormulaCache {
+            directory: cache.directory.clone(),
+            reference_digest: Some(vec![2; 32]),
+            ..Default::default()
+        };
+        assert_ne!(upgraded.file(&key), Some(file.clone()));
+
+        // Formulas in the cache directory are used without invoking MicroTeX
+        let svg = r#"<svg width="10" height="10"></svg>"#;
+        write_cache_file(&file, svg).expect("write");
+        assert_eq!(cache.get(&key).as_deref(), Some(svg));
+
+        // ... and kept in memory afterwards
+        fs::remove_file(&file).expect("remove");
+        assert_eq!(cache.get(&key).as_deref(), Some(svg));
+
+        // New formulas are persisted without leaving temporary files behind
+        cache.insert(other.clone(), svg.to_owned());
+        let files = fs::read_dir(dir.path().join("formulas"))
+            .expect("read dir")
+            .map(|entry| entry.expect("entry").path())
+            .collect::<Vec<_>>();
+        assert_eq!(files, cache.file(&other).into_iter().collect::<Vec<_>>());
+        cache.formulas.clear();
+        assert_eq!(cache.get(&other).as_deref(), Some(svg));
+        assert_eq!(cache.get(&key), None);
+    }
+
+    #[test]
+    fn test_latex_fallback() {
+        let latex = Latex::new(r#"\frac{1}{"#, 12.0);
+        assert!(!latex.fallback);
+        let latex = latex.with_fallback();
+        assert!(latex.fallback);
+        assert!(latex.fallback_font_family.is_none());
+
+        let (r, context) = crate::test_util::renderer(Size::new(200.0, 200.0));
+        let style = style::Style::new();
+        let area = r.first_page().first_layer().area();
+
+        // The source is printed in the built-in Courier font by default
+        let mut courier = style;
+        courier.set_font_override(context.font_cache.source_font_family());
+        assert_eq!(
+            courier.str_width(&context.font_cache, "iii"),
+            courier.str_width(&context.font_cache, "MMM")
+        );
+        let result = latex
+            .fallback_element(&context, None)
+            .render(&context, area.clone(), style)
+            .expect("render");
+        let text_width = courier.str_width(&context.font_cache, &latex.formula);
+        assert!(result.size.width > text_width);
+
+        let family = context.font_cache.default_font_family();
+        let latex = latex.with_fallback_font_family(family);
+        assert!(latex.fallback);
+        let result = latex
+            .fallback_element(&context, None)
+            .render(&context, area.clone(), style)
+            .expect("render");
+        assert!(!result.has_more);
+        let text_width = style.str_width(&context.font_cache, &latex.formula);
+        assert!(result.size.width > text_width);
+        assert!(result.size.height > style.line_height(&context.font_cache));
+
+        // Numbered formulas keep their number
+        let mut numbered = Latex::new(r#"\frac{1}{"#, 12.0)
+            .with_label("eq:broken")
+            .with_fallback();
+        let result = numbered.render(&context, area, style).expect("fallback");
+        assert!(result.size.height > style.line_height(&context.font_cache) * 2.0);
+        assert_eq!(context.take_warnings().len(), 1);
+        let equation = context.equation("eq:broken").expect("equation");
+        assert_eq!(equation.to_string(), "1");
+    }
+
+    #[test]
+    fn test_latex_macros() {
+        let macros = LatexMacros::new();
+        assert!(macros.is_empty());
+        assert_eq!(macros.expand(r"\R").expect("expand"), r"\R");
+
+        let mut macros = macros
+            .with_macro(r"\R", 0, r"\mathbb{R}")
+            .with_macro("abs", 1, r"\left| #1 \right|")
+            .with_macro("pair", 2, r"(#1, #2)")
+            .with_macro("x", 0, r"\alpha")
+            .with_macro("id", 1, "#1")
+            .with_macro("loop", 0, r"\loop");
+        assert!(!macros.is_empty());
+        assert_eq!(
+            macros.expand(r"\abs{\frac{a}{b}} \in \R").expect("expand"),
+            r"\left| \frac{a}{b} \right| \in \mathbb{R}"
+        );
+        assert_eq!(
+            macros
+                .expand(r"\pair a\R + \pair{\abs{x}}{\{y\}}")
+                .expect("expand"),
+            r"(a, \mathbb{R}) + (\left| x \right|, \{y\})"
+        );
+        // Only complete control words are expanded
+        assert_eq!(macros.expand(r"\Rx \\R").expect("expand"), r"\Rx \\R");
+        assert_eq!(
+            macros.expand(r"\x{}b \pair\x b").expect("expand"),
+            r"\alpha{}b (\alpha, b)"
+        );
+        assert_eq!(
+            macros.expand(r"\abs\x y").expect("expand"),
+            r"\left| \alpha \right| y"
+        );
+        assert_eq!(
+            macros.expand(r"\pair\x\x y").expect("expand"),
+            r"(\alpha, \alpha) y"
+        );
+        assert_eq!(macros.expand(r"2\x y").expect("expand"), r"2\alpha y");
+        assert_eq!(macros.expand(r"\id{\x}y").expect("expand"), r"\alpha y");
+        assert_eq!(macros.expand(r"\id{##}").expect("expand"), r"##");
+
+        let err = macros.expand(r"1 + \abs").expect_err("missing argument");
+        match err.kind() {
+            ErrorKind::LatexError { formula, message } => {
+                assert_eq!(formula, r"1 + \abs");
+                assert!(message.contains(r"\abs"));
+            }
+            kind => panic!("unexpected error kind: {:?}", kind),
+        }
+        assert!(macros.expand(r"\loop").is_err());
+
+        macros
+            .load_preamble(
+                r"% house macros
+                \newcommand{\E}{\mathbb{E}}
+                \renewcommand\abs[1]{\lvert #1 \rvert} % replaced
+                \providecommand{\R}{\mathbb{Q}}
+                \newcommand*{\inner}[ 2 ]{\langle #1, #2 \rangle}
+                \def\set#1{\{ #1 \}}
+                \DeclareMathOperator*{\argmax}{arg\,max}",
+            )
+            .expect("load preamble");
+        assert_eq!(
+            macros
+                .expand(r"\E[\abs{X}] \in \R \inner{u}{v} \set{1} \argmax_x")
+                .expect("expand"),
+            r"\mathbb{E}[\lvert X \rvert] \in \mathbb{R} \langle u, v \rangle \{ 1 \} \operatorname*{arg\,max}_x"
+        );
+
+        for preamble in [
+            r"\newcommand{\a}[1][x]{#1}",
+            r"\newcommand{\a}[10]{#1}",
+            r"\newcommand{\a}{",
+            r"\usepackage{amsmath}",
+            r"text",
+        ] {
+            let err = LatexMacros::new()
+                .load_preamble(preamble)
+                .expect_err(preamble);
+            assert!(matches!(err.kind(), ErrorKind::InvalidData));
+        }
+    }
+
     #[test]
     fn test_latex_block() {
         let latex = Latex::new(r#"a + b"#, 10.0).inline().block();
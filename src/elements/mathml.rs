@@ -0,0 +1,908 @@
+// Copyright (c) 2026 Ronan Le Meillat - SCTG Development
+//
+// SPDX-License-Identifier: MIT OR Apache-2.0
+// Licensed under the MIT License or the Apache License, Version 2.0
+
+//! Conversion of LaTeX formulas to MathML.
+//!
+//! The MathML is embedded next to the rendered formulas so that screen readers and extraction
+//! tools can recover the math, see [`Latex`][].  The converter produces presentation MathML for
+//! the commonly used subset of LaTeX math: identifiers, numbers and operators, sub- and
+//! superscripts, fractions, roots, `\left`/`\right` delimiters, accents, font commands, text,
+//! spacing and matrix-like environments.  Unknown commands are kept as text.  The LaTeX source is
+//! added as an `application/x-tex` annotation.
+//!
+//! [`Latex`]: ../struct.Latex.html
+
+use super::latex::{control_sequence, group};
+
+/// A token of a LaTeX formula.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Token<'a> {
+    /// A control sequence including the backslash, e.g. `\alpha` or `\{`
+    Command(&'a str),
+    Open,
+    Close,
+    Sup,
+    Sub,
+    Align,
+    Prime,
+    Number(&'a str),
+    Letter(char),
+    Other(char),
+}
+
+/// Converts the given LaTeX formula to a MathML `<math>` element.
+///
+/// Math delimiters around the formula (`$…$`, `$$…$$`, `\(…\)` and `\[…\]`) are removed.  If
+/// `display` is set, the formula is marked as a display formula.
+pub(crate) fn latex_to_mathml(formula: &str, display: bool) -> String {
+    let mut parser = Parser {
+        rest: strip_math_delimiters(formula),
+        display,
+        variant: None,
+    };
+    let content = row(parser.parse_all());
+    format!(
+        concat!(
+            "<math xmlns=\"http://www.w3.org/1998/Math/MathML\" display=\"{}\">",
+            "<semantics>{}<annotation encoding=\"application/x-tex\">{}</annotation></semantics>",
+            "</math>"
+        ),
+        if display { "block" } else { "inline" },
+        content,
+        escape(formula.trim())
+    )
+}
+
+fn strip_math_delimiters(formula: &str) -> &str {
+    let formula = formula.trim();
+    for (start, end) in [("$$", "$$"), ("\\[", "\\]"), ("\\(", "\\)"), ("$", "$")] {
+        if formula.len() >= start.len() + end.len() {
+            if let Some(inner) = formula
+                .strip_prefix(start)
+                .and_then(|s| s.strip_suffix(end))
+            {
+                return inner;
+            }
+        }
+    }
+    formula
+}
+
+struct Parser<'a> {
+    rest: &'a str,
+    display: bool,
+    /// The `mathvariant` set by a font command like `\mathbb`
+    variant: Option<&'static str>,
+}
+
+impl<'a> Parser<'a> {
+    fn peek_token(&self) -> Option<Token<'a>> {
+        let mut parser = Parser {
+            rest: self.rest,
+            display: self.display,
+            variant: None,
+        };
+        parser.next_token()
+    }
+
+    fn next_token(&mut self) -> Option<Token<'a>> {
+        self.rest = self.rest.trim_start();
+        let c = self.rest.chars().next()?;
+        let len = match c {
+            '\\' => {
+                let (name, rest) = control_sequence(self.rest);
+                self.rest = rest;
+                return Some(Token::Command(name));
+            }
+            '0'..='9' => {
+                let mut len = self
+                    .rest
+                    .find(|c: char| !c.is_ascii_digit())
+                    .unwrap_or(self.rest.len());
+                // Include a decimal part
+                let decimals = self.rest[len..]
+                    .strip_prefix('.')
+                    .map(|s| s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len()));
+                if let Some(decimals) = decimals.filter(|n| *n > 0) {
+                    len += 1 + decimals;
+                }
+                let (number, rest) = self.rest.split_at(len);
+                self.rest = rest;
+                return Some(Token::Number(number));
+            }
+            c => c.len_utf8(),
+        };
+        self.rest = &self.rest[len..];
+        Some(match c {
+            '{' => Token::Open,
+            '}' => Token::Close,
+            '^' => Token::Sup,
+            '_' => Token::Sub,
+            '&' => Token::Align,
+            '\'' => Token::Prime,
+            c if c.is_alphabetic() => Token::Letter(c),
+            c => Token::Other(c),
+        })
+    }
+
+    /// Returns the content of the group in braces at the current position without parsing it.
+    fn raw_group(&mut self) -> Option<&'a str> {
+        let (content, rest) = group(self.rest.trim_start())?;
+        self.rest = rest;
+        Some(content)
+    }
+
+    /// Returns the content of the optional argument in brackets at the current position.
+    fn optional_argument(&mut self) -> Option<&'a str> {
+        let rest = self.rest.trim_start().strip_prefix('[')?;
+        let end = rest.find(']')?;
+        self.rest = &rest[end + 1..];
+        Some(&rest[..end])
+    }
+
+    /// Parses the complete input, skipping unmatched closing tokens.
+    fn parse_all(&mut self) -> Vec<String> {
+        let mut items = Vec::new();
+        loop {
+            items.extend(self.parse_row());
+            if self.next_token().is_none() {
+                return items;
+            }
+        }
+    }
+
+    /// Parses elements until the end of the input or a terminating token, which is not consumed.
+    fn parse_row(&mut self) -> Vec<String> {
+        let mut items = Vec::new();
+        while let Some(token) = self.peek_token() {
+            if is_terminator(token) {
+                break;
+            }
+            self.next_token();
+            items.push(self.parse_element(token));
+        }
+        items
+    }
+
+    /// Parses the element that starts with the given token, including its scripts.
+    fn parse_element(&mut self, token: Token<'a>) -> String {
+        let (mut sub, mut sup, mut primes) = (None, None, String::new());
+        let (base, mut limits) = match token {
+            // A script without base, e.g. `{}^{14}C` or `^2`
+            Token::Sup => {
+                sup = Some(self.parse_argument());
+                ("<mrow></mrow>".to_owned(), false)
+            }
+            Token::Sub => {
+                sub = Some(self.parse_argument());
+                ("<mrow></mrow>".to_owned(), false)
+            }
+            Token::Command(name) => (self.parse_command(name), has_limits(name) && self.display),
+            token => (self.parse_atom(token), false),
+        };
+        loop {
+            match self.peek_token() {
+                Some(Token::Sup) if sup.is_none() => {
+                    self.next_token();
+                    sup = Some(self.parse_argument());
+                }
+                Some(Token::Sub) if sub.is_none() => {
+                    self.next_token();
+                    sub = Some(self.parse_argument());
+                }
+                Some(Token::Prime) => {
+                    self.next_token();
+                    primes.push('′');
+                }
+                Some(Token::Command("\\limits")) => {
+                    self.next_token();
+                    limits = true;
+                }
+                Some(Token::Command("\\nolimits")) => {
+                    self.next_token();
+                    limits = false;
+                }
+                _ => break,
+            }
+        }
+        if !primes.is_empty() {
+            let primes = mo(&primes);
+            sup = Some(match sup {
+                Some(sup) => format!("<mrow>{}{}</mrow>", primes, sup),
+                None => primes,
+            });
+        }
+        let (under, over) = if limits {
+            ("munder", "mover")
+        } else {
+            ("msub", "msup")
+        };
+        match (sub, sup) {
+            (None, None) => base,
+            (Some(sub), None) => format!("<{0}>{1}{2}</{0}>", under, base, sub),
+            (None, Some(sup)) => format!("<{0}>{1}{2}</{0}>", over, base, sup),
+            (Some(sub), Some(sup)) => {
+                let both = if limits { "munderover" } else { "msubsup" };
+                format!("<{0}>{1}{2}{3}</{0}>", both, base, sub, sup)
+            }
+        }
+    }
+
+    /// Parses a group or a single token without scripts, e.g. the argument of `\frac`.
+    fn parse_argument(&mut self) -> String {
+        match self.next_token() {
+            Some(Token::Open) => {
+                let items = self.parse_row();
+                if let Some(Token::Close) = self.peek_token() {
+                    self.next_token();
+                }
+                row(items)
+            }
+            Some(Token::Command(name)) => self.parse_command(name),
+            Some(token) if !is_terminator(token) => self.parse_atom(token),
+            _ => "<mrow></mrow>".to_owned(),
+        }
+    }
+
+    fn parse_atom(&mut self, token: Token<'a>) -> String {
+        match token {
+            Token::Open => {
+                let items = self.parse_row();
+                if let Some(Token::Close) = self.peek_token() {
+                    self.next_token();
+                }
+                row(items)
+            }
+            Token::Letter(c) => mi(&c.to_string(), self.variant),
+            Token::Number(n) => match self.variant {
+                Some(variant) => format!("<mn mathvariant=\"{}\">{}</mn>", variant, n),
+                None => format!("<mn>{}</mn>", n),
+            },
+            Token::Prime => mo("′"),
+            Token::Other('-') => mo("−"),
+            Token::Other('*') => mo("∗"),
+            Token::Other(c) => mo(&c.to_string()),
+            Token::Command(name) => self.parse_command(name),
+            Token::Sup | Token::Sub | Token::Align | Token::Close => String::new(),
+        }
+    }
+
+    fn parse_command(&mut self, name: &'a str) -> String {
+        match name {
+            "\\frac" | "\\dfrac" | "\\tfrac" | "\\cfrac" => {
+                let numerator = self.parse_argument();
+                let denominator = self.parse_argument();
+                format!("<mfrac>{}{}</mfrac>", numerator, denominator)
+            }
+            "\\binom" | "\\dbinom" | "\\tbinom" => {
+                let n = self.parse_argument();
+                let k = self.parse_argument();
+                format!(
+                    "<mrow>{}<mfrac linethickness=\"0\">{}{}</mfrac>{}</mrow>",
+                    mo("("),
+                    n,
+                    k,
+                    mo(")")
+                )
+            }
+            "\\sqrt" => match self.optional_argument() {
+                Some(index) => {
+                    let index = row(self.sub_parser(index).parse_all());
+                    let radicand = self.parse_argument();
+                    format!("<mroot>{}{}</mroot>", radicand, index)
+                }
+                None => format!("<msqrt>{}</msqrt>", self.parse_argument()),
+            },
+            "\\left" => {
+                let open = self.delimiter();
+                let items = self.parse_row();
+                let close = match self.peek_token() {
+                    Some(Token::Command("\\right")) => {
+                        self.next_token();
+                        self.delimiter()
+                    }
+                    _ => String::new(),
+                };
+                let mut content = String::from("<mrow>");
+                if !open.is_empty() {
+                    content.push_str(&fence(&open));
+                }
+                content.push_str(&items.concat());
+                if !close.is_empty() {
+                    content.push_str(&fence(&close));
+                }
+                content.push_str("</mrow>");
+                content
+            }
+            "\\big" | "\\Big" | "\\bigg" | "\\Bigg" | "\\bigl" | "\\bigr" | "\\Bigl" | "\\Bigr"
+            | "\\biggl" | "\\biggr" | "\\Biggl" | "\\Biggr" | "\\middle" => {
+                let delimiter = self.delimiter();
+                mo(&delimiter)
+            }
+            "\\text" | "\\textrm" | "\\textit" | "\\textbf" | "\\textsf" | "\\texttt"
+            | "\\mbox" | "\\hbox" => {
+                let text = self.raw_group().unwrap_or_default();
+                format!("<mtext>{}</mtext>", escape(text))
+            }
+            "\\operatorname" | "\\mathop" => {
+                if self.rest.starts_with('*') {
+                    self.rest = &self.rest[1..];
+                }
+                let text = self.raw_group().unwrap_or_default();
+                let text: String = text
+                    .replace("\\,", " ")
+                    .chars()
+                    .filter(|c| *c != '\\' && *c != '{' && *c != '}')
+                    .collect();
+                mi(text.trim(), None)
+            }
+            "\\begin" => {
+                let environment = self.raw_group().unwrap_or_default();
+                self.parse_environment(environment)
+            }
+            "\\not" => match self.peek_token() {
+                Some(Token::Other('=')) => {
+                    self.next_token();
+                    mo("≠")
+                }
+                Some(Token::Other(c)) => {
+                    self.next_token();
+                    mo(&format!("{}\u{338}", c))
+                }
+                Some(Token::Command(name)) if symbol(name).is_some() => {
+                    self.next_token();
+                    let (_, symbol) = symbol(name).unwrap_or_default();
+                    mo(&format!("{}\u{338}", symbol))
+                }
+                _ => String::new(),
+            },
+            "\\displaystyle"
+            | "\\textstyle"
+            | "\\scriptstyle"
+            | "\\scriptscriptstyle"
+            | "\\limits"
+            | "\\nolimits"
+            | "\\\\"
+            | "\\right"
+            | "\\end" => String::new(),
+            name => {
+                if let Some(variant) = font_variant(name) {
+                    let previous = self.variant.replace(variant);
+                    let argument = self.parse_argument();
+                    self.variant = previous;
+                    argument
+                } else if let Some((accent, over)) = accent(name) {
+                    let argument = self.parse_argument();
+                    if over {
+                        format!(
+                            "<mover accent=\"true\">{}<mo stretchy=\"false\">{}</mo></mover>",
+                            argument, accent
+                        )
+                    } else {
+                        format!(
+                            "<munder accentunder=\"true\">{}<mo stretchy=\"false\">{}</mo></munder>",
+                            argument, accent
+                        )
+                    }
+                } else if let Some(width) = space(name) {
+                    format!("<mspace width=\"{}\"></mspace>", width)
+                } else if let Some((is_identifier, symbol)) = symbol(name) {
+                    if is_identifier {
+                        mi(symbol, self.variant)
+                    } else if has_limits(name) {
+                        format!("<mo movablelimits=\"true\">{}</mo>", symbol)
+                    } else {
+                        mo(symbol)
+                    }
+                } else if FUNCTIONS.contains(&&name[1..]) {
+                    mi(&name[1..], None)
+                } else {
+                    format!("<mtext>{}</mtext>", escape(name))
+                }
+            }
+        }
+    }
+
+    /// Parses a matrix-like environment after its `\begin{…}` command.
+    fn parse_environment(&mut self, environment: &str) -> String {
+        let environment = environment.trim_end_matches('*');
+        if environment == "array" {
+            let _ = self.raw_group();
+        }
+        let mut rows = Vec::new();
+        let mut cells = Vec::new();
+        loop {
+            cells.push(format!("<mtd>{}</mtd>", self.parse_row().concat()));
+            match self.next_token() {
+                Some(Token::Align) => {}
+                Some(Token::Command("\\\\")) => rows.push(std::mem::take(&mut cells)),
+                Some(Token::Command("\\end")) => {
+                    let _ = self.raw_group();
+                    break;
+                }
+                // Unmatched closing tokens are skipped
+                Some(_) => {}
+                None => break,
+            }
+        }
+        // A line break before the end of the environment does not start a new row
+        if cells != ["<mtd></mtd>"] || rows.is_empty() {
+            rows.push(cells);
+        }
+
+        let mut table = String::from(if environment == "cases" {
+            "<mtable columnalign=\"left left\">"
+        } else {
+            "<mtable>"
+        });
+        for cells in rows {
+            table.push_str("<mtr>");
+            table.push_str(&cells.concat());
+            table.push_str("</mtr>");
+        }
+        table.push_str("</mtable>");
+
+        let (open, close) = match environment {
+            "pmatrix" => ("(", ")"),
+            "bmatrix" => ("[", "]"),
+            "Bmatrix" => ("{", "}"),
+            "vmatrix" => ("|", "|"),
+            "Vmatrix" => ("‖", "‖"),
+            "cases" => ("{", ""),
+            _ => return table,
+        };
+        let mut result = format!("<mrow>{}{}", fence(open), table);
+        if !close.is_empty() {
+            result.push_str(&fence(close));
+        }
+        result.push_str("</mrow>");
+        result
+    }
+
+    /// Reads the delimiter after `\left`, `\right` or a size command.
+    fn delimiter(&mut self) -> String {
+        match self.next_token() {
+            Some(Token::Other('.')) | None => String::new(),
+            Some(Token::Other(c)) => c.to_string(),
+            Some(Token::Command(name)) => match name {
+                "\\{" | "\\lbrace" => "{".to_owned(),
+                "\\}" | "\\rbrace" => "}".to_owned(),
+                "\\|" => "‖".to_owned(),
+                name => symbol(name)
+                    .map(|(_, symbol)| symbol.to_owned())
+                    .unwrap_or_default(),
+            },
+            Some(_) => String::new(),
+        }
+    }
+
+    fn sub_parser(&self, input: &'a str) -> Parser<'a> {
+        Parser {
+            rest: input,
+            display: self.display,
+            variant: self.variant,
+        }
+    }
+}
+
+fn is_terminator(token: Token<'_>) -> bool {
+    matches!(
+        token,
+        Token::Close
+            | Token::Align
+            | Token::Command("\\\\")
+            | Token::Command("\\end")
+            | Token::Command("\\right")
+    )
+}
+
+/// Wraps the given elements in an `<mrow>` unless it is a single element.
+fn row(mut items: Vec<String>) -> String {
+    items.retain(|item| !item.is_empty());
+    if items.len() == 1 {
+        items.remove(0)
+    } else {
+        format!("<mrow>{}</mrow>", items.concat())
+    }
+}
+
+fn mi(text: &str, variant: Option<&str>) -> String {
+    // Identifiers with more than one character are upright by default
+    let variant = variant.or_else(|| {
+        let upright = text.chars().count() == 1 && ('Α'..='Ω').contains(&text.chars().next()?);
+        upright.then_some("normal")
+    });
+    match variant {
+        Some(variant) => format!("<mi mathvariant=\"{}\">{}</mi>", variant, escape(text)),
+        None => format!("<mi>{}</mi>", escape(text)),
+    }
+}
+
+fn mo(text: &str) -> String {
+    format!("<mo>{}</mo>", escape(text))
+}
+
+fn fence(text: &str) -> String {
+    format!("<mo fence=\"true\">{}</mo>", escape(text))
+}
+
+fn escape(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '&' => escaped.push_str("&amp;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Returns whether the scripts of the given command are placed below and above it in display
+/// formulas.
+fn has_limits(name: &str) -> bool {
+    matches!(
+        name,
+        "\\sum"
+            | "\\prod"
+            | "\\coprod"
+            | "\\bigcup"
+            | "\\bigcap"
+            | "\\bigoplus"
+            | "\\bigotimes"
+            | "\\bigvee"
+            | "\\bigwedge"
+            | "\\lim"
+            | "\\liminf"
+            | "\\limsup"
+            | "\\max"
+            | "\\min"
+            | "\\sup"
+            | "\\inf"
+            | "\\det"
+            | "\\gcd"
+            | "\\Pr"
+    )
+}
+
+/// The functions that are printed as upright identifiers.
+const FUNCTIONS: &[&str] = &[
+    "arccos", "arcsin", "arctan", "arg", "cos", "cosh", "cot", "coth", "csc", "deg", "det", "dim",
+    "exp", "gcd", "hom", "inf", "ker", "lg", "lim", "liminf", "limsup", "ln", "log", "max", "min",
+    "Pr", "sec", "sin", "sinh", "sup", "tan", "tanh",
+];
+
+/// Returns the `mathvariant` of a font command.
+fn font_variant(name: &str) -> Option<&'static str> {
+    Some(match name {
+        "\\mathbb" => "double-struck",
+        "\\mathbf" | "\\boldsymbol" | "\\bm" => "bold",
+        "\\mathrm" | "\\mathup" => "normal",
+        "\\mathit" => "italic",
+        "\\mathcal" => "script",
+        "\\mathscr" => "script",
+        "\\mathfrak" => "fraktur",
+        "\\mathsf" => "sans-serif",
+        "\\mathtt" => "monospace",
+        _ => return None,
+    })
+}
+
+/// Returns the accent character of an accent command and whether it is placed above the base.
+fn accent(name: &str) -> Option<(&'static str, bool)> {
+    Some(match name {
+        "\\hat" | "\\widehat" => ("^", true),
+        "\\bar" | "\\overline" => ("‾", true),
+        "\\vec" | "\\overrightarrow" => ("→", true),
+        "\\overleftarrow" => ("←", true),
+        "\\dot" => ("˙", true),
+        "\\ddot" => ("¨", true),
+        "\\tilde" | "\\widetilde" => ("~", true),
+        "\\check" => ("ˇ", true),
+        "\\breve" => ("˘", true),
+        "\\acute" => ("´", true),
+        "\\grave" => ("`", true),
+        "\\overbrace" => ("⏞", true),
+        "\\underline" => ("_", false),
+        "\\underbrace" => ("⏟", false),
+        _ => return None,
+    })
+}
+
+/// Returns the width of a spacing command.
+fn space(name: &str) -> Option<&'static str> {
+    Some(match name {
+        "\\," | "\\thinspace" => "0.1667em",
+        "\\:" | "\\>" | "\\medspace" => "0.2222em",
+        "\\;" | "\\thickspace" => "0.2778em",
+        "\\!" | "\\negthinspace" => "-0.1667em",
+        "\\ " => "0.25em",
+        "\\quad" => "1em",
+        "\\qquad" => "2em",
+        _ => return None,
+    })
+}
+
+/// Returns the character of a symbol command and whether it is an identifier or an operator.
+fn symbol(name: &str) -> Option<(bool, &'static str)> {
+    let identifier = |s| Some((true, s));
+    let operator = |s| Some((false, s));
+    match &name[1..] {
+        // Greek letters
+        "alpha" => identifier("α"),
+        "beta" => identifier("β"),
+        "gamma" => identifier("γ"),
+        "delta" => identifier("δ"),
+        "epsilon" => identifier("ϵ"),
+        "varepsilon" => identifier("ε"),
+        "zeta" => identifier("ζ"),
+        "eta" => identifier("η"),
+        "theta" => identifier("θ"),
+        "vartheta" => identifier("ϑ"),
+        "iota" => identifier("ι"),
+        "kappa" => identifier("κ"),
+        "lambda" => identifier("λ"),
+        "mu" => identifier("μ"),
+        "nu" => identifier("ν"),
+        "xi" => identifier("ξ"),
+        "omicron" => identifier("ο"),
+        "pi" => identifier("π"),
+        "varpi" => identifier("ϖ"),
+        "rho" => identifier("ρ"),
+        "varrho" => identifier("ϱ"),
+        "sigma" => identifier("σ"),
+        "varsigma" => identifier("ς"),
+        "tau" => identifier("τ"),
+        "upsilon" => identifier("υ"),
+        "phi" => identifier("ϕ"),
+        "varphi" => identifier("φ"),
+        "chi" => identifier("χ"),
+        "psi" => identifier("ψ"),
+        "omega" => identifier("ω"),
+        "Gamma" => identifier("Γ"),
+        "Delta" => identifier("Δ"),
+        "Theta" => identifier("Θ"),
+        "Lambda" => identifier("Λ"),
+        "Xi" => identifier("Ξ"),
+        "Pi" => identifier("Π"),
+        "Sigma" => identifier("Σ"),
+        "Upsilon" => identifier("Υ"),
+        "Phi" => identifier("Φ"),
+        "Psi" => identifier("Ψ"),
+        "Omega" => identifier("Ω"),
+        // Other identifiers
+        "infty" => identifier("∞"),
+        "partial" => identifier("∂"),
+        "nabla" => identifier("∇"),
+        "emptyset" | "varnothing" => identifier("∅"),
+        "hbar" => identifier("ℏ"),
+        "ell" => identifier("ℓ"),
+        "aleph" => identifier("ℵ"),
+        "Re" => identifier("ℜ"),
+        "Im" => identifier("ℑ"),
+        "imath" => identifier("ı"),
+        "jmath" => identifier("ȷ"),
+        // Large operators
+        "sum" => operator("∑"),
+        "prod" => operator("∏"),
+        "coprod" => operator("∐"),
+        "int" => operator("∫"),
+        "iint" => operator("∬"),
+        "iiint" => operator("∭"),
+        "oint" => operator("∮"),
+        "bigcup" => operator("⋃"),
+        "bigcap" => operator("⋂"),
+        "bigoplus" => operator("⨁"),
+        "bigotimes" => operator("⨂"),
+        "bigvee" => operator("⋁"),
+        "bigwedge" => operator("⋀"),
+        // Binary operators and relations
+        "pm" => operator("±"),
+        "mp" => operator("∓"),
+        "times" => operator("×"),
+        "div" => operator("÷"),
+        "cdot" => operator("⋅"),
+        "ast" => operator("∗"),
+        "star" => operator("⋆"),
+        "circ" => operator("∘"),
+        "bullet" => operator("∙"),
+        "le" | "leq" => operator("≤"),
+        "ge" | "geq" => operator("≥"),
+        "ne" | "neq" => operator("≠"),
+        "approx" => operator("≈"),
+        "equiv" => operator("≡"),
+        "sim" => operator("∼"),
+        "simeq" => operator("≃"),
+        "cong" => operator("≅"),
+        "propto" => operator("∝"),
+        "ll" => operator("≪"),
+        "gg" => operator("≫"),
+        "subset" => operator("⊂"),
+        "subseteq" => operator("⊆"),
+        "supset" => operator("⊃"),
+        "supseteq" => operator("⊇"),
+        "in" => operator("∈"),
+        "notin" => operator("∉"),
+        "ni" => operator("∋"),
+        "cup" => operator("∪"),
+        "cap" => operator("∩"),
+        "setminus" => operator("∖"),
+        "wedge" | "land" => operator("∧"),
+        "vee" | "lor" => operator("∨"),
+        "neg" | "lnot" => operator("¬"),
+        "oplus" => operator("⊕"),
+        "otimes" => operator("⊗"),
+        "forall" => operator("∀"),
+        "exists" => operator("∃"),
+        "to" | "rightarrow" => operator("→"),
+        "leftarrow" | "gets" => operator("←"),
+        "Rightarrow" => operator("⇒"),
+        "Leftarrow" => operator("⇐"),
+        "leftrightarrow" => operator("↔"),
+        "Leftrightarrow" | "iff" => operator("⇔"),
+        "implies" => operator("⟹"),
+        "mapsto" => operator("↦"),
+        "mid" => operator("∣"),
+        "parallel" => operator("∥"),
+        "perp" => operator("⊥"),
+        "angle" => operator("∠"),
+        "colon" => operator(":"),
+        "prime" => operator("′"),
+        "dots" | "ldots" => operator("…"),
+        "cdots" => operator("⋯"),
+        "vdots" => operator("⋮"),
+        "ddots" => operator("⋱"),
+        // Delimiters
+        "langle" => operator("⟨"),
+        "rangle" => operator("⟩"),
+        "lvert" | "rvert" | "vert" => operator("|"),
+        "lVert" | "rVert" | "Vert" => operator("‖"),
+        "lfloor" => operator("⌊"),
+        "rfloor" => operator("⌋"),
+        "lceil" => operator("⌈"),
+        "rceil" => operator("⌉"),
+        "{" | "lbrace" => operator("{"),
+        "}" | "rbrace" => operator("}"),
+        "|" => operator("‖"),
+        // Escaped characters
+        "#" => identifier("#"),
+        "%" => identifier("%"),
+        "&" => operator("&"),
+        "_" => identifier("_"),
+        "$" => identifier("$"),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Returns the content of the `<semantics>` element of the MathML for the given formula.
+    fn convert(formula: &str) -> String {
+        let mathml = latex_to_mathml(formula, false);
+        let start = mathml.find("<semantics>").expect("semantics") + "<semantics>".len();
+        let end = mathml.find("<annotation").expect("annotation");
+        mathml[start..end].to_owned()
+    }
+
+    #[test]
+    fn test_math_element() {
+        assert_eq!(
+            latex_to_mathml(r"\[a < b\]", true),
+            concat!(
+                "<math xmlns=\"http://www.w3.org/1998/Math/MathML\" display=\"block\">",
+                "<semantics><mrow><mi>a</mi><mo>&lt;</mo><mi>b</mi></mrow>",
+                "<annotation encoding=\"application/x-tex\">\\[a &lt; b\\]</annotation>",
+                "</semantics></math>"
+            )
+        );
+        assert!(latex_to_mathml("x", false).contains("display=\"inline\""));
+        assert_eq!(convert("$x$"), "<mi>x</mi>");
+        assert_eq!(convert(""), "<mrow></mrow>");
+    }
+
+    #[test]
+    fn test_tokens() {
+        assert_eq!(
+            convert("E = mc^2"),
+            "<mrow><mi>E</mi><mo>=</mo><mi>m</mi><msup><mi>c</mi><mn>2</mn></msup></mrow>"
+        );
+        assert_eq!(
+            convert(r"3.14 - 2x'"),
+            concat!(
+                "<mrow><mn>3.14</mn><mo>−</mo><mn>2</mn>",
+                "<msup><mi>x</mi><mo>′</mo></msup></mrow>"
+            )
+        );
+        assert_eq!(
+            convert(r"\alpha \Gamma \sin"),
+            "<mrow><mi>α</mi><mi mathvariant=\"normal\">Γ</mi><mi>sin</mi></mrow>"
+        );
+        assert_eq!(
+            convert(r"\foo \le 1."),
+            "<mrow><mtext>\\foo</mtext><mo>≤</mo><mn>1</mn><mo>.</mo></mrow>"
+        );
+    }
+
+    #[test]
+    fn test_scripts_and_fractions() {
+        assert_eq!(
+            convert(r"x_{i,j}^{2}"),
+            concat!(
+                "<msubsup><mi>x</mi><mrow><mi>i</mi><mo>,</mo><mi>j</mi></mrow>",
+                "<mn>2</mn></msubsup>"
+            )
+        );
+        assert_eq!(
+            convert(r"\frac{a+1}b"),
+            "<mfrac><mrow><mi>a</mi><mo>+</mo><mn>1</mn></mrow><mi>b</mi></mfrac>"
+        );
+        assert_eq!(
+            convert(r"\sqrt[3]{x} \sqrt2"),
+            "<mrow><mroot><mi>x</mi><mn>3</mn></mroot><msqrt><mn>2</mn></msqrt></mrow>"
+        );
+        assert_eq!(
+            convert(r"\sum_{i=1}^n i"),
+            concat!(
+                "<mrow><msubsup><mo movablelimits=\"true\">∑</mo>",
+                "<mrow><mi>i</mi><mo>=</mo><mn>1</mn></mrow><mi>n</mi></msubsup><mi>i</mi></mrow>"
+            )
+        );
+        let display = latex_to_mathml(r"\lim_{x \to 0} x", true);
+        assert!(display.contains("<munder><mi>lim</mi><mrow><mi>x</mi><mo>→</mo><mn>0</mn>"));
+    }
+
+    #[test]
+    fn test_commands() {
+        assert_eq!(
+            convert(r"\left( \frac{1}{2} \right."),
+            concat!(
+                "<mrow><mo fence=\"true\">(</mo>",
+                "<mfrac><mn>1</mn><mn>2</mn></mfrac></mrow>"
+            )
+        );
+        assert_eq!(
+            convert(r"\mathbb{R}^n"),
+            "<msup><mi mathvariant=\"double-struck\">R</mi><mi>n</mi></msup>"
+        );
+        assert_eq!(
+            convert(r"\text{if } x \neq 0"),
+            "<mrow><mtext>if </mtext><mi>x</mi><mo>≠</mo><mn>0</mn></mrow>"
+        );
+        assert_eq!(
+            convert(r"\operatorname*{arg\,max} \vec{v}"),
+            concat!(
+                "<mrow><mi>arg max</mi><mover accent=\"true\"><mi>v</mi>",
+                "<mo stretchy=\"false\">→</mo></mover></mrow>"
+            )
+        );
+        assert_eq!(
+            convert(r"a\,\not\in B"),
+            concat!(
+                "<mrow><mi>a</mi><mspace width=\"0.1667em\"></mspace>",
+                "<mo>∈\u{338}</mo><mi>B</mi></mrow>"
+            )
+        );
+    }
+
+    #[test]
+    fn test_environments() {
+        assert_eq!(
+            convert(r"\begin{pmatrix} a & b \\ c & d \\ \end{pmatrix}"),
+            concat!(
+                "<mrow><mo fence=\"true\">(</mo><mtable>",
+                "<mtr><mtd><mi>a</mi></mtd><mtd><mi>b</mi></mtd></mtr>",
+                "<mtr><mtd><mi>c</mi></mtd><mtd><mi>d</mi></mtd></mtr>",
+                "</mtable><mo fence=\"true\">)</mo></mrow>"
+            )
+        );
+        assert_eq!(
+            convert(r"\begin{array}{cc} 1 & 2 \end{array} }"),
+            "<mtable><mtr><mtd><mn>1</mn></mtd><mtd><mn>2</mn></mtd></mtr></mtable>"
+        );
+    }
+}
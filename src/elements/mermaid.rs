@@ -5,14 +5,37 @@
 
 //! Mermaid diagram element.
 //!
-//! Renders Mermaid diagrams to SVG by executing the embedded Mermaid runtime inside a
-//! headless Chrome instance. The produced SVG is embedded into the PDF using the
-//! existing `Image` element. Note that rendering requires the `mermaid` feature and a
-//! working headless Chrome executable available at runtime. Doc examples demonstrate
-//! API usage but do not perform rendering to avoid requiring Chrome in doctests.
+//! Renders Mermaid diagrams to SVG with a [`DiagramRenderer`][]. By default, the embedded
+//! Mermaid runtime is executed inside a headless Chrome instance ([`ChromeRenderer`][]), but
+//! other backends like a local command ([`CommandRenderer`][]) or a stub returning a fixed SVG
+//! can be used instead. The produced SVG is embedded into the PDF using the existing `Image`
+//! element. Note that rendering requires the `mermaid` feature and, for the default backend, a
+//! working headless Chrome executable available at runtime. Doc examples demonstrate API usage
+//! but do not perform rendering to avoid requiring Chrome in doctests.
+//!
+//! [`DiagramRenderer`]: trait.DiagramRenderer.html
+//! [`ChromeRenderer`]: struct.ChromeRenderer.html
+//! [`CommandRenderer`]: struct.CommandRenderer.html
 
 #[cfg(feature = "mermaid")]
-use crate::{Alignment, Position};
+use sha2::Digest as _;
+#[cfg(feature = "mermaid")]
+use std::ffi::OsString;
+#[cfg(feature = "mermaid")]
+use std::path::{Path, PathBuf};
+#[cfg(feature = "mermaid")]
+use std::sync::{mpsc, Arc, Mutex};
+#[cfg(feature = "mermaid")]
+use std::time::{Duration, Instant, SystemTime};
+#[cfg(feature = "mermaid")]
+use std::{fmt, fs, io};
+
+#[cfg(feature = "mermaid")]
+use super::{lock, SourceFallback};
+#[cfg(feature = "mermaid")]
+use crate::error::{Context as _, Error, ErrorKind};
+#[cfg(feature = "mermaid")]
+use crate::{fonts, Alignment, Context, Position};
 
 #[cfg(feature = "mermaid")]
 mod inner {
@@ -26,7 +49,7 @@ mod inner {
     use crate::style::Style;
     use crate::{Context, Element, RenderResult, Size};
 
-    use super::Mermaid;
+    use super::{lock, Mermaid, MermaidConfig};
 
     #[derive(Debug)]
     struct CompileError;
@@ -93,10 +116,8 @@ mod inner {
     /// the process doesn't keep child processes or background threads alive which
     /// can prevent the main program from exiting when its output is piped.
     pub fn shutdown_browser() -> Result<(), Error> {
-        // If we have a pool tab, try to clear references so it can be dropped.
-        if let Some(tab_arc) = POOL_TAB.get() {
-            let _ = tab_arc.clone(); // ensure we at least hold a clone so dropping our clone reduces refs
-        }
+        // If we have a pool tab, clear our reference so it can be dropped.
+        lock(&POOL_TAB).take();
 
         // If there is a running Browser, try to obtain its process id and kill it using sysinfo
         if let Some(browser) = BROWSER.get() {
@@ -179,9 +200,16 @@ mod inner {
 
     // Path to the embedded helper HTML file written to a temp location and reused.
     use headless_chrome::Tab;
-    use std::sync::Arc;
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
     static HELPER_PATH: OnceCell<std::path::PathBuf> = OnceCell::new();
-    static POOL_TAB: OnceCell<Arc<Tab>> = OnceCell::new();
+    // The helper tab, or `None` if it has not been opened yet or has been closed after a timeout.
+    static POOL_TAB: Mutex<Option<Arc<Tab>>> = Mutex::new(None);
+
+    /// The value returned by the helper page if a diagram has not been rendered in time.
+    const TIMED_OUT: &str = "genpdfi-mermaid-timed-out";
+    /// The default timeout of a headless Chrome tab, used for diagrams without a timeout.
+    const DEFAULT_TAB_TIMEOUT: Duration = Duration::from_secs(20);
 
     fn ensure_helper_file() -> Result<&'static std::path::PathBuf, Error> {
         HELPER_PATH.get_or_try_init(|| {
@@ -206,100 +234,155 @@ mod inner {
         })
     }
 
-    fn get_pool_tab() -> Result<&'static Arc<Tab>, Error> {
+    fn get_pool_tab() -> Result<Arc<Tab>, Error> {
         let browser = get_browser()?;
         let helper_path = ensure_helper_file()?;
-        POOL_TAB.get_or_try_init(|| {
-            let tab = browser.new_tab().map_err(|e| {
-                Error::new(format!("Failed to open tab: {}", e), ErrorKind::Internal)
-            })?;
-            tab.navigate_to(&format!("file://{}?pool=1", helper_path.display()))
-                .map_err(|e| {
-                    Error::new(format!("Failed to navigate: {}", e), ErrorKind::Internal)
-                })?;
-            tab.wait_until_navigated()
-                .map_err(|e| Error::new(format!("Navigation error: {}", e), ErrorKind::Internal))?;
-            // attempt to wait for metrics but do not fail if missing
-            let _ = tab.wait_for_element("#mermaid-metrics");
-            Ok(tab)
-        })
+        let mut pool_tab = lock(&POOL_TAB);
+        if let Some(tab) = pool_tab.as_ref() {
+            return Ok(tab.clone());
+        }
+        let tab = browser
+            .new_tab()
+            .map_err(|e| Error::new(format!("Failed to open tab: {}", e), ErrorKind::Internal))?;
+        tab.navigate_to(&format!("file://{}?pool=1", helper_path.display()))
+            .map_err(|e| Error::new(format!("Failed to navigate: {}", e), ErrorKind::Internal))?;
+        tab.wait_until_navigated()
+            .map_err(|e| Error::new(format!("Navigation error: {}", e), ErrorKind::Internal))?;
+        // attempt to wait for metrics but do not fail if missing
+        let _ = tab.wait_for_element("#mermaid-metrics");
+        *pool_tab = Some(tab.clone());
+        Ok(tab)
+    }
+
+    /// Closes the given helper tab, e.g. because it is still busy with a diagram that timed out,
+    /// so that the next diagram is rendered in a new tab.
+    fn reset_pool_tab(tab: &Arc<Tab>) {
+        let mut pool_tab = lock(&POOL_TAB);
+        if pool_tab.as_ref().is_some_and(|t| Arc::ptr_eq(t, tab)) {
+            *pool_tab = None;
+        }
+        drop(pool_tab);
+        let _ = tab.close(false);
     }
 
     impl Mermaid {
         /// Renders the diagram string to an SVG string using an embedded helper page and
         /// the `mermaid.min.js` script from `examples/helper`.
         pub fn render_svg(diagram: &str) -> Result<String, Error> {
-            // Use the shared Browser instance rather than starting a new one for every render.
-            let browser = get_browser()?;
-
-            // The helper files used by the existing example are embedded in the crate and reused
-            // here so the rendering logic stays consistent. We embed both the `index.html`
-            // which defines a `render` helper and the `mermaid.min.js` runtime so no external
-            // network access is required during rendering.
-            // Reuse a helper tab to avoid opening a new tab for every render.
-            // This improves stability and performance (see examples/mermaid_pool_proof_of_concept.rs).
-            let tab = get_pool_tab()?;
-
-            // Inject the mermaid runtime into the page so it can compile the diagram string.
-            // We pass `false` for the optional await flag because loading the runtime is
-            // synchronous for our embedded script.
-            // Pool helper page handles mermaid runtime; no need to inject runtime here.
-
-            // Submit the diagram to the pool and wait for the Promise result synchronously.
-            let id = format!(
-                "rust-{}-{}",
-                std::process::id(),
-                std::time::SystemTime::now()
-                    .duration_since(std::time::UNIX_EPOCH)
-                    .map(|d| d.as_millis())
-                    .unwrap_or(0)
-            );
-            let js_diagram = match serde_json::to_string(diagram) {
-                Ok(s) => s,
-                Err(e) => {
-                    return Err(Error::new(
-                        format!("Failed to JSON-encode diagram: {}", e),
-                        ErrorKind::Internal,
-                    ));
-                }
-            };
-            let submit = format!("window.__mermaidPool.submitTask('{}', {})", id, js_diagram);
-            let data = tab.evaluate(&submit, true).map_err(|e| {
-                Error::new(format!("JS execution error: {}", e), ErrorKind::Internal)
-            })?;
+            Self::render_svg_with_config(diagram, &MermaidConfig::new())
+        }
 
-            let raw = data.value.unwrap_or_default().to_string();
-            // The returned value may be quoted; unescape and strip surrounding quotes.
-            let svg = unescape(raw.trim_matches('\"')).unwrap_or_default();
+        /// Renders the diagram string to an SVG string like [`render_svg`][] and applies the
+        /// given configuration to this diagram.
+        ///
+        /// [`render_svg`]: #method.render_svg
+        pub fn render_svg_with_config(
+            diagram: &str,
+            config: &MermaidConfig,
+        ) -> Result<String, Error> {
+            render_svg(diagram, config, Duration::MAX)
+        }
+    }
 
-            // Detect whether the helper reported a JS-side error (we return a JSON error object
-            // from `examples/helper/index.html` in that case), or whether the result was `null`/empty.
-            if svg.trim().starts_with('{') && svg.contains("\"error\"") {
+    /// Renders the diagram string to an SVG string in the helper tab and gives up after the
+    /// given timeout.
+    ///
+    /// The timeout is enforced by the helper page and, in case its JavaScript thread is blocked,
+    /// by the tab itself.  If it expires or the tab reports any other error, the helper tab is
+    /// closed so that the next diagram is not rendered in a hung tab.
+    pub(super) fn render_svg(
+        diagram: &str,
+        config: &MermaidConfig,
+        timeout: Duration,
+    ) -> Result<String, Error> {
+        // The helper files used by the existing example are embedded in the crate and reused
+        // here so the rendering logic stays consistent. We embed both the `index.html`
+        // which defines a `render` helper and the `mermaid.min.js` runtime so no external
+        // network access is required during rendering.
+        // Reuse a helper tab to avoid opening a new tab for every render.
+        // This improves stability and performance (see examples/mermaid_pool_proof_of_concept.rs).
+        let tab = get_pool_tab()?;
+
+        // Inject the mermaid runtime into the page so it can compile the diagram string.
+        // We pass `false` for the optional await flag because loading the runtime is
+        // synchronous for our embedded script.
+        // Pool helper page handles mermaid runtime; no need to inject runtime here.
+
+        // Submit the diagram to the pool and wait for the Promise result synchronously.
+        let id = format!(
+            "rust-{}-{}",
+            std::process::id(),
+            std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map(|d| d.as_millis())
+                .unwrap_or(0)
+        );
+        // The configuration is added to the source like for all other renderers
+        let js_diagram = match serde_json::to_string(&config.apply_to(diagram)) {
+            Ok(s) => s,
+            Err(e) => {
                 return Err(Error::new(
-                    format!("Mermaid JS error: {}", svg),
-                    ErrorKind::InvalidData,
+                    format!("Failed to JSON-encode diagram: {}", e),
+                    ErrorKind::Internal,
                 ));
             }
-
-            if svg == "null" || svg.trim().is_empty() {
-                // Provide a diagnostic with the raw JS response and a small snippet of the diagram so
-                // users can quickly see what went wrong.
-                let snippet = if diagram.len() > 200 {
-                    format!("{}...", &diagram[..200])
-                } else {
-                    diagram.to_string()
-                };
-                return Err(Error::new(
-                    format!(
-                        "Mermaid failed to compile diagram (raw: {:?}; diagram snippet: {})",
-                        raw, snippet
-                    ),
-                    ErrorKind::InvalidData,
-                ));
+        };
+        let task = format!("window.__mermaidPool.submitTask('{}', {})", id, js_diagram);
+        // Timers cannot wait longer than i32::MAX milliseconds, so we wait indefinitely then.
+        let submit = match i32::try_from(timeout.as_millis()) {
+            Ok(ms) => {
+                tab.set_default_timeout(timeout);
+                format!(
+                    "Promise.race([{}, new Promise(resolve => setTimeout(() => resolve('{}'), {}))])",
+                    task, TIMED_OUT, ms
+                )
+            }
+            Err(_) => {
+                tab.set_default_timeout(DEFAULT_TAB_TIMEOUT);
+                task
             }
+        };
+        let data = tab.evaluate(&submit, true).map_err(|e| {
+            reset_pool_tab(&tab);
+            Error::new(format!("JS execution error: {}", e), ErrorKind::Internal)
+        })?;
 
-            Ok(svg)
+        let raw = data.value.unwrap_or_default().to_string();
+        // The returned value may be quoted; unescape and strip surrounding quotes.
+        let svg = unescape(raw.trim_matches('\"')).unwrap_or_default();
+
+        if svg == TIMED_OUT {
+            reset_pool_tab(&tab);
+            return Err(super::timeout_error(timeout));
+        }
+
+        // Detect whether the helper reported a JS-side error (we return a JSON error object
+        // from `examples/helper/index.html` in that case), or whether the result was `null`/empty.
+        if svg.trim().starts_with('{') && svg.contains("\"error\"") {
+            return Err(Error::new(
+                format!("Mermaid JS error: {}", svg),
+                ErrorKind::InvalidData,
+            ));
         }
+
+        if svg == "null" || svg.trim().is_empty() {
+            // Provide a diagnostic with the raw JS response and a small snippet of the diagram so
+            // users can quickly see what went wrong.
+            let snippet = if diagram.chars().count() > 200 {
+                format!("{}...", diagram.chars().take(200).collect::<String>())
+            } else {
+                diagram.to_string()
+            };
+            return Err(Error::new(
+                format!(
+                    "Mermaid failed to compile diagram (raw: {:?}; diagram snippet: {})",
+                    raw, snippet
+                ),
+                ErrorKind::InvalidData,
+            ));
+        }
+
+        Ok(svg)
     }
 
     /// Apply a direct scale to the provided SVG markup by inserting a `<g transform="scale(...)">`
@@ -662,11 +745,32 @@ mod inner {
             area: render::Area<'_>,
             style: Style,
         ) -> Result<RenderResult, Error> {
-            // Render diagram either as SVG or PNG (raster fallback)
-            // Render diagram to SVG string
-            let svg = match Self::render_svg(&self.diagram) {
-                Ok(s) => s,
-                Err(e) => return Err(e),
+            if let Some(element) = &mut self.fallback_element {
+                return element.render(context, area, style);
+            }
+
+            // Render diagram to SVG string with the configured backend
+            let svg = match self.render_diagram() {
+                Ok(svg) => svg,
+                Err(err) if self.fallback => {
+                    context.warn(err.to_string());
+                    let element = self.fallback_element(context, &err);
+                    return self
+                        .fallback_element
+                        .insert(element)
+                        .render(context, area, style);
+                }
+                Err(err) => return Err(err),
+            };
+
+            // Convert the labels to outlines before the SVG is scaled and parsed
+            let svg = if self.text_outlines {
+                let family = self
+                    .text_font_family
+                    .unwrap_or_else(|| context.font_cache.default_font_family());
+                crate::elements::svg_text::text_to_paths(&svg, &context.font_cache, family)
+            } else {
+                svg
             };
 
             // If auto-scaling is enabled we request the computed scale and allow
@@ -687,6 +791,9 @@ mod inner {
                 if let Some(link) = &self.link {
                     parsed_img = parsed_img.with_link(link.clone());
                 }
+                if let Some(alt_text) = &self.alt_text {
+                    parsed_img.set_alt_text(alt_text.clone());
+                }
                 let mut result = parsed_img.render(context, area, style)?;
                 // Apply scaling factor to the result size
                 if (used_scale - 1.0).abs() >= f32::EPSILON {
@@ -733,6 +840,9 @@ mod inner {
                     if let Some(link) = &self.link {
                         img = img.with_link(link.clone());
                     }
+                    if let Some(alt_text) = &self.alt_text {
+                        img.set_alt_text(alt_text.clone());
+                    }
                     let result = img.render(context, area, style);
                     // Add SVG source to RenderResult for reference
                     let mut res = result?;
@@ -753,6 +863,9 @@ mod inner {
                             if let Some(link) = &self.link {
                                 img = img.with_link(link.clone());
                             }
+                            if let Some(alt_text) = &self.alt_text {
+                                img.set_alt_text(alt_text.clone());
+                            }
                             let mut result = img.render(context, area, style)?;
                             // Apply scaling factor to the result size
                             if (used_scale - 1.0).abs() >= f32::EPSILON {
@@ -839,6 +952,36 @@ pub struct Mermaid {
     alignment: Alignment,
     position: Option<Position>,
     link: Option<String>,
+
+    /// Alternative text of the diagram for accessibility tools.
+    alt_text: Option<String>,
+
+    /// Backend that renders the diagram, or `None` to use the default renderer.
+    renderer: Option<SharedRenderer>,
+
+    /// Mermaid configuration (theme, fonts, ...) applied to this diagram.
+    config: MermaidConfig,
+
+    /// Whether the labels are converted to outlines with the fonts of the document.
+    text_outlines: bool,
+
+    /// Font family of the label outlines, or `None` to use the default font family.
+    text_font_family: Option<fonts::FontFamily<fonts::Font>>,
+
+    /// Maximum time to wait for the renderer.
+    timeout: Duration,
+
+    /// Whether to print the source of the diagram instead of failing if it cannot be rendered.
+    fallback: bool,
+
+    /// Font family of the fallback, e.g. a monospace font.
+    fallback_font_family: Option<fonts::FontFamily<fonts::Font>>,
+
+    /// The result of rendering the diagram, shared with the clones created to measure it.
+    rendered: RenderedDiagram,
+
+    /// The fallback element, set once rendering the diagram has failed.
+    fallback_element: Option<SourceFallback>,
 }
 
 #[cfg(feature = "mermaid")]
@@ -857,14 +1000,6 @@ impl Mermaid {
     /// # }
     /// ```
     pub fn new<S: Into<String>>(diagram: S) -> Self {
-        // Attempt to initialize the global headless Chrome instance asynchronously so
-        // Mermaid users do not need to call `ensure_browser()` manually. We ignore any
-        // initialization errors here; they will surface during `render()` if Chrome is
-        // not available.
-        let _ = std::thread::spawn(|| {
-            let _ = inner::ensure_browser();
-        });
-
         Mermaid {
             diagram: diagram.into(),
             scale: 1.0,
@@ -873,6 +1008,16 @@ impl Mermaid {
             alignment: Alignment::default(),
             position: None,
             link: None,
+            alt_text: None,
+            renderer: None,
+            config: MermaidConfig::new(),
+            text_outlines: false,
+            text_font_family: None,
+            timeout: DEFAULT_TIMEOUT,
+            fallback: false,
+            fallback_font_family: None,
+            rendered: RenderedDiagram::default(),
+            fallback_element: None,
         }
     }
 
@@ -916,6 +1061,21 @@ impl Mermaid {
         self
     }
 
+    /// Set the alternative text that describes the diagram for accessibility tools.
+    ///
+    /// See [`Image::set_alt_text`](struct.Image.html#method.set_alt_text) for details.
+    pub fn set_alt_text<S: Into<String>>(&mut self, alt_text: S) {
+        self.alt_text = Some(alt_text.into());
+    }
+
+    /// Set the alternative text of the diagram and return it.
+    ///
+    /// See [`set_alt_text`](#method.set_alt_text) for details.
+    pub fn with_alt_text<S: Into<String>>(mut self, alt_text: S) -> Self {
+        self.set_alt_text(alt_text);
+        self
+    }
+
     /// Set the scale to apply directly to the generated SVG.
     ///
     /// The scaling is applied to the SVG markup (a wrapper `<g transform="scale(...)">`
@@ -959,6 +1119,966 @@ impl Mermaid {
         self.auto_scale = true;
         self
     }
+
+    /// Set the Mermaid configuration of this diagram, e.g. its theme and colors.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[cfg(feature = "mermaid")]
+    /// # {
+    /// use genpdfi_extended::elements::{Mermaid, MermaidConfig, MermaidTheme};
+    /// let config = MermaidConfig::new()
+    ///     .with_theme(MermaidTheme::Base)
+    ///     .with_font_family("Noto Sans")
+    ///     .with_theme_variable("primaryColor", "#004f9f")
+    ///     .with_option("flowchart.curve", "linear");
+    /// let m = Mermaid::new("graph TB\na-->b").with_config(config);
+    /// # }
+    /// ```
+    pub fn with_config(mut self, config: MermaidConfig) -> Self {
+        self.config = config;
+        self.rendered = RenderedDiagram::default();
+        self
+    }
+
+    /// Convert the labels of the diagram to outlines with the default font family of the
+    /// document, so that they do not depend on the fonts installed on the system, see
+    /// [`Image::from_svg_string_with_fonts`][].
+    ///
+    /// Mermaid draws its labels as HTML in `<foreignObject>` elements by default, which cannot be
+    /// converted, so this also disables HTML labels (`htmlLabels: false`, see
+    /// [`MermaidConfig::with_html_labels`][]).  Font sizes set by the CSS rules of the diagram
+    /// are ignored; labels use the default Mermaid font size of 16 pixels unless it is set with
+    /// attributes.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[cfg(feature = "mermaid")]
+    /// # {
+    /// use genpdfi_extended::elements::Mermaid;
+    /// let m = Mermaid::new("graph TB\na-->b").with_text_outlines();
+    /// # }
+    /// ```
+    ///
+    /// [`Image::from_svg_string_with_fonts`]: struct.Image.html#method.from_svg_string_with_fonts
+    /// [`MermaidConfig::with_html_labels`]: struct.MermaidConfig.html#method.with_html_labels
+    pub fn with_text_outlines(mut self) -> Self {
+        self.text_outlines = true;
+        self.rendered = RenderedDiagram::default();
+        self
+    }
+
+    /// Convert the labels of the diagram to outlines, see [`with_text_outlines`][], with the
+    /// given font family, which must have been added to the [`FontCache`][].
+    ///
+    /// [`with_text_outlines`]: #method.with_text_outlines
+    /// [`FontCache`]: ../fonts/struct.FontCache.html
+    pub fn with_text_outline_font_family(
+        mut self,
+        font_family: fonts::FontFamily<fonts::Font>,
+    ) -> Self {
+        self.text_font_family = Some(font_family);
+        self.with_text_outlines()
+    }
+
+    /// Set the maximum time to wait for the renderer of this diagram (default: 30 seconds).
+    ///
+    /// If the renderer does not return in time, for example because the helper page in headless
+    /// Chrome hangs, rendering the diagram fails with an error, or prints the fallback if it is
+    /// enabled, see [`with_fallback`][].  Use [`Duration::MAX`][] to wait indefinitely.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[cfg(feature = "mermaid")]
+    /// # {
+    /// use genpdfi_extended::elements::Mermaid;
+    /// use std::time::Duration;
+    /// let m = Mermaid::new("graph TB\na-->b").with_timeout(Duration::from_secs(5));
+    /// # }
+    /// ```
+    ///
+    /// [`with_fallback`]: #method.with_fallback
+    /// [`Duration::MAX`]: https://doc.rust-lang.org/std/time/struct.Duration.html#associatedconstant.MAX
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self.rendered = RenderedDiagram::default();
+        self
+    }
+
+    /// Print the source of the diagram in a red frame with an error note instead of failing if
+    /// it cannot be rendered, e.g. if Chrome is missing, the diagram is invalid or the renderer
+    /// times out.
+    ///
+    /// The error is also reported with [`Context::warn`][].  The source is printed in the built-in
+    /// Courier font, which only covers Latin characters, unless another font family is set with
+    /// [`with_fallback_font_family`][].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[cfg(feature = "mermaid")]
+    /// # {
+    /// use genpdfi_extended::elements::Mermaid;
+    /// let m = Mermaid::new("graph TB\na-->b").with_fallback();
+    /// # }
+    /// ```
+    ///
+    /// [`Context::warn`]: ../struct.Context.html#method.warn
+    /// [`with_fallback_font_family`]: #method.with_fallback_font_family
+    pub fn with_fallback(mut self) -> Self {
+        self.fallback = true;
+        self.rendered = RenderedDiagram::default();
+        self
+    }
+
+    /// Enable the fallback, see [`with_fallback`][], and set the font family that is used to
+    /// print the diagram source, typically a monospace font that has been added to the
+    /// [`FontCache`][].
+    ///
+    /// [`with_fallback`]: #method.with_fallback
+    /// [`FontCache`]: ../fonts/struct.FontCache.html
+    pub fn with_fallback_font_family(
+        mut self,
+        font_family: fonts::FontFamily<fonts::Font>,
+    ) -> Self {
+        self.fallback_font_family = Some(font_family);
+        self.with_fallback()
+    }
+
+    /// Renders the diagram with its renderer, waiting at most for the timeout.
+    ///
+    /// The result is shared with the clones of this element, so that measuring the element does
+    /// not render the diagram again.  Errors are only kept if the fallback is enabled, as the
+    /// rendering fails otherwise.  The renderer runs on a separate thread that is abandoned if it
+    /// times out; renderers that can stop their work do so, see
+    /// [`DiagramRenderer::render_svg_with_timeout`][].
+    ///
+    /// [`DiagramRenderer::render_svg_with_timeout`]: trait.DiagramRenderer.html#method.render_svg_with_timeout
+    fn render_diagram(&self) -> Result<String, Error> {
+        let mut rendered = lock(&self.rendered.0);
+        if let Some(result) = rendered.as_ref() {
+            return result
+                .clone()
+                .map_err(|msg| Error::new(msg, ErrorKind::Internal));
+        }
+        let renderer = self.renderer();
+        let (diagram, timeout) = (self.diagram.clone(), self.timeout);
+        let config = if self.text_outlines {
+            self.config.clone().with_html_labels(false)
+        } else {
+            self.config.clone()
+        };
+        let (sender, receiver) = mpsc::channel();
+        std::thread::Builder::new()
+            .name("mermaid-render".to_owned())
+            .spawn(move || {
+                let _ = sender.send(renderer.render_svg_with_timeout(&diagram, &config, timeout));
+            })
+            .context("Failed to start the Mermaid render thread")?;
+        let result = match receiver.recv_timeout(self.timeout) {
+            Ok(result) => result,
+            Err(mpsc::RecvTimeoutError::Timeout) => Err(timeout_error(self.timeout)),
+            Err(mpsc::RecvTimeoutError::Disconnected) => {
+                Err(Error::new("Mermaid renderer panicked", ErrorKind::Internal))
+            }
+        };
+        match &result {
+            Ok(svg) => *rendered = Some(Ok(svg.clone())),
+            Err(err) if self.fallback => *rendered = Some(Err(err.to_string())),
+            Err(_) => {}
+        }
+        result
+    }
+
+    /// Creates the element that prints the error and the source of the diagram in a red frame.
+    fn fallback_element(&self, context: &Context, err: &Error) -> SourceFallback {
+        SourceFallback::new(
+            context,
+            &self.diagram,
+            Some(format!("Mermaid diagram could not be rendered: {}", err)),
+            self.fallback_font_family,
+        )
+    }
+
+    /// Set the backend that renders this diagram to SVG, overriding the default renderer.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[cfg(feature = "mermaid")]
+    /// # {
+    /// use genpdfi_extended::elements::{CommandRenderer, Mermaid};
+    /// let mmdc = CommandRenderer::new("mmdc").with_args(["-i", "-", "-o", "-", "-e", "svg"]);
+    /// let m = Mermaid::new("graph TB\na-->b").with_renderer(mmdc);
+    /// # }
+    /// ```
+    pub fn with_renderer(mut self, renderer: impl DiagramRenderer + 'static) -> Self {
+        self.renderer = Some(SharedRenderer(Arc::new(renderer)));
+        self.rendered = RenderedDiagram::default();
+        self
+    }
+
+    /// Set the backend that renders all diagrams without an explicit renderer, see
+    /// [`with_renderer`][].
+    ///
+    /// Until a default renderer is set, or after [`clear_default_renderer`][] has been called,
+    /// the diagrams are rendered with the shared headless Chrome instance ([`ChromeRenderer`][]).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[cfg(feature = "mermaid")]
+    /// # {
+    /// use genpdfi_extended::elements::Mermaid;
+    /// use genpdfi_extended::error::Error;
+    ///
+    /// // A stub for environments without Chrome
+    /// Mermaid::set_default_renderer(|_: &str| -> Result<String, Error> {
+    ///     Ok(r#"<svg xmlns="http://www.w3.org/2000/svg" width="10" height="10"></svg>"#.to_owned())
+    /// });
+    /// # Mermaid::clear_default_renderer();
+    /// # }
+    /// ```
+    ///
+    /// [`with_renderer`]: #method.with_renderer
+    /// [`clear_default_renderer`]: #method.clear_default_renderer
+    /// [`ChromeRenderer`]: struct.ChromeRenderer.html
+    pub fn set_default_renderer(renderer: impl DiagramRenderer + 'static) {
+        *lock(&DEFAULT_RENDERER) = Some(Arc::new(renderer));
+    }
+
+    /// Reset the default renderer to the shared headless Chrome instance.
+    pub fn clear_default_renderer() {
+        *lock(&DEFAULT_RENDERER) = None;
+    }
+
+    /// Set the persistent cache of the diagrams rendered with headless Chrome, or `None` to
+    /// disable caching (default).
+    ///
+    /// The cache is consulted by [`ChromeRenderer`][] before the diagram is sent to the browser,
+    /// see [`MermaidCache`][] for details.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[cfg(feature = "mermaid")]
+    /// # {
+    /// use genpdfi_extended::elements::{Mermaid, MermaidCache};
+    /// Mermaid::set_cache(Some(
+    ///     MermaidCache::new(std::env::temp_dir().join("diagrams")).with_max_entries(500),
+    /// ));
+    /// # Mermaid::set_cache(None);
+    /// # }
+    /// ```
+    ///
+    /// [`ChromeRenderer`]: struct.ChromeRenderer.html
+    /// [`MermaidCache`]: struct.MermaidCache.html
+    pub fn set_cache(cache: Option<MermaidCache>) {
+        *lock(&CACHE) = cache;
+    }
+
+    /// Returns the renderer of this diagram.
+    fn renderer(&self) -> Arc<dyn DiagramRenderer> {
+        match &self.renderer {
+            Some(renderer) => renderer.0.clone(),
+            None => lock(&DEFAULT_RENDERER)
+                .clone()
+                .unwrap_or_else(|| Arc::new(ChromeRenderer)),
+        }
+    }
+}
+
+/// A built-in Mermaid theme, see [`MermaidConfig::with_theme`][].
+///
+/// [`MermaidConfig::with_theme`]: struct.MermaidConfig.html#method.with_theme
+#[cfg(feature = "mermaid")]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MermaidTheme {
+    /// The default theme
+    Default,
+    /// A black and white theme for printed documents
+    Neutral,
+    /// A theme for dark backgrounds
+    Dark,
+    /// A theme with shades of green
+    Forest,
+    /// The only theme that can be customized with theme variables
+    Base,
+}
+
+#[cfg(feature = "mermaid")]
+impl MermaidTheme {
+    /// Returns the name of the theme in the Mermaid configuration.
+    pub fn name(self) -> &'static str {
+        match self {
+            MermaidTheme::Default => "default",
+            MermaidTheme::Neutral => "neutral",
+            MermaidTheme::Dark => "dark",
+            MermaidTheme::Forest => "forest",
+            MermaidTheme::Base => "base",
+        }
+    }
+}
+
+/// The Mermaid configuration of a diagram, e.g. its theme, fonts and colors.
+///
+/// The configuration uses the format of the `mermaid.initialize` function of the Mermaid
+/// runtime and only applies to the diagram it is set for, see [`Mermaid::with_config`][].  Common
+/// settings have dedicated methods; all other settings can be set with [`with_option`][].
+///
+/// Theme variables like `primaryColor` are only used by the [`MermaidTheme::Base`][] theme.
+///
+/// # Examples
+///
+/// ```
+/// # #[cfg(feature = "mermaid")]
+/// # {
+/// use genpdfi_extended::elements::{MermaidConfig, MermaidTheme};
+/// let config = MermaidConfig::new()
+///     .with_theme(MermaidTheme::Base)
+///     .with_theme_variable("primaryColor", "#004f9f");
+/// let flowchart = MermaidConfig::new().with_option("flowchart.curve", "linear");
+/// assert_eq!(flowchart.to_json(), r#"{"flowchart":{"curve":"linear"}}"#);
+/// # }
+/// ```
+///
+/// [`Mermaid::with_config`]: struct.Mermaid.html#method.with_config
+/// [`with_option`]: #method.with_option
+/// [`MermaidTheme::Base`]: enum.MermaidTheme.html#variant.Base
+#[cfg(feature = "mermaid")]
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct MermaidConfig {
+    config: serde_json::Map<String, serde_json::Value>,
+}
+
+#[cfg(feature = "mermaid")]
+impl MermaidConfig {
+    /// Creates an empty configuration that uses the defaults of Mermaid.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the theme of the diagram.
+    pub fn with_theme(self, theme: MermaidTheme) -> Self {
+        self.with_option("theme", theme.name())
+    }
+
+    /// Sets the font family of the diagram, e.g. `"Noto Sans, sans-serif"`.
+    pub fn with_font_family(self, font_family: impl Into<String>) -> Self {
+        let font_family = font_family.into();
+        self.with_option("fontFamily", font_family.clone())
+            .with_theme_variable("fontFamily", font_family)
+    }
+
+    /// Sets the given theme variable (`themeVariables`), e.g. `primaryColor` or `lineColor`.
+    pub fn with_theme_variable(
+        self,
+        name: impl Into<String>,
+        value: impl Into<serde_json::Value>,
+    ) -> Self {
+        let path = format!("themeVariables.{}", name.into());
+        self.with_option(&path, value)
+    }
+
+    /// Sets the option with the given path, which uses dots to separate the keys of nested
+    /// objects, e.g. `flowchart.curve` or `sequence.mirrorActors`.
+    pub fn with_option(mut self, path: &str, value: impl Into<serde_json::Value>) -> Self {
+        let mut keys: Vec<&str> = path.split('.').collect();
+        let last = keys.pop().unwrap_or_default();
+        let mut object = &mut self.config;
+        for key in keys {
+            let entry = object
+                .entry(key)
+                .or_insert_with(|| serde_json::Value::Object(Default::default()));
+            if !entry.is_object() {
+                *entry = serde_json::Value::Object(Default::default());
+            }
+            object = match entry {
+                serde_json::Value::Object(object) => object,
+                _ => unreachable!(),
+            };
+        }
+        object.insert(last.to_owned(), value.into());
+        self
+    }
+
+    /// Sets whether labels are drawn as HTML in `<foreignObject>` elements (the Mermaid default)
+    /// or as SVG `<text>` elements.
+    pub fn with_html_labels(self, html_labels: bool) -> Self {
+        self.with_option("htmlLabels", html_labels)
+            .with_option("flowchart.htmlLabels", html_labels)
+    }
+
+    /// Returns whether no option has been set.
+    pub fn is_empty(&self) -> bool {
+        self.config.is_empty()
+    }
+
+    /// Returns the configuration as a JSON object.
+    pub fn to_json(&self) -> String {
+        serde_json::Value::Object(self.config.clone()).to_string()
+    }
+
+    /// Adds the configuration as an `init` directive to the given diagram source.
+    ///
+    /// The directive is placed after a YAML front matter block as the front matter must start
+    /// the diagram.  The source is returned unchanged if the configuration is empty.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[cfg(feature = "mermaid")]
+    /// # {
+    /// use genpdfi_extended::elements::{MermaidConfig, MermaidTheme};
+    /// let config = MermaidConfig::new().with_theme(MermaidTheme::Dark);
+    /// assert_eq!(
+    ///     config.apply_to("graph TB\na-->b"),
+    ///     "%%{init: {\"theme\":\"dark\"}}%%\ngraph TB\na-->b"
+    /// );
+    /// # }
+    /// ```
+    pub fn apply_to(&self, source: &str) -> String {
+        if self.is_empty() {
+            return source.to_owned();
+        }
+        let directive = format!("%%{{init: {}}}%%\n", self.to_json());
+        // Find the end of the front matter, i. e. the line after the closing `---`
+        let mut lines = source.trim_start().split_inclusive('\n');
+        let mut end = source.len() - source.trim_start().len();
+        if lines.next().map(str::trim_end) == Some("---") {
+            end += source[end..].find('\n').map_or(0, |i| i + 1);
+            for line in lines {
+                end += line.len();
+                if line.trim_end() == "---" && line.ends_with('\n') {
+                    return format!("{}{}{}", &source[..end], directive, &source[end..]);
+                }
+            }
+        }
+        directive + source
+    }
+}
+
+/// A backend that renders the source of a diagram to SVG.
+///
+/// [`Mermaid`][] elements use the shared headless Chrome instance ([`ChromeRenderer`][]) by
+/// default.  Other implementations can be set per element with [`Mermaid::with_renderer`][] or
+/// for all diagrams with [`Mermaid::set_default_renderer`][], for example a local command
+/// ([`CommandRenderer`][]) or a stub returning a fixed SVG in tests.  This trait is also
+/// implemented for closures with the same signature as [`render_svg`][].
+///
+/// # Examples
+///
+/// ```
+/// # #[cfg(feature = "mermaid")]
+/// # {
+/// use genpdfi_extended::elements::{DiagramRenderer, Mermaid};
+/// use genpdfi_extended::error::Error;
+///
+/// struct Placeholder;
+///
+/// impl DiagramRenderer for Placeholder {
+///     fn render_svg(&self, _source: &str) -> Result<String, Error> {
+///         Ok(r#"<svg xmlns="http://www.w3.org/2000/svg" width="10" height="10"></svg>"#.to_owned())
+///     }
+/// }
+///
+/// let m = Mermaid::new("graph TB\na-->b").with_renderer(Placeholder);
+/// # }
+/// ```
+///
+/// [`Mermaid`]: struct.Mermaid.html
+/// [`ChromeRenderer`]: struct.ChromeRenderer.html
+/// [`CommandRenderer`]: struct.CommandRenderer.html
+/// [`Mermaid::with_renderer`]: struct.Mermaid.html#method.with_renderer
+/// [`Mermaid::set_default_renderer`]: struct.Mermaid.html#method.set_default_renderer
+/// [`render_svg`]: #tymethod.render_svg
+#[cfg(feature = "mermaid")]
+pub trait DiagramRenderer: Send + Sync {
+    /// Renders the given diagram source to an SVG document.
+    fn render_svg(&self, source: &str) -> Result<String, Error>;
+
+    /// Renders the given diagram source with the given configuration to an SVG document.
+    ///
+    /// The default implementation adds the configuration as an `init` directive to the source
+    /// (see [`MermaidConfig::apply_to`][]) and calls [`render_svg`][].
+    ///
+    /// [`MermaidConfig::apply_to`]: struct.MermaidConfig.html#method.apply_to
+    /// [`render_svg`]: #tymethod.render_svg
+    fn render_svg_with_config(
+        &self,
+        source: &str,
+        config: &MermaidConfig,
+    ) -> Result<String, Error> {
+        self.render_svg(&config.apply_to(source))
+    }
+
+    /// Renders the given diagram source with the given configuration to an SVG document and gives
+    /// up after the given timeout.
+    ///
+    /// [`Mermaid`][] stops waiting for the renderer after the timeout in any case, but the
+    /// renderer keeps working in the background unless it implements this method and stops its
+    /// work itself, as [`ChromeRenderer`][] and [`CommandRenderer`][] do.  The default
+    /// implementation ignores the timeout and calls [`render_svg_with_config`][].
+    ///
+    /// [`Mermaid`]: struct.Mermaid.html
+    /// [`ChromeRenderer`]: struct.ChromeRenderer.html
+    /// [`CommandRenderer`]: struct.CommandRenderer.html
+    /// [`render_svg_with_config`]: #method.render_svg_with_config
+    fn render_svg_with_timeout(
+        &self,
+        source: &str,
+        config: &MermaidConfig,
+        timeout: Duration,
+    ) -> Result<String, Error> {
+        let _ = timeout;
+        self.render_svg_with_config(source, config)
+    }
+}
+
+#[cfg(feature = "mermaid")]
+impl<F> DiagramRenderer for F
+where
+    F: Fn(&str) -> Result<String, Error> + Send + Sync,
+{
+    fn render_svg(&self, source: &str) -> Result<String, Error> {
+        self(source)
+    }
+}
+
+/// The default [`DiagramRenderer`][] that executes the embedded Mermaid runtime in the shared
+/// headless Chrome instance, see [`Mermaid::render_svg`][].
+///
+/// Chrome is started when the first diagram is rendered, so the start-up time counts towards the
+/// timeout of that diagram (see [`Mermaid::with_timeout`][]).  Call [`Mermaid::ensure_browser`][]
+/// to start it in advance.
+///
+/// [`DiagramRenderer`]: trait.DiagramRenderer.html
+/// [`Mermaid::render_svg`]: struct.Mermaid.html#method.render_svg
+/// [`Mermaid::with_timeout`]: struct.Mermaid.html#method.with_timeout
+/// [`Mermaid::ensure_browser`]: struct.Mermaid.html#method.ensure_browser
+#[cfg(feature = "mermaid")]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ChromeRenderer;
+
+#[cfg(feature = "mermaid")]
+impl DiagramRenderer for ChromeRenderer {
+    fn render_svg(&self, source: &str) -> Result<String, Error> {
+        self.render_svg_with_config(source, &MermaidConfig::new())
+    }
+
+    fn render_svg_with_config(
+        &self,
+        source: &str,
+        config: &MermaidConfig,
+    ) -> Result<String, Error> {
+        self.render_svg_with_timeout(source, config, Duration::MAX)
+    }
+
+    fn render_svg_with_timeout(
+        &self,
+        source: &str,
+        config: &MermaidConfig,
+        timeout: Duration,
+    ) -> Result<String, Error> {
+        let cache = lock(&CACHE).clone();
+        if let Some(svg) = cache.as_ref().and_then(|cache| cache.get(source, config)) {
+            return Ok(svg);
+        }
+        let svg = inner::render_svg(source, config, timeout)?;
+        if let Some(cache) = &cache {
+            let sanitized =
+                inner::sanitize_svg_for_printpdf(&inner::strip_slice_class_from_path_tags(&svg));
+            let _ = cache.insert(source, config, &sanitized);
+        }
+        Ok(svg)
+    }
+}
+
+/// The version of the bundled Mermaid runtime, see `build.rs`
+#[cfg(feature = "mermaid")]
+const MERMAID_VERSION: &str = match option_env!("MERMAID_VERSION") {
+    Some(version) => version,
+    None => "unknown",
+};
+
+/// A persistent, content-addressed cache of the SVGs rendered by [`ChromeRenderer`][].
+///
+/// The diagrams are stored as SVG files in the cache directory, named after a SHA-256 digest of
+/// the diagram source, its configuration and the version of the bundled Mermaid runtime, so that
+/// unchanged diagrams are not sent to headless Chrome again, also by later processes.  The SVGs
+/// are stored after they have been sanitized for `printpdf`.  Set the cache with
+/// [`Mermaid::set_cache`][].
+///
+/// Old files are evicted after a new diagram has been stored, see [`with_max_entries`][],
+/// [`with_max_size`][] and [`with_max_age`][].  Errors when accessing the cache directory while
+/// rendering are ignored.
+///
+/// [`ChromeRenderer`]: struct.ChromeRenderer.html
+/// [`Mermaid::set_cache`]: struct.Mermaid.html#method.set_cache
+/// [`with_max_entries`]: #method.with_max_entries
+/// [`with_max_size`]: #method.with_max_size
+/// [`with_max_age`]: #method.with_max_age
+#[cfg(feature = "mermaid")]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MermaidCache {
+    directory: PathBuf,
+    max_entries: Option<usize>,
+    max_size: Option<u64>,
+    max_age: Option<Duration>,
+}
+
+#[cfg(feature = "mermaid")]
+impl MermaidCache {
+    /// Creates a cache that stores the diagrams in the given directory without limits.
+    ///
+    /// The directory is created when the first diagram is stored.
+    pub fn new(directory: impl Into<PathBuf>) -> Self {
+        MermaidCache {
+            directory: directory.into(),
+            max_entries: None,
+            max_size: None,
+            max_age: None,
+        }
+    }
+
+    /// Sets the maximum number of diagrams in the cache; the least recently used diagrams are
+    /// evicted first.
+    pub fn with_max_entries(mut self, max_entries: usize) -> Self {
+        self.max_entries = Some(max_entries);
+        self
+    }
+
+    /// Sets the maximum total size of the cached diagrams in bytes; the least recently used
+    /// diagrams are evicted first.
+    pub fn with_max_size(mut self, max_size: u64) -> Self {
+        self.max_size = Some(max_size);
+        self
+    }
+
+    /// Sets the maximum time since a diagram has last been used before it is evicted.
+    pub fn with_max_age(mut self, max_age: Duration) -> Self {
+        self.max_age = Some(max_age);
+        self
+    }
+
+    /// Returns the cache directory.
+    pub fn directory(&self) -> &Path {
+        &self.directory
+    }
+
+    /// Returns the cached SVG of the given diagram source and configuration, if any.
+    pub fn get(&self, source: &str, config: &MermaidConfig) -> Option<String> {
+        let file = self.file(source, config);
+        let modified = fs::metadata(&file).and_then(|m| m.modified()).ok()?;
+        if self.is_expired(modified) {
+            let _ = fs::remove_file(&file);
+            return None;
+        }
+        let svg = fs::read_to_string(&file).ok()?;
+        // Mark the diagram as recently used for the eviction
+        if let Ok(f) = fs::File::options().append(true).open(&file) {
+            let _ = f.set_modified(SystemTime::now());
+        }
+        Some(svg)
+    }
+
+    /// Stores the SVG of the given diagram source and configuration and evicts old diagrams.
+    pub fn insert(&self, source: &str, config: &MermaidConfig, svg: &str) -> Result<(), Error> {
+        fs::create_dir_all(&self.directory).with_context(|| {
+            format!(
+                "Failed to create the Mermaid cache directory {}",
+                self.directory.display()
+            )
+        })?;
+        // Write to a temporary file first so that concurrent readers never see partial files
+        let file = self.file(source, config);
+        let tmp = file.with_extension(format!("{}.tmp", std::process::id()));
+        fs::write(&tmp, svg)
+            .and_then(|_| fs::rename(&tmp, &file))
+            .with_context(|| format!("Failed to write the cached diagram {}", file.display()))?;
+        self.evict().map(|_| ())
+    }
+
+    /// Removes the expired diagrams and, if the cache exceeds its limits, the least recently used
+    /// diagrams, and returns the number of removed files.
+    pub fn evict(&self) -> Result<usize, Error> {
+        let mut entries = self.entries()?;
+        let mut removed = 0;
+        // Most recently used first
+        entries.sort_by(|a, b| b.1.cmp(&a.1));
+        let (mut count, mut size) = (0, 0);
+        for (path, modified, len) in entries {
+            count += 1;
+            size += len;
+            let evict = self.is_expired(modified)
+                || self.max_entries.is_some_and(|max| count > max)
+                || self.max_size.is_some_and(|max| size > max);
+            if evict {
+                fs::remove_file(&path).with_context(|| {
+                    format!("Failed to remove the cached diagram {}", path.display())
+                })?;
+                removed += 1;
+                count -= 1;
+                size -= len;
+            }
+        }
+        Ok(removed)
+    }
+
+    /// Removes all diagrams from the cache directory.
+    pub fn clear(&self) -> Result<(), Error> {
+        for (path, _, _) in self.entries()? {
+            fs::remove_file(&path).with_context(|| {
+                format!("Failed to remove the cached diagram {}", path.display())
+            })?;
+        }
+        Ok(())
+    }
+
+    /// Returns the path, the modification time and the size of the cached diagrams.
+    fn entries(&self) -> Result<Vec<(PathBuf, SystemTime, u64)>, Error> {
+        let dir = match fs::read_dir(&self.directory) {
+            Ok(dir) => dir,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(err) => {
+                return Err(Error::new(
+                    format!(
+                        "Failed to read the Mermaid cache directory {}",
+                        self.directory.display()
+                    ),
+                    err,
+                ))
+            }
+        };
+        let mut entries = Vec::new();
+        for entry in dir {
+            let entry = entry.context("Failed to read the Mermaid cache directory")?;
+            let path = entry.path();
+            if path.extension() != Some(std::ffi::OsStr::new("svg")) {
+                continue;
+            }
+            if let Ok(metadata) = entry.metadata() {
+                let modified = metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH);
+                entries.push((path, modified, metadata.len()));
+            }
+        }
+        Ok(entries)
+    }
+
+    fn is_expired(&self, modified: SystemTime) -> bool {
+        match (self.max_age, modified.elapsed()) {
+            (Some(max_age), Ok(age)) => age > max_age,
+            _ => false,
+        }
+    }
+
+    /// Returns the path of the cache file of the given diagram source and configuration.
+    ///
+    /// The file is named after a SHA-256 digest, so that the names are stable across Rust
+    /// releases and platforms.
+    fn file(&self, source: &str, config: &MermaidConfig) -> PathBuf {
+        // Neither the version nor the JSON configuration contain line breaks
+        let mut hasher = sha2::Sha256::new();
+        hasher.update(format!("{}\n{}\n", MERMAID_VERSION, config.to_json()));
+        hasher.update(source.as_bytes());
+        let name: String = hasher
+            .finalize()
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect();
+        self.directory.join(format!("{}.svg", name))
+    }
+}
+
+/// A [`DiagramRenderer`][] that runs a local command, e.g. the Mermaid CLI `mmdc`.
+///
+/// The diagram source is written to the standard input of the command and the SVG is read
+/// from its standard output.  If the command exits with a failure status, an error with its
+/// standard error output is returned.
+///
+/// # Examples
+///
+/// ```
+/// # #[cfg(feature = "mermaid")]
+/// # {
+/// use genpdfi_extended::elements::CommandRenderer;
+/// let renderer = CommandRenderer::new("mmdc").with_args(["-i", "-", "-o", "-", "-e", "svg"]);
+/// # }
+/// ```
+///
+/// [`DiagramRenderer`]: trait.DiagramRenderer.html
+#[cfg(feature = "mermaid")]
+#[derive(Clone, Debug)]
+pub struct CommandRenderer {
+    program: OsString,
+    args: Vec<OsString>,
+}
+
+#[cfg(feature = "mermaid")]
+impl CommandRenderer {
+    /// Creates a renderer that runs the given program without arguments.
+    pub fn new(program: impl Into<OsString>) -> Self {
+        CommandRenderer {
+            program: program.into(),
+            args: Vec::new(),
+        }
+    }
+
+    /// Adds the given arguments to the command line of the program.
+    pub fn with_args<I, S>(mut self, args: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<OsString>,
+    {
+        self.args.extend(args.into_iter().map(Into::into));
+        self
+    }
+}
+
+#[cfg(feature = "mermaid")]
+impl CommandRenderer {
+    /// Runs the command with the given diagram source and kills it if it has not exited after
+    /// the given timeout.
+    fn run(&self, source: &str, timeout: Duration) -> Result<String, Error> {
+        use std::io::{Read, Write as _};
+        use std::process::{Command, Stdio};
+
+        /// Reads the given pipe to the end from another thread.
+        fn read_pipe(pipe: Option<impl Read + Send + 'static>) -> std::thread::JoinHandle<Vec<u8>> {
+            std::thread::spawn(move || {
+                let mut buf = Vec::new();
+                if let Some(mut pipe) = pipe {
+                    let _ = pipe.read_to_end(&mut buf);
+                }
+                buf
+            })
+        }
+
+        let program = self.program.to_string_lossy();
+        let mut child = Command::new(&self.program)
+            .args(&self.args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .with_context(|| format!("Failed to run diagram renderer {}", program))?;
+
+        // Write the source and read the output from other threads so that neither a large input
+        // nor a large output can block the command
+        let mut stdin = child.stdin.take();
+        let input = source.to_owned();
+        let writer = std::thread::spawn(move || match stdin.as_mut() {
+            Some(stdin) => stdin.write_all(input.as_bytes()),
+            None => Ok(()),
+        });
+        let stdout = read_pipe(child.stdout.take());
+        let stderr = read_pipe(child.stderr.take());
+
+        let deadline = Instant::now().checked_add(timeout);
+        let status = loop {
+            let status = child
+                .try_wait()
+                .with_context(|| format!("Failed to run diagram renderer {}", program))?;
+            if let Some(status) = status {
+                break status;
+            }
+            if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+                let _ = child.kill();
+                let _ = child.wait();
+                return Err(timeout_error(timeout));
+            }
+            std::thread::sleep(Duration::from_millis(10));
+        };
+        // A command that does not read its input may close the pipe early
+        let _ = writer.join();
+        let stdout = stdout.join().unwrap_or_default();
+        let stderr = stderr.join().unwrap_or_default();
+
+        if !status.success() {
+            return Err(Error::new(
+                format!(
+                    "Diagram renderer {} failed ({}): {}",
+                    program,
+                    status,
+                    String::from_utf8_lossy(&stderr).trim()
+                ),
+                ErrorKind::InvalidData,
+            ));
+        }
+        String::from_utf8(stdout).map_err(|_| {
+            Error::new(
+                format!("Diagram renderer {} returned invalid UTF-8", program),
+                ErrorKind::InvalidData,
+            )
+        })
+    }
+}
+
+#[cfg(feature = "mermaid")]
+impl DiagramRenderer for CommandRenderer {
+    fn render_svg(&self, source: &str) -> Result<String, Error> {
+        self.run(source, Duration::MAX)
+    }
+
+    fn render_svg_with_timeout(
+        &self,
+        source: &str,
+        config: &MermaidConfig,
+        timeout: Duration,
+    ) -> Result<String, Error> {
+        self.run(&config.apply_to(source), timeout)
+    }
+}
+
+/// A renderer that is shared between clones of a [`Mermaid`][] element.
+///
+/// [`Mermaid`]: struct.Mermaid.html
+#[cfg(feature = "mermaid")]
+#[derive(Clone)]
+struct SharedRenderer(Arc<dyn DiagramRenderer>);
+
+#[cfg(feature = "mermaid")]
+impl fmt::Debug for SharedRenderer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("DiagramRenderer")
+    }
+}
+
+/// The result of rendering a [`Mermaid`][] element, or `None` if it has not been rendered yet.
+///
+/// The result is shared between the clones of the element, and errors are stored as their
+/// message.
+///
+/// [`Mermaid`]: struct.Mermaid.html
+#[cfg(feature = "mermaid")]
+#[derive(Clone, Debug, Default)]
+struct RenderedDiagram(Arc<Mutex<Option<Result<String, String>>>>);
+
+/// The default timeout of the renderer of a diagram
+#[cfg(feature = "mermaid")]
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// The renderer of the diagrams without an explicit renderer, or `None` to use Chrome
+#[cfg(feature = "mermaid")]
+static DEFAULT_RENDERER: Mutex<Option<Arc<dyn DiagramRenderer>>> = Mutex::new(None);
+
+/// The persistent cache of the diagrams rendered with Chrome, see `Mermaid::set_cache`
+#[cfg(feature = "mermaid")]
+static CACHE: Mutex<Option<MermaidCache>> = Mutex::new(None);
+
+/// Returns the error of a renderer that has not returned within the given timeout.
+#[cfg(feature = "mermaid")]
+fn timeout_error(timeout: Duration) -> Error {
+    Error::new(
+        format!(
+            "Mermaid rendering timed out after {} s",
+            timeout.as_secs_f32()
+        ),
+        ErrorKind::Internal,
+    )
 }
 
 #[cfg(all(test, feature = "mermaid"))]
@@ -1018,6 +2138,344 @@ mod tests {
         }
     }
 
+    #[test]
+    fn render_mermaid_with_custom_renderer() {
+        let r = Renderer::new(Size::new(200.0, 200.0), "t").expect("renderer");
+        let area = r.first_page().first_layer().area();
+        let context = crate::test_util::context();
+
+        // A stub renderer does not need Chrome
+        let sources = Arc::new(Mutex::new(Vec::new()));
+        let seen = sources.clone();
+        let mut m = Mermaid::new("graph TB\na-->b").with_renderer(
+            move |source: &str| -> Result<String, Error> {
+                seen.lock().unwrap().push(source.to_owned());
+                Ok(concat!(
+                    r#"<svg xmlns="http://www.w3.org/2000/svg" width="40" height="20">"#,
+                    r#"<rect x="0" y="0" width="40" height="20" fill="red"/></svg>"#
+                )
+                .to_owned())
+            },
+        );
+        let res = m
+            .render(&context, area.clone(), Style::new())
+            .expect("render");
+        assert!(res.size.width.0 > 0.0);
+        assert!(res.size.height.0 > 0.0);
+        assert_eq!(*sources.lock().unwrap(), vec!["graph TB\na-->b".to_owned()]);
+
+        // Errors of the renderer are propagated
+        let mut failing =
+            Mermaid::new("graph TB\na-->b").with_renderer(|_: &str| -> Result<String, Error> {
+                Err(Error::new("no renderer", ErrorKind::InvalidData))
+            });
+        let err = failing
+            .render(&context, area, Style::new())
+            .expect_err("renderer error");
+        assert_eq!(err.to_string(), "no renderer");
+    }
+
+    #[test]
+    fn mermaid_text_outlines_use_document_fonts() {
+        let (r, context) = crate::test_util::renderer(Size::new(200.0, 200.0));
+        let area = r.first_page().first_layer().area();
+
+        let sources = Arc::new(Mutex::new(Vec::new()));
+        let seen = sources.clone();
+        let mut m = Mermaid::new("graph TB\na-->b")
+            .with_renderer(move |source: &str| -> Result<String, Error> {
+                seen.lock().unwrap().push(source.to_owned());
+                Ok(concat!(
+                    r#"<svg xmlns="http://www.w3.org/2000/svg" width="40" height="20">"#,
+                    r#"<text x="5" y="15" fill="#333">a</text></svg>"#
+                )
+                .to_owned())
+            })
+            .with_text_outlines();
+        let res = m.render(&context, area, Style::new()).expect("render");
+        let svg = res.svg.expect("svg");
+        assert!(!svg.contains("<text"));
+        assert!(svg.contains("<g fill=\"#333\"><path d=\"M"));
+        // HTML labels are disabled so that Mermaid emits <text> elements
+        assert!(sources.lock().unwrap()[0].contains(r#""htmlLabels":false"#));
+    }
+
+    #[test]
+    fn mermaid_cache_stores_and_evicts_diagrams() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let config = MermaidConfig::new();
+        let cache = MermaidCache::new(dir.path().join("diagrams")).with_max_entries(2);
+        assert_eq!(cache.get("graph TB\na-->b", &config), None);
+        assert_eq!(cache.evict().expect("evict"), 0);
+
+        cache
+            .insert("graph TB\na-->b", &config, "<svg>a</svg>")
+            .expect("insert");
+        assert_eq!(
+            cache.get("graph TB\na-->b", &config).as_deref(),
+            Some("<svg>a</svg>")
+        );
+        assert_eq!(cache.get("graph TB\na-->c", &config), None);
+        let dark = MermaidConfig::new().with_theme(MermaidTheme::Dark);
+        assert_eq!(cache.get("graph TB\na-->b", &dark), None);
+
+        // The least recently used diagram is evicted first
+        let old = SystemTime::now() - Duration::from_secs(3600);
+        let touch = |file: PathBuf, time: SystemTime| {
+            fs::File::options()
+                .append(true)
+                .open(file)
+                .and_then(|f| f.set_modified(time))
+                .expect("set modified");
+        };
+        touch(cache.file("graph TB\na-->b", &config), old);
+        cache
+            .insert("graph TB\na-->c", &config, "<svg>c</svg>")
+            .expect("insert");
+        touch(
+            cache.file("graph TB\na-->c", &config),
+            old - Duration::from_secs(60),
+        );
+        assert!(cache.get("graph TB\na-->b", &config).is_some());
+        cache
+            .insert("graph TB\na-->d", &config, "<svg>d</svg>")
+            .expect("insert");
+        assert_eq!(cache.get("graph TB\na-->c", &config), None);
+        assert!(cache.get("graph TB\na-->b", &config).is_some());
+        assert!(cache.get("graph TB\na-->d", &config).is_some());
+
+        // Expired diagrams are not used
+        let cache = cache.with_max_age(Duration::from_secs(60));
+        touch(cache.file("graph TB\na-->d", &config), old);
+        assert_eq!(cache.get("graph TB\na-->d", &config), None);
+        assert_eq!(cache.evict().expect("evict"), 0);
+
+        cache.clear().expect("clear");
+        assert_eq!(cache.get("graph TB\na-->b", &config), None);
+        let size_limited = MermaidCache::new(cache.directory()).with_max_size(10);
+        size_limited
+            .insert("a", &config, "<svg>a</svg>")
+            .expect("insert");
+        assert_eq!(size_limited.get("a", &config), None);
+    }
+
+    #[test]
+    fn chrome_renderer_uses_cache() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let config = MermaidConfig::new();
+        let cache = MermaidCache::new(dir.path());
+        let source = "graph LR\ncached-->diagram";
+        let svg = r#"<svg xmlns="http://www.w3.org/2000/svg" width="1" height="1"></svg>"#;
+        cache.insert(source, &config, svg).expect("insert");
+
+        // The cached SVG is returned without starting Chrome
+        Mermaid::set_cache(Some(cache));
+        let result = ChromeRenderer.render_svg(source);
+        Mermaid::set_cache(None);
+        assert_eq!(result.expect("cached svg"), svg);
+    }
+
+    #[test]
+    fn mermaid_fallback_prints_source_on_timeout() {
+        let (r, context) = crate::test_util::renderer(Size::new(200.0, 200.0));
+        let area = r.first_page().first_layer().area();
+
+        // A renderer that hangs like the helper page in headless Chrome
+        let hanging = |_: &str| -> Result<String, Error> {
+            std::thread::sleep(Duration::from_secs(2));
+            Err(Error::new("too late", ErrorKind::Internal))
+        };
+        let mut m = Mermaid::new("graph TB\n\na-->b")
+            .with_renderer(hanging)
+            .with_timeout(Duration::from_millis(50));
+        let err = m
+            .render(&context, area.clone(), Style::new())
+            .expect_err("timeout");
+        assert!(err.to_string().contains("timed out"));
+
+        let mut m = m.with_fallback();
+        let res = m
+            .render(&context, area.clone(), Style::new())
+            .expect("fallback");
+        assert!(!res.has_more);
+        let line_height = Style::new().line_height(&context.font_cache);
+        assert!(res.size.height > line_height * 3.0);
+        let warnings = context.take_warnings();
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].contains("timed out"));
+
+        // The fallback is reused without calling the renderer again
+        assert!(m.fallback_element.is_some());
+        m.render(&context, area, Style::new()).expect("fallback");
+        assert!(context.take_warnings().is_empty());
+    }
+
+    #[test]
+    fn mermaid_renders_next_diagram_after_timeout() {
+        let (r, context) = crate::test_util::renderer(Size::new(200.0, 200.0));
+        let area = r.first_page().first_layer().area();
+
+        // Only the first call hangs, later diagrams must not wait for it
+        let calls = Arc::new(Mutex::new(0));
+        let counter = calls.clone();
+        let svg = r#"<svg xmlns="http://www.w3.org/2000/svg" width="40" height="20"></svg>"#;
+        let renderer = move |_: &str| -> Result<String, Error> {
+            let call = {
+                let mut calls = counter.lock().unwrap();
+                *calls += 1;
+                *calls
+            };
+            if call == 1 {
+                std::thread::sleep(Duration::from_secs(2));
+            }
+            Ok(svg.to_owned())
+        };
+        let mut m = Mermaid::new("graph TB\na-->b")
+            .with_renderer(renderer.clone())
+            .with_timeout(Duration::from_millis(50));
+        let err = m
+            .render(&context, area.clone(), Style::new())
+            .expect_err("timeout");
+        assert!(err.to_string().contains("timed out"));
+
+        let start = Instant::now();
+        let mut m = Mermaid::new("graph TB\nb-->c")
+            .with_renderer(renderer)
+            .with_timeout(Duration::from_millis(500));
+        m.render(&context, area, Style::new()).expect("render");
+        assert!(start.elapsed() < Duration::from_secs(1));
+        assert_eq!(*calls.lock().unwrap(), 2);
+    }
+
+    #[test]
+    fn mermaid_measure_reuses_rendered_diagram() {
+        let (r, context) = crate::test_util::renderer(Size::new(200.0, 200.0));
+        let area = r.first_page().first_layer().area();
+
+        let calls = Arc::new(Mutex::new(0));
+        let counter = calls.clone();
+        let svg = r#"<svg xmlns="http://www.w3.org/2000/svg" width="40" height="20"></svg>"#;
+        let mut m = Mermaid::new("graph TB\na-->b").with_renderer(
+            move |_: &str| -> Result<String, Error> {
+                *counter.lock().unwrap() += 1;
+                Ok(svg.to_owned())
+            },
+        );
+        m.measure(&context, area.size(), Style::new())
+            .expect("measure");
+        m.render(&context, area.clone(), Style::new())
+            .expect("render");
+        assert_eq!(*calls.lock().unwrap(), 1);
+
+        // Failures are only rendered once if the fallback is enabled
+        let calls = Arc::new(Mutex::new(0));
+        let counter = calls.clone();
+        let mut m = Mermaid::new("graph TB\na-->b")
+            .with_renderer(move |_: &str| -> Result<String, Error> {
+                *counter.lock().unwrap() += 1;
+                Err(Error::new("broken", ErrorKind::InvalidData))
+            })
+            .with_fallback();
+        m.measure(&context, area.size(), Style::new())
+            .expect("measure");
+        m.render(&context, area, Style::new()).expect("fallback");
+        assert_eq!(*calls.lock().unwrap(), 1);
+        assert_eq!(context.take_warnings().len(), 1);
+    }
+
+    #[test]
+    fn mermaid_config_builds_nested_options() {
+        let config = MermaidConfig::new();
+        assert!(config.is_empty());
+        assert_eq!(config.to_json(), "{}");
+        assert_eq!(config.apply_to("graph TB\na-->b"), "graph TB\na-->b");
+
+        let config = MermaidConfig::new()
+            .with_theme(MermaidTheme::Forest)
+            .with_font_family("Noto Sans")
+            .with_option("flowchart", true)
+            .with_option("flowchart.curve", "basis")
+            .with_option("flowchart.padding", 10);
+        let json: serde_json::Value = serde_json::from_str(&config.to_json()).expect("json");
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "theme": "forest",
+                "fontFamily": "Noto Sans",
+                "themeVariables": { "fontFamily": "Noto Sans" },
+                "flowchart": { "curve": "basis", "padding": 10 },
+            })
+        );
+    }
+
+    #[test]
+    fn mermaid_config_is_added_after_front_matter() {
+        let config = MermaidConfig::new().with_theme(MermaidTheme::Neutral);
+        let directive = "%%{init: {\"theme\":\"neutral\"}}%%\n";
+        assert_eq!(
+            config.apply_to("graph TB\na-->b"),
+            format!("{}graph TB\na-->b", directive)
+        );
+        assert_eq!(
+            config.apply_to("\n---\ntitle: T\n---\ngraph TB\na-->b"),
+            format!("\n---\ntitle: T\n---\n{}graph TB\na-->b", directive)
+        );
+        assert_eq!(
+            config.apply_to("---\n---\ngraph TB"),
+            format!("---\n---\n{}graph TB", directive)
+        );
+        // An unterminated front matter is left to Mermaid
+        assert_eq!(
+            config.apply_to("---\ngraph TB"),
+            format!("{}---\ngraph TB", directive)
+        );
+
+        // Renderers without explicit configuration support receive the directive
+        let renderer = |source: &str| -> Result<String, Error> { Ok(source.to_owned()) };
+        assert_eq!(
+            renderer
+                .render_svg_with_config("graph TB\na-->b", &config)
+                .expect("render"),
+            format!("{}graph TB\na-->b", directive)
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn command_renderer_pipes_source_through_command() {
+        let svg = r#"<svg xmlns="http://www.w3.org/2000/svg" width="1" height="1"></svg>"#;
+        let cat = CommandRenderer::new("cat");
+        assert_eq!(cat.render_svg(svg).expect("cat"), svg);
+
+        let err = CommandRenderer::new("sh")
+            .with_args(["-c", "echo broken >&2; exit 3"])
+            .render_svg(svg)
+            .expect_err("failing command");
+        assert!(matches!(err.kind(), ErrorKind::InvalidData));
+        assert!(err.to_string().contains("broken"));
+
+        let err = CommandRenderer::new("genpdfi-missing-diagram-renderer")
+            .render_svg(svg)
+            .expect_err("missing command");
+        assert!(matches!(err.kind(), ErrorKind::IoError(_)));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn command_renderer_kills_command_after_timeout() {
+        let start = Instant::now();
+        let err = CommandRenderer::new("sleep")
+            .with_args(["5"])
+            .render_svg_with_timeout(
+                "graph TB\na-->b",
+                &MermaidConfig::new(),
+                Duration::from_millis(100),
+            )
+            .expect_err("timeout");
+        assert!(err.to_string().contains("timed out"));
+        assert!(start.elapsed() < Duration::from_secs(4));
+    }
+
     #[test]
     #[ignore = "    Requires headless Chrome; enable and run manually in suitable environment"]
     fn invalid_syntax_returns_error() {
@@ -0,0 +1,629 @@
+// Copyright (c) 2026 Ronan Le Meillat - SCTG Development
+//
+// SPDX-License-Identifier: MIT OR Apache-2.0
+// Licensed under the MIT License or the Apache License, Version 2.0
+
+//! Conversion of SVG text to outlines.
+//!
+//! `printpdf` renders the `<text>` elements of SVG images with the fonts that its SVG converter
+//! finds on the system, so the result differs between machines.  The functions in this module
+//! replace `<text>` elements with `<path>` elements that contain the outlines of the glyphs of
+//! fonts loaded into a [`FontCache`][].
+//!
+//! Only a subset of SVG text layout is supported: absolute and relative positions (`x`, `y`,
+//! `dx` and `dy`, using the first value of a list), `<tspan>` elements, the font size, weight and
+//! style and the `text-anchor` property.  Font properties are inherited from the ancestors of the
+//! `<text>` element if they are set as attributes or in a `style` attribute, but CSS rules from
+//! `<style>` elements are ignored.  The `font-family` property is ignored as all text is set
+//! with the given font family.
+//!
+//! [`FontCache`]: ../../fonts/struct.FontCache.html
+
+use std::fmt::Write as _;
+
+use crate::fonts::{Font, FontCache, FontFamily};
+use crate::style::Style;
+
+/// The default font size of SVG text in user units.
+const DEFAULT_FONT_SIZE: f32 = 16.0;
+
+/// The attributes of `<text>` and `<tspan>` elements that only apply to text layout and are not
+/// copied to the generated elements, in addition to the `font-*` attributes.
+const TEXT_ATTRIBUTES: &[&str] = &[
+    "x",
+    "y",
+    "dx",
+    "dy",
+    "rotate",
+    "textLength",
+    "lengthAdjust",
+    "text-anchor",
+    "font",
+];
+
+/// The alignment of a text chunk relative to its start position.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Anchor {
+    Start,
+    Middle,
+    End,
+}
+
+/// The inherited font properties of an SVG element.
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct TextProperties {
+    font_size: f32,
+    bold: bool,
+    italic: bool,
+    anchor: Anchor,
+}
+
+impl Default for TextProperties {
+    fn default() -> Self {
+        TextProperties {
+            font_size: DEFAULT_FONT_SIZE,
+            bold: false,
+            italic: false,
+            anchor: Anchor::Start,
+        }
+    }
+}
+
+impl TextProperties {
+    /// Returns the properties of an element with the given attributes and these inherited
+    /// properties.  Declarations in the `style` attribute take precedence.
+    fn apply(mut self, tag: &Tag<'_>) -> Self {
+        let style = tag.attribute("style").unwrap_or_default();
+        let declarations = style.split(';').filter_map(|declaration| {
+            let (name, value) = declaration.split_once(':')?;
+            Some((name.trim().to_owned(), value.trim().to_owned()))
+        });
+        for (name, value) in tag.attributes.iter().cloned().chain(declarations) {
+            match name.as_str() {
+                "font-size" => {
+                    if let Some(size) = parse_length(&value, self.font_size) {
+                        self.font_size = size;
+                    }
+                }
+                "font-weight" => {
+                    self.bold = match value.as_str() {
+                        "bold" | "bolder" => true,
+                        "normal" | "lighter" => false,
+                        value => value.parse::<u16>().map(|w| w >= 600).unwrap_or(self.bold),
+                    }
+                }
+                "font-style" => self.italic = matches!(value.as_str(), "italic" | "oblique"),
+                "text-anchor" => {
+                    self.anchor = match value.as_str() {
+                        "middle" => Anchor::Middle,
+                        "end" => Anchor::End,
+                        _ => Anchor::Start,
+                    }
+                }
+                _ => {}
+            }
+        }
+        self
+    }
+
+    fn font(&self, family: FontFamily<Font>) -> Font {
+        let mut style = Style::new();
+        if self.bold {
+            style.set_bold();
+        }
+        if self.italic {
+            style.set_italic();
+        }
+        family.get(style)
+    }
+}
+
+/// A parsed start tag of an SVG element.
+struct Tag<'s> {
+    name: &'s str,
+    attributes: Vec<(String, String)>,
+    self_closing: bool,
+}
+
+impl<'s> Tag<'s> {
+    /// Parses a start tag including the angle brackets.
+    fn parse(tag: &'s str) -> Tag<'s> {
+        let inner = tag.trim_start_matches('<').trim_end_matches('>');
+        let self_closing = inner.ends_with('/');
+        let inner = inner.trim_end_matches('/');
+        let name_end = inner
+            .find(|c: char| c.is_whitespace())
+            .unwrap_or(inner.len());
+        let raw_attributes = inner[name_end..].trim();
+
+        let mut attributes = Vec::new();
+        let mut rest = raw_attributes;
+        while let Some(eq) = rest.find('=') {
+            let name = rest[..eq].trim();
+            let value = rest[eq + 1..].trim_start();
+            let quote = match value.chars().next() {
+                Some(quote @ ('"' | '\'')) => quote,
+                _ => break,
+            };
+            let Some(end) = value[1..].find(quote) else {
+                break;
+            };
+            attributes.push((name.to_owned(), decode_entities(&value[1..=end])));
+            rest = &value[end + 2..];
+        }
+
+        Tag {
+            name: &inner[..name_end],
+            attributes,
+            self_closing,
+        }
+    }
+
+    fn attribute(&self, name: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Returns the first value of a coordinate attribute like `x` or `dy`.
+    fn coordinate(&self, name: &str, font_size: f32) -> Option<f32> {
+        let value = self.attribute(name)?;
+        let first = value
+            .split(|c: char| c.is_whitespace() || c == ',')
+            .find(|s| !s.is_empty())?;
+        parse_length(first, font_size)
+    }
+
+    /// Returns the start tag of an element with the given name and the attributes of this tag,
+    /// except for the text layout attributes, see `TEXT_ATTRIBUTES`.
+    fn with_name(&self, name: &str, extra: &str) -> String {
+        let mut tag = format!("<{}", name);
+        for (name, value) in &self.attributes {
+            if !TEXT_ATTRIBUTES.contains(&name.as_str()) && !name.starts_with("font-") {
+                let _ = write!(tag, " {}=\"{}\"", name, encode_attribute(value));
+            }
+        }
+        if !extra.is_empty() {
+            tag.push(' ');
+            tag.push_str(extra);
+        }
+        tag
+    }
+}
+
+/// Parses a length in user units, resolving relative units with the given font size.
+fn parse_length(value: &str, font_size: f32) -> Option<f32> {
+    let value = value.trim();
+    let units: [(&str, f32); 7] = [
+        ("px", 1.0),
+        ("pt", 96.0 / 72.0),
+        ("pc", 16.0),
+        ("mm", 96.0 / 25.4),
+        ("cm", 96.0 / 2.54),
+        ("in", 96.0),
+        ("em", font_size),
+    ];
+    for (unit, factor) in units {
+        if let Some(number) = value.strip_suffix(unit) {
+            return number.trim().parse::<f32>().ok().map(|n| n * factor);
+        }
+    }
+    if let Some(number) = value.strip_suffix('%') {
+        return number
+            .trim()
+            .parse::<f32>()
+            .ok()
+            .map(|n| n / 100.0 * font_size);
+    }
+    value.parse().ok()
+}
+
+/// Replaces the predefined XML entities and character references.
+fn decode_entities(s: &str) -> String {
+    let mut result = String::with_capacity(s.len());
+    let mut rest = s;
+    while let Some(start) = rest.find('&') {
+        result.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let decoded = rest.find(';').and_then(|end| {
+            let c = match &rest[1..end] {
+                "lt" => '<',
+                "gt" => '>',
+                "amp" => '&',
+                "quot" => '"',
+                "apos" => '\'',
+                "nbsp" => '\u{A0}',
+                entity => {
+                    let code = if let Some(hex) = entity
+                        .strip_prefix("#x")
+                        .or_else(|| entity.strip_prefix("#X"))
+                    {
+                        u32::from_str_radix(hex, 16).ok()?
+                    } else {
+                        entity.strip_prefix('#')?.parse().ok()?
+                    };
+                    char::from_u32(code)?
+                }
+            };
+            Some((c, end + 1))
+        });
+        match decoded {
+            Some((c, len)) => {
+                result.push(c);
+                rest = &rest[len..];
+            }
+            None => {
+                result.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    result.push_str(rest);
+    result
+}
+
+/// Escapes the characters of an attribute value that must not appear in a quoted value.
+fn encode_attribute(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('"', "&quot;")
+}
+
+/// Returns the length of the tag at the start of the string, ignoring `>` in quoted attribute
+/// values.
+fn tag_len(s: &str) -> Option<usize> {
+    let mut quote = None;
+    for (i, c) in s.char_indices() {
+        match (quote, c) {
+            (None, '"' | '\'') => quote = Some(c),
+            (Some(q), c) if q == c => quote = None,
+            (None, '>') => return Some(i + 1),
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Writes glyph outlines as SVG path data.
+struct PathData<'a> {
+    data: &'a mut String,
+    x: f32,
+    y: f32,
+    font_size: f32,
+}
+
+impl PathData<'_> {
+    fn point(&mut self, x: f32, y: f32) {
+        let _ = write!(
+            self.data,
+            "{:.3} {:.3} ",
+            self.x + x * self.font_size,
+            self.y + y * self.font_size
+        );
+    }
+}
+
+impl rusttype::OutlineBuilder for PathData<'_> {
+    fn move_to(&mut self, x: f32, y: f32) {
+        self.data.push('M');
+        self.point(x, y);
+    }
+
+    fn line_to(&mut self, x: f32, y: f32) {
+        self.data.push('L');
+        self.point(x, y);
+    }
+
+    fn quad_to(&mut self, x1: f32, y1: f32, x: f32, y: f32) {
+        self.data.push('Q');
+        self.point(x1, y1);
+        self.point(x, y);
+    }
+
+    fn curve_to(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, x: f32, y: f32) {
+        self.data.push('C');
+        self.point(x1, y1);
+        self.point(x2, y2);
+        self.point(x, y);
+    }
+
+    fn close(&mut self) {
+        self.data.push_str("Z ");
+    }
+}
+
+/// A sequence of glyphs that is aligned as a whole according to the text anchor.
+struct Chunk {
+    anchor: Anchor,
+    start: f32,
+    end: f32,
+    /// The `<path>` elements of the chunk.
+    paths: String,
+}
+
+/// Lays out the content of a `<text>` element.
+struct TextLayout<'f> {
+    font_cache: &'f FontCache,
+    family: FontFamily<Font>,
+    x: f32,
+    y: f32,
+    chunks: Vec<Chunk>,
+    /// Whether collapsed white space has to be inserted before the next glyph.
+    pending_space: bool,
+}
+
+impl TextLayout<'_> {
+    /// Moves to the position set by the given `<text>` or `<tspan>` tag, starting a new chunk
+    /// if an absolute position is set.
+    fn position(&mut self, tag: &Tag<'_>, properties: TextProperties) {
+        let x = tag.coordinate("x", properties.font_size);
+        let y = tag.coordinate("y", properties.font_size);
+        if x.is_some() || y.is_some() || self.chunks.is_empty() {
+            self.x = x.unwrap_or(self.x);
+            self.y = y.unwrap_or(self.y);
+            self.chunks.push(Chunk {
+                anchor: properties.anchor,
+                start: self.x,
+                end: self.x,
+                paths: String::new(),
+            });
+            self.pending_space = false;
+        }
+        self.x += tag.coordinate("dx", properties.font_size).unwrap_or(0.0);
+        self.y += tag.coordinate("dy", properties.font_size).unwrap_or(0.0);
+    }
+
+    /// Adds the outlines of the given text with collapsed white space to the current chunk.
+    ///
+    /// The outlines are written to a `<path>` element with the attributes of the given tag.
+    fn add_text(&mut self, text: &str, tag: &Tag<'_>, properties: TextProperties) {
+        let font = properties.font(self.family);
+        let mut data = String::new();
+        let chunk = self.chunks.last_mut().expect("no text chunk");
+        for c in text.chars() {
+            if c.is_whitespace() && c != '\u{A0}' {
+                // Leading white space of a chunk is dropped
+                self.pending_space = chunk.end > chunk.start || !chunk.paths.is_empty();
+                continue;
+            }
+            let space = if self.pending_space { Some(' ') } else { None };
+            for c in space.into_iter().chain(Some(c)) {
+                let mut path = PathData {
+                    data: &mut data,
+                    x: self.x,
+                    y: self.y,
+                    font_size: properties.font_size,
+                };
+                self.x += font.char_outline(self.font_cache, c, &mut path) * properties.font_size;
+            }
+            self.pending_space = false;
+            chunk.end = self.x;
+        }
+        if !data.is_empty() {
+            let _ = write!(
+                chunk.paths,
+                "{}/>",
+                tag.with_name("path", &format!("d=\"{}\"", data.trim_end()))
+            );
+        }
+    }
+
+    /// Returns the SVG markup for the laid out chunks.
+    fn finish(self) -> String {
+        let mut result = String::new();
+        for chunk in self.chunks {
+            let width = chunk.end - chunk.start;
+            let shift = match chunk.anchor {
+                Anchor::Start => 0.0,
+                Anchor::Middle => -width / 2.0,
+                Anchor::End => -width,
+            };
+            if shift == 0.0 {
+                result.push_str(&chunk.paths);
+            } else {
+                let _ = write!(
+                    result,
+                    "<g transform=\"translate({:.3} 0)\">{}</g>",
+                    shift, chunk.paths
+                );
+            }
+        }
+        result
+    }
+}
+
+/// Converts the content of a `<text>` element to a group of `<path>` elements.
+fn convert_text(
+    text: &Tag<'_>,
+    properties: TextProperties,
+    content: &str,
+    font_cache: &FontCache,
+    family: FontFamily<Font>,
+) -> String {
+    let mut layout = TextLayout {
+        font_cache,
+        family,
+        x: 0.0,
+        y: 0.0,
+        chunks: Vec::new(),
+        pending_space: false,
+    };
+    layout.position(text, properties);
+
+    // The tags and properties of the open <text> and <tspan> elements.  The attributes of the
+    // <text> element are set on the surrounding group, so its own paths have no attributes.
+    let mut stack = vec![(Tag::parse("<text>"), properties)];
+    let mut rest = content;
+    while !rest.is_empty() {
+        let start = rest.find('<').unwrap_or(rest.len());
+        if start > 0 {
+            let (tag, properties) = stack.last().expect("empty element stack");
+            layout.add_text(&decode_entities(&rest[..start]), tag, *properties);
+            rest = &rest[start..];
+            continue;
+        }
+        let len = tag_len(rest).unwrap_or(rest.len());
+        let tag = &rest[..len];
+        rest = &rest[len..];
+        if tag.starts_with("</") {
+            if tag.starts_with("</tspan") && stack.len() > 1 {
+                stack.pop();
+            }
+        } else if tag.starts_with("<tspan") {
+            let tag = Tag::parse(tag);
+            let properties = stack.last().expect("empty element stack").1.apply(&tag);
+            layout.position(&tag, properties);
+            if !tag.self_closing {
+                stack.push((tag, properties));
+            }
+        } else if tag.starts_with("<title") || tag.starts_with("<desc") {
+            // Descriptive elements are not rendered
+            let name = if tag.starts_with("<title") {
+                "</title>"
+            } else {
+                "</desc>"
+            };
+            if !tag.ends_with("/>") {
+                let end = rest
+                    .find(name)
+                    .map(|i| i + name.len())
+                    .unwrap_or(rest.len());
+                rest = &rest[end..];
+            }
+        }
+    }
+
+    format!("{}>{}</g>", text.with_name("g", ""), layout.finish())
+}
+
+/// Replaces all `<text>` elements of the given SVG markup with the outlines of the glyphs of
+/// the given font family.
+///
+/// The font is selected from the family according to the font weight and style of the text.
+/// Other elements are left unchanged.
+pub(crate) fn text_to_paths(svg: &str, font_cache: &FontCache, family: FontFamily<Font>) -> String {
+    let mut result = String::with_capacity(svg.len());
+    // The inherited properties of the open elements
+    let mut stack: Vec<TextProperties> = Vec::new();
+    let mut rest = svg;
+    while let Some(start) = rest.find('<') {
+        result.push_str(&rest[..start]);
+        rest = &rest[start..];
+
+        // Comments, CDATA sections, processing instructions and declarations are copied
+        let end_marker = if rest.starts_with("<!--") {
+            Some("-->")
+        } else if rest.starts_with("<![CDATA[") {
+            Some("]]>")
+        } else if rest.starts_with("<?") || rest.starts_with("<!") {
+            Some(">")
+        } else {
+            None
+        };
+        let len = match end_marker {
+            Some(marker) => rest.find(marker).map(|i| i + marker.len()),
+            None => tag_len(rest),
+        };
+        let Some(len) = len else {
+            break;
+        };
+        let tag = &rest[..len];
+        if end_marker.is_some() || tag.starts_with("</") {
+            if end_marker.is_none() {
+                stack.pop();
+            }
+            result.push_str(tag);
+            rest = &rest[len..];
+            continue;
+        }
+
+        let tag = Tag::parse(tag);
+        let properties = stack.last().copied().unwrap_or_default().apply(&tag);
+        if tag.name == "text" && !tag.self_closing {
+            if let Some(end) = rest[len..].find("</text>") {
+                let content = &rest[len..len + end];
+                result.push_str(&convert_text(&tag, properties, content, font_cache, family));
+                rest = &rest[len + end + "</text>".len()..];
+                continue;
+            }
+        }
+        if !tag.self_closing {
+            stack.push(properties);
+        }
+        result.push_str(&rest[..len]);
+        rest = &rest[len..];
+    }
+    result.push_str(rest);
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_length, text_to_paths};
+    use crate::test_util;
+
+    #[test]
+    fn test_parse_length() {
+        assert_eq!(parse_length("12", 16.0), Some(12.0));
+        assert_eq!(parse_length("12px", 16.0), Some(12.0));
+        assert_eq!(parse_length("1.5em", 10.0), Some(15.0));
+        assert_eq!(parse_length("6pt", 16.0), Some(8.0));
+        assert_eq!(parse_length("large", 16.0), None);
+    }
+
+    #[test]
+    fn test_text_is_converted_to_paths() {
+        let cache = test_util::font_cache();
+        let family = cache.default_font_family();
+        let svg = r##"<svg xmlns="http://www.w3.org/2000/svg" width="100" height="50">
+            <!-- <text>not a text</text> -->
+            <g font-size="10"><text x="10" y="20" fill="#f00">A &amp; B</text></g>
+            <rect width="10" height="10"/>
+        </svg>"##;
+        let converted = text_to_paths(svg, &cache, family);
+        assert!(converted.contains("<!-- <text>not a text</text> -->"));
+        assert!(converted.contains("<rect width=\"10\" height=\"10\"/>"));
+        assert!(!converted.contains("A &amp; B"));
+        assert!(converted.contains("<g fill=\"#f00\"><path d=\"M"));
+
+        // The inherited font size is used: "A" starts at x = 10 and the glyphs are less than one
+        // em (10 units) high above the baseline at y = 20.
+        let start = converted.find("d=\"").expect("path data") + 3;
+        let end = start + converted[start..].find('"').expect("end of path data");
+        let numbers: Vec<f32> = converted[start..end]
+            .split(|c: char| c.is_ascii_alphabetic() || c.is_whitespace())
+            .filter_map(|s| s.parse().ok())
+            .collect();
+        let mut xs = numbers.iter().step_by(2);
+        let mut ys = numbers.iter().skip(1).step_by(2);
+        assert!(xs.all(|x| *x >= 10.0 && *x < 40.0));
+        assert!(ys.all(|y| *y > 10.0 && *y < 21.0));
+    }
+
+    #[test]
+    fn test_text_anchor_and_tspans() {
+        let cache = test_util::font_cache();
+        let family = cache.default_font_family();
+        let svg = r#"<svg xmlns="http://www.w3.org/2000/svg"><text x="50" y="10" text-anchor="middle" style="font-size: 8px">
+                <tspan x="50" dy="1em">First</tspan>
+                <tspan x="50" dy="1em" font-weight="bold">Second</tspan>
+            </text></svg>"#;
+        let converted = text_to_paths(svg, &cache, family);
+        assert!(!converted.contains("<text"));
+        assert!(!converted.contains("<tspan"));
+        // One centered group per line
+        assert_eq!(converted.matches("<g transform=\"translate(-").count(), 2);
+        // The text layout attributes are not copied to the paths
+        assert_eq!(converted.matches("<path d=\"M").count(), 2);
+        assert!(!converted.contains("dy="));
+        assert!(!converted.contains("font-weight="));
+        assert!(!converted.contains("text-anchor="));
+
+        let image = crate::elements::Image::from_svg_string_with_fonts(
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="100" height="50"><text y="20">Label</text></svg>"#,
+            &cache,
+        );
+        assert!(image.is_ok());
+    }
+}
@@ -135,6 +135,16 @@ pub enum ErrorKind {
     /// *Only available if the `images` feature is enabled.*
     #[cfg(feature = "images")]
     ImageError(image::ImageError),
+    /// A LaTeX formula could not be rendered by MicroTeX.
+    ///
+    /// *Only available if the `latex` feature is enabled.*
+    #[cfg(feature = "latex")]
+    LatexError {
+        /// The LaTeX source of the formula.
+        formula: String,
+        /// The message of the LaTeX parser.
+        message: String,
+    },
 }
 
 impl From<io::Error> for ErrorKind {
@@ -94,6 +94,10 @@ pub struct FontCache {
     // a font, but the default font is always loaded in new, so this options is always some
     // (outside of new).
     default_font_family: Option<FontFamily<Font>>,
+    // The built-in Courier family that prints the source of formulas and diagrams that could not
+    // be rendered.  It is always some (outside of new).
+    #[cfg(any(feature = "latex", feature = "mermaid"))]
+    source_font_family: Option<FontFamily<Font>>,
     // Cache to deduplicate embedded fonts by their data pointer
     embedded_font_cache: HashMap<*const Vec<u8>, crate::render::IndirectFontRef>,
 }
@@ -117,9 +121,21 @@ impl FontCache {
             fonts: Vec::new(),
             pdf_fonts: Vec::new(),
             default_font_family: None,
+            #[cfg(any(feature = "latex", feature = "mermaid"))]
+            source_font_family: None,
             embedded_font_cache: HashMap::new(),
         };
         font_cache.default_font_family = Some(font_cache.add_font_family(default_font_family));
+        #[cfg(any(feature = "latex", feature = "mermaid"))]
+        {
+            let courier = FontFamily {
+                regular: FontData::courier(FontStyle::Regular),
+                bold: FontData::courier(FontStyle::Bold),
+                italic: FontData::courier(FontStyle::Italic),
+                bold_italic: FontData::courier(FontStyle::BoldItalic),
+            };
+            font_cache.source_font_family = Some(font_cache.add_font_family(courier));
+        }
         font_cache
     }
 
@@ -205,6 +221,14 @@ impl FontCache {
             .expect("Invariant violated: no default font family for FontCache")
     }
 
+    /// Returns the built-in Courier font family that prints the source of formulas and diagrams
+    /// that could not be rendered if no other font family has been set.
+    #[cfg(any(feature = "latex", feature = "mermaid"))]
+    pub(crate) fn source_font_family(&self) -> FontFamily<Font> {
+        self.source_font_family
+            .expect("Invariant violated: no source font family for FontCache")
+    }
+
     /// Returns a reference to the emebdded PDF font for the given font, if available.
     ///
     /// This method may only be called with [`Font`][] instances that have been created by this
@@ -236,6 +260,8 @@ impl Clone for FontCache {
             fonts: self.fonts.clone(),
             pdf_fonts: Vec::new(),
             default_font_family: self.default_font_family,
+            #[cfg(any(feature = "latex", feature = "mermaid"))]
+            source_font_family: self.source_font_family,
             embedded_font_cache: HashMap::new(),
         }
     }
@@ -251,6 +277,23 @@ pub struct FontData {
 }
 
 impl FontData {
+    /// Returns the given style of the built-in Courier font.
+    ///
+    /// The metrics are read from the bundled Courier Prime font, which has the same fixed advance
+    /// width as Courier in all styles.
+    #[cfg(any(feature = "latex", feature = "mermaid"))]
+    fn courier(style: FontStyle) -> FontData {
+        static METRICS: &[u8] = include_bytes!(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/fonts/CourierPrime-Regular.ttf"
+        ));
+        FontData {
+            rt_font: rusttype::Font::try_from_bytes(METRICS)
+                .expect("Invariant violated: the bundled Courier Prime font is invalid"),
+            raw_data: RawFontData::Builtin(Builtin::Courier.style(style)),
+        }
+    }
+
     /// Loads a font from the given data.
     ///
     /// The provided data must by readable by [`rusttype`][].  If `builtin` is set, a built-in PDF
@@ -1012,6 +1055,26 @@ impl Font {
         )))
     }
 
+    /// Adds the outline of a character to the given builder and returns the advance width of the
+    /// character.
+    ///
+    /// The outline and the advance width are given in units of the font size, i. e. they have to
+    /// be multiplied with the font size.  The origin is on the baseline and the y axis points
+    /// downwards.
+    pub(crate) fn char_outline(
+        &self,
+        font_cache: &FontCache,
+        c: char,
+        builder: &mut impl rusttype::OutlineBuilder,
+    ) -> f32 {
+        font_cache
+            .get_rt_font(*self)
+            .glyph(c)
+            .scaled(self.scale)
+            .build_outline(builder);
+        self.char_h_metrics(font_cache, c).advance_width
+    }
+
     fn char_h_metrics(&self, font_cache: &FontCache, c: char) -> rusttype::HMetrics {
         // If this is a built-in font, use standardized metrics instead of system font metrics
         if self.is_builtin {
@@ -12,6 +12,8 @@
 //! libraries or tools.
 #![warn(missing_docs, rust_2018_idioms)]
 
+#[cfg(test)]
+mod test_util;
 mod wrap;
 
 pub mod elements;
@@ -21,8 +23,10 @@ pub mod render;
 pub mod style;
 pub mod subsetting;
 
+use std::cell;
 use std::fs;
 use std::io;
+use std::mem;
 use std::path;
 
 use derive_more::{Add, AddAssign, Div, DivAssign, Into, Mul, MulAssign, Sub, SubAssign, Sum};
@@ -437,7 +441,9 @@ impl<T: Into<Mm>> From<T> for Margins {
 /// [`SimplePageDecorator`][] for a basic implementation.
 ///
 /// If the `hyphenation` feature is enabled, users can activate hyphenation with the
-/// [`set_hyphenator`][] method.
+/// [`set_hyphenator`][] method.  Additional hyphenators for text in other languages (see
+/// [`Style::set_language`][]) can be registered with [`add_hyphenator`][] and
+/// [`load_hyphenator`][].  Soft hyphens (U+00AD) in the text are always used as break points.
 ///
 /// # Example
 ///
@@ -457,7 +463,10 @@ impl<T: Into<Mm>> From<T> for Margins {
 /// [`push`]: #method.push
 /// [`render`]: #method.render
 /// [`render_to_file`]: #method.render_to_file
-/// [`set_hyphenation`]: #method.set_hyphenation
+/// [`set_hyphenator`]: #method.set_hyphenator
+/// [`add_hyphenator`]: #method.add_hyphenator
+/// [`load_hyphenator`]: #method.load_hyphenator
+/// [`Style::set_language`]: style/struct.Style.html#method.set_language
 /// [`set_page_decorator`]: #method.set_page_decorator
 /// [`PageDecorator`]: trait.PageDecorator.html
 /// [`SimplePageDecorator`]: struct.SimplePageDecorator.html
@@ -545,11 +554,127 @@ impl Document {
     /// Activates hyphenation and sets the hyphentor to use.
     ///
     /// *Only available if the `hyphenation` feature is enabled.*
+    ///
+    /// This hyphenator is used for all text without a language and for text with a language that
+    /// has no hyphenator registered with [`add_hyphenator`][].
+    ///
+    /// [`add_hyphenator`]: #method.add_hyphenator
     #[cfg(feature = "hyphenation")]
     pub fn set_hyphenator(&mut self, hyphenator: hyphenation::Standard) {
         self.context.hyphenator = Some(hyphenator);
     }
 
+    /// Sets the hyphenator to use for text with the given language.
+    ///
+    /// *Only available if the `hyphenation` feature is enabled.*
+    ///
+    /// The language of a text is set with [`Style::set_language`][].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # #[cfg(feature = "hyphenation")]
+    /// # {
+    /// use genpdfi_extended::{elements, fonts, style, Document};
+    /// use hyphenation::{Language, Load, Standard};
+    ///
+    /// let font_family = fonts::from_files(concat!(env!("CARGO_MANIFEST_DIR"), "/fonts"), "NotoSans", None)
+    ///     .expect("Failed to load font family");
+    /// let mut doc = Document::new(font_family);
+    /// let en = Standard::from_embedded(Language::EnglishUS).expect("Failed to load dictionary");
+    /// doc.add_hyphenator(Language::EnglishUS, en);
+    /// doc.push(elements::Paragraph::new("Hyphenation").styled(
+    ///     style::Style::new().with_language(Language::EnglishUS),
+    /// ));
+    /// # }
+    /// ```
+    ///
+    /// [`Style::set_language`]: style/struct.Style.html#method.set_language
+    #[cfg(feature = "hyphenation")]
+    pub fn add_hyphenator(
+        &mut self,
+        language: hyphenation::Language,
+        hyphenator: hyphenation::Standard,
+    ) {
+        self.context.hyphenators.insert(language, hyphenator);
+    }
+
+    /// Loads a hyphenation dictionary for the given language from the given file and uses it for
+    /// text with this language.
+    ///
+    /// *Only available if the `hyphenation` feature is enabled.*
+    ///
+    /// The file must contain a dictionary in the format used by the [`hyphenation`][] crate.
+    ///
+    /// [`hyphenation`]: https://docs.rs/hyphenation
+    #[cfg(feature = "hyphenation")]
+    pub fn load_hyphenator(
+        &mut self,
+        language: hyphenation::Language,
+        path: impl AsRef<path::Path>,
+    ) -> Result<(), error::Error> {
+        use hyphenation::Load as _;
+
+        let path = path.as_ref();
+        let hyphenator = hyphenation::Standard::from_path(language, path).map_err(|err| {
+            error::Error::new(
+                format!(
+                    "Could not load hyphenation dictionary {}: {}",
+                    path.display(),
+                    err
+                ),
+                error::ErrorKind::InvalidData,
+            )
+        })?;
+        self.add_hyphenator(language, hyphenator);
+        Ok(())
+    }
+
+    /// Defines a LaTeX macro that is expanded in all [`Latex`][] formulas of this document.
+    ///
+    /// *Only available if the `latex` feature is enabled.*
+    ///
+    /// See [`LatexMacros::define`][] for details.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # #[cfg(feature = "latex")]
+    /// # {
+    /// use genpdfi_extended::{elements, fonts, Document};
+    ///
+    /// let font_family = fonts::from_files(concat!(env!("CARGO_MANIFEST_DIR"), "/fonts"), "NotoSans", None)
+    ///     .expect("Failed to load font family");
+    /// let mut doc = Document::new(font_family);
+    /// doc.add_latex_macro("R", 0, r"\mathbb{R}");
+    /// doc.add_latex_macro("norm", 1, r"\left\lVert #1 \right\rVert");
+    /// doc.push(elements::Latex::new(r"\norm{x} \in \R", 12.0));
+    /// # }
+    /// ```
+    ///
+    /// [`Latex`]: elements/struct.Latex.html
+    /// [`LatexMacros::define`]: elements/struct.LatexMacros.html#method.define
+    #[cfg(feature = "latex")]
+    pub fn add_latex_macro(&mut self, name: &str, arguments: usize, definition: impl Into<String>) {
+        self.context
+            .latex_macros
+            .define(name, arguments, definition);
+    }
+
+    /// Defines the LaTeX macros of the given preamble for all [`Latex`][] formulas of this
+    /// document.
+    ///
+    /// *Only available if the `latex` feature is enabled.*
+    ///
+    /// See [`LatexMacros::load_preamble`][] for the supported commands.
+    ///
+    /// [`Latex`]: elements/struct.Latex.html
+    /// [`LatexMacros::load_preamble`]: elements/struct.LatexMacros.html#method.load_preamble
+    #[cfg(feature = "latex")]
+    pub fn load_latex_preamble(&mut self, preamble: &str) -> Result<(), error::Error> {
+        self.context.latex_macros.load_preamble(preamble)
+    }
+
     /// Sets the title of the PDF document.
     ///
     /// If this method is not called, the PDF title will be empty.
@@ -663,13 +788,23 @@ impl Document {
             renderer = renderer.with_modification_date(modification_date);
         }
         self.context.font_cache.load_pdf_fonts(&mut renderer)?;
+        self.context.reset_registries();
         let mut results = Vec::new();
         loop {
+            self.context.page.set(renderer.page_count());
             let mut area = renderer.last_page().last_layer().area();
             if let Some(decorator) = &mut self.decorator {
                 area = decorator.decorate_page(&self.context, area, self.style)?;
             }
-            let result = self.root.render(&self.context, area, self.style)?;
+            let mut result = self.root.render(&self.context, area.clone(), self.style)?;
+            if result.has_more && result.size == Size::new(0, 0) {
+                // Elements that moved themselves to the next page, for example paragraphs because
+                // of their orphans and widows, ignore these limits once they have been moved.
+                // Moving them again would not help if nothing has been rendered on this page, so
+                // we render them on this page instead.
+                result = self.root.render(&self.context, area, self.style)?;
+            }
+            result.warnings.extend(self.context.take_warnings());
             results.push(result.clone());
             if result.has_more {
                 if result.size == Size::new(0, 0) {
@@ -730,6 +865,13 @@ pub struct RenderResult {
     pub has_more: bool,
     /// Optional SVG string (useful for LaTeX and Mermaid diagrams)
     pub svg: Option<String>,
+    /// Non-fatal problems reported with [`Context::warn`][] while rendering a page.
+    ///
+    /// This field is filled by [`Document::render`][] for each page.
+    ///
+    /// [`Context::warn`]: struct.Context.html#method.warn
+    /// [`Document::render`]: struct.Document.html#method.render
+    pub warnings: Vec<String>,
 }
 
 /// A helper trait to make `PageDecorator` objects cloneable as trait objects.
@@ -946,6 +1088,63 @@ pub trait Element: ElementClone {
         style: style::Style,
     ) -> Result<RenderResult, error::Error>;
 
+    /// Measures this element without rendering it to the document.
+    ///
+    /// This method returns the [`RenderResult`][] that the next call to [`render`][] would return
+    /// for an area with the given size.  It does not change the state of this element, so it can
+    /// be used to make layout decisions before rendering.
+    ///
+    /// The default implementation renders a clone of this element into an off-screen area that is
+    /// discarded afterwards.  As a clone of a [`LinearLayout`][] starts with its first element,
+    /// the layout and the elements of this crate that wrap other elements override this method so
+    /// that partially rendered layouts are measured from the element where the rendering stopped.
+    /// Custom elements that wrap other elements should forward the measurement too.  Warnings, figures and equations reported during the measurement are
+    /// discarded too.  Other side effects of the rendering are not undone:  formulas and diagrams
+    /// are rendered with MicroTeX and Chrome again, and the results are stored in the
+    /// process-wide formula and diagram caches.
+    /// Elements can override this method if they can calculate their size more efficiently.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use genpdfi_extended::{elements, error, render, style, Context, Element, RenderResult};
+    ///
+    /// /// A paragraph that is only started if it fits completely into the area.
+    /// #[derive(Clone)]
+    /// struct Unbroken(elements::Paragraph);
+    ///
+    /// impl Element for Unbroken {
+    ///     fn render(
+    ///         &mut self,
+    ///         context: &Context,
+    ///         area: render::Area<'_>,
+    ///         style: style::Style,
+    ///     ) -> Result<RenderResult, error::Error> {
+    ///         if self.0.measure(context, area.size(), style)?.has_more {
+    ///             return Ok(RenderResult {
+    ///                 has_more: true,
+    ///                 ..Default::default()
+    ///             });
+    ///         }
+    ///         self.0.render(context, area, style)
+    ///     }
+    /// }
+    /// ```
+    ///
+    /// [`RenderResult`]: struct.RenderResult.html
+    /// [`render`]: #tymethod.render
+    /// [`LinearLayout`]: elements/struct.LinearLayout.html
+    fn measure(
+        &self,
+        context: &Context,
+        size: Size,
+        style: style::Style,
+    ) -> Result<RenderResult, error::Error> {
+        let renderer = render::Renderer::new(size, "")?;
+        let area = renderer.first_page().first_layer().area();
+        context.dry_run(|| self.clone_box().render(context, area, style))
+    }
+
     /// Draws a frame around this element using the given line style.
     fn framed(self, line_style: impl Into<style::LineStyle>) -> elements::FramedElement<Self>
     where
@@ -969,6 +1168,49 @@ pub trait Element: ElementClone {
     {
         elements::StyledElement::new(self, style.into())
     }
+
+    /// Prevents this element from being split across pages, see [`KeepTogether`][].
+    ///
+    /// [`KeepTogether`]: elements/struct.KeepTogether.html
+    fn kept_together(self) -> elements::KeepTogether<Self>
+    where
+        Self: Sized,
+    {
+        elements::KeepTogether::new(self)
+    }
+
+    /// Keeps this element on the same page as the following element, see [`KeepWithNext`][].
+    ///
+    /// [`KeepWithNext`]: elements/struct.KeepWithNext.html
+    fn kept_with_next(self) -> elements::KeepWithNext<Self>
+    where
+        Self: Sized,
+    {
+        elements::KeepWithNext::new(self)
+    }
+
+    /// Returns whether this element must not be split across pages.
+    ///
+    /// If this method returns true, a [`LinearLayout`][] moves this element to the next page if
+    /// it does not fit completely into the remaining space of the current page.  The default
+    /// implementation returns false.
+    ///
+    /// [`LinearLayout`]: elements/struct.LinearLayout.html
+    fn is_kept_together(&self) -> bool {
+        false
+    }
+
+    /// Returns whether this element must be rendered on the same page as the start of the
+    /// following element.
+    ///
+    /// If this method returns true, a [`LinearLayout`][] moves this element to the next page if
+    /// the following element cannot be started on the current page.  The default implementation
+    /// returns false.
+    ///
+    /// [`LinearLayout`]: elements/struct.LinearLayout.html
+    fn is_kept_with_next(&self) -> bool {
+        false
+    }
 }
 
 /// The context for a rendering process.
@@ -986,38 +1228,200 @@ pub struct Context {
     /// If this field is `None`, hyphenation is disabled.
     #[cfg(feature = "hyphenation")]
     pub hyphenator: Option<hyphenation::Standard>,
+    /// The hyphenators to use for text with a language, see [`Style::set_language`][].
+    ///
+    /// *Only available if the `hyphenation` feature is enabled.*
+    ///
+    /// [`Style::set_language`]: style/struct.Style.html#method.set_language
+    #[cfg(feature = "hyphenation")]
+    pub hyphenators: std::collections::HashMap<hyphenation::Language, hyphenation::Standard>,
+    /// The macros that are expanded in LaTeX formulas, see [`Document::add_latex_macro`][].
+    ///
+    /// *Only available if the `latex` feature is enabled.*
+    ///
+    /// [`Document::add_latex_macro`]: struct.Document.html#method.add_latex_macro
+    #[cfg(feature = "latex")]
+    pub latex_macros: elements::LatexMacros,
+    warnings: cell::RefCell<Vec<String>>,
+    figures: cell::RefCell<Vec<elements::FigureEntry>>,
+    equations: cell::RefCell<Vec<elements::EquationEntry>>,
+    equation_section: cell::Cell<Option<usize>>,
+    page: cell::Cell<usize>,
 }
 
 impl Clone for Context {
     fn clone(&self) -> Context {
-        #[cfg(feature = "hyphenation")]
-        {
-            Context {
-                font_cache: self.font_cache.clone(),
-                hyphenator: self.hyphenator.clone(),
-            }
-        }
-
-        #[cfg(not(feature = "hyphenation"))]
-        {
-            Context {
-                font_cache: self.font_cache.clone(),
-            }
+        Context {
+            font_cache: self.font_cache.clone(),
+            #[cfg(feature = "hyphenation")]
+            hyphenator: self.hyphenator.clone(),
+            #[cfg(feature = "hyphenation")]
+            hyphenators: self.hyphenators.clone(),
+            #[cfg(feature = "latex")]
+            latex_macros: self.latex_macros.clone(),
+            warnings: self.warnings.clone(),
+            figures: self.figures.clone(),
+            equations: self.equations.clone(),
+            equation_section: self.equation_section.clone(),
+            page: self.page.clone(),
         }
     }
 }
 impl Context {
-    #[cfg(not(feature = "hyphenation"))]
-    fn new(font_cache: fonts::FontCache) -> Context {
-        Context { font_cache }
-    }
-
-    #[cfg(feature = "hyphenation")]
     fn new(font_cache: fonts::FontCache) -> Context {
         Context {
             font_cache,
+            #[cfg(feature = "hyphenation")]
             hyphenator: None,
+            #[cfg(feature = "hyphenation")]
+            hyphenators: std::collections::HashMap::new(),
+            #[cfg(feature = "latex")]
+            latex_macros: Default::default(),
+            warnings: Default::default(),
+            figures: Default::default(),
+            equations: Default::default(),
+            equation_section: Default::default(),
+            page: cell::Cell::new(1),
+        }
+    }
+
+    /// Reports a non-fatal problem that occurred during the rendering process.
+    ///
+    /// Elements should use this method if they could work around a problem, for example by
+    /// breaking a word that is too long for a line, instead of aborting the rendering process.
+    /// The warnings are collected in the [`RenderResult::warnings`][] field of the page that is
+    /// currently being rendered by [`Document::render`][].
+    ///
+    /// [`RenderResult::warnings`]: struct.RenderResult.html#structfield.warnings
+    /// [`Document::render`]: struct.Document.html#method.render
+    pub fn warn(&self, msg: impl Into<String>) {
+        self.warnings.borrow_mut().push(msg.into());
+    }
+
+    /// Removes and returns all warnings that have been reported since the last call of this
+    /// method.
+    pub fn take_warnings(&self) -> Vec<String> {
+        mem::take(&mut *self.warnings.borrow_mut())
+    }
+
+    /// Returns the number of the page that is currently being rendered, starting at 1.
+    pub fn page(&self) -> usize {
+        self.page.get()
+    }
+
+    /// Assigns the next figure number to a figure with the given caption on the current page and
+    /// returns the number.
+    ///
+    /// This method is called by the [`Figure`][] element.  The registered figures are listed by
+    /// the [`ListOfFigures`][] element.
+    ///
+    /// [`Figure`]: elements/struct.Figure.html
+    /// [`ListOfFigures`]: elements/struct.ListOfFigures.html
+    pub fn register_figure(&self, caption: impl Into<String>) -> usize {
+        let mut figures = self.figures.borrow_mut();
+        let number = figures.len() + 1;
+        figures.push(elements::FigureEntry {
+            number,
+            caption: caption.into(),
+            page: self.page(),
+        });
+        number
+    }
+
+    /// Returns the figures that have been registered so far.
+    ///
+    /// The registry is cleared at the start of [`Document::render`][].
+    ///
+    /// [`Document::render`]: struct.Document.html#method.render
+    pub fn figures(&self) -> Vec<elements::FigureEntry> {
+        self.figures.borrow().clone()
+    }
+
+    /// Sets the section that is used for the numbers of the following equations.
+    ///
+    /// If a section is set, equations are numbered per section, e.g. “(3.1)”, “(3.2)” in section
+    /// 3.  Otherwise they are numbered throughout the document, e.g. “(1)”, “(2)”.  This method is
+    /// called by the [`EquationSection`][] element.
+    ///
+    /// [`EquationSection`]: elements/struct.EquationSection.html
+    pub fn set_equation_section(&self, section: Option<usize>) {
+        self.equation_section.set(section);
+    }
+
+    /// Assigns the next equation number in the current section to an equation with the given
+    /// label on the current page and returns the registered entry.
+    ///
+    /// This method is called by numbered [`Latex`][] formulas.  If the label has already been
+    /// registered, a warning is reported and references resolve to the first equation with that
+    /// label.
+    ///
+    /// [`Latex`]: elements/struct.Latex.html
+    pub fn register_equation(&self, label: Option<String>) -> elements::EquationEntry {
+        let section = self.equation_section.get();
+        if let Some(label) = &label {
+            if self.equation(label).is_some() {
+                self.warn(format!("Duplicate equation label: {}", label));
+            }
         }
+        let mut equations = self.equations.borrow_mut();
+        let number = equations.iter().filter(|e| e.section == section).count() + 1;
+        let entry = elements::EquationEntry {
+            section,
+            number,
+            label,
+            page: self.page(),
+        };
+        equations.push(entry.clone());
+        entry
+    }
+
+    /// Returns the equations that have been registered so far.
+    pub fn equations(&self) -> Vec<elements::EquationEntry> {
+        self.equations.borrow().clone()
+    }
+
+    /// Returns the equation with the given label if it has already been registered.
+    pub fn equation(&self, label: &str) -> Option<elements::EquationEntry> {
+        self.equations
+            .borrow()
+            .iter()
+            .find(|e| e.label.as_deref() == Some(label))
+            .cloned()
+    }
+
+    /// Clears the figures and equations registered by a previous rendering process.
+    fn reset_registries(&self) {
+        self.figures.borrow_mut().clear();
+        self.equations.borrow_mut().clear();
+        self.equation_section.set(None);
+    }
+
+    /// Returns the number of warnings that have been reported since the last call of
+    /// [`take_warnings`][].
+    ///
+    /// [`take_warnings`]: #method.take_warnings
+    fn warning_count(&self) -> usize {
+        self.warnings.borrow().len()
+    }
+
+    /// Discards all warnings except for the first `len` warnings.
+    fn truncate_warnings(&self, len: usize) {
+        self.warnings.borrow_mut().truncate(len);
+    }
+
+    /// Calls the given function and discards all warnings, figures and equations that are
+    /// reported during the call.
+    fn dry_run<T>(&self, f: impl FnOnce() -> T) -> T {
+        let warnings = self.warnings.borrow().len();
+        let figures = self.figures.borrow().len();
+        let equations = self.equations.borrow().len();
+        let equation_section = self.equation_section.get();
+        let result = f();
+        self.warnings.borrow_mut().truncate(warnings);
+        self.figures.borrow_mut().truncate(figures);
+        self.equations.borrow_mut().truncate(equations);
+        self.equation_section.set(equation_section);
+        result
     }
 }
 
@@ -1142,4 +1546,25 @@ mod tests {
         // original has more content -> likely larger PDF
         assert!(out1.len() >= out2.len());
     }
+
+    #[test]
+    fn test_document_renders_orphans_on_short_page() {
+        use crate::elements::Paragraph;
+        use crate::style::Style;
+        use crate::{test_util, Document, Mm, Size};
+
+        let cache = test_util::font_cache();
+        let style = Style::new();
+        // One word per line and room for exactly three lines per page
+        let width = style.str_width(&cache, "aaaa ") * 1.5;
+        let height = style.line_height(&cache) * 3.0 + Mm(0.1);
+
+        let mut doc = Document::new(test_util::font_family());
+        doc.set_paper_size(Size::new(width, height));
+        doc.push(Paragraph::new("aaaa aaaa aaaa aaaa").with_orphans(4));
+        let results = doc.render(std::io::sink()).expect("render");
+        assert_eq!(results.len(), 2);
+        assert!(results[0].size.height > Mm(0.0));
+        assert!(!results[1].has_more);
+    }
 }
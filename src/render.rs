@@ -22,9 +22,11 @@
 //! [`TextSection`]: struct.TextSection.html
 
 use std::cell;
+use std::collections;
 use std::io;
 use std::ops;
 use std::rc;
+use std::sync;
 
 use crate::error::{Error, ErrorKind};
 use crate::fonts;
@@ -215,30 +217,40 @@ fn parse_and_build_tj(text: &str) -> Option<TJResult> {
     })
 }
 
-/// Post-process the PDF to add link annotations to the appropriate pages
+/// Post-process the PDF to add link annotations and image soft masks
 fn postprocess_pdf(
     buf: &[u8],
     page_annotations: Vec<Vec<LinkAnnotation>>,
+    image_patches: Vec<(String, ImagePatch)>,
 ) -> Result<Vec<u8>, Error> {
-    // Add annotations to the PDF (TJ post-processing is no longer needed since
-    // WriteCodepointsWithKerning already generates proper TJ operations)
-    add_annotations_to_pdf(buf, page_annotations)
-}
-
-/// Add link annotations to pages in the PDF
-fn add_annotations_to_pdf(
-    buf: &[u8],
-    page_annotations: Vec<Vec<LinkAnnotation>>,
-) -> Result<Vec<u8>, Error> {
-    use lopdf::{Dictionary, Document, Object};
+    use lopdf::Document;
 
     let mut doc = Document::load_mem(buf).map_err(|e| {
         Error::new(
-            format!("Failed to parse PDF for adding annotations: {:?}", e),
+            format!("Failed to parse PDF for post-processing: {:?}", e),
             ErrorKind::PdfError("parse failure".to_string()),
         )
     })?;
 
+    // Add annotations to the PDF (TJ post-processing is no longer needed since
+    // WriteCodepointsWithKerning already generates proper TJ operations)
+    add_annotations_to_pdf(&mut doc, page_annotations);
+    apply_image_patches(&mut doc, image_patches);
+
+    let mut out: Vec<u8> = Vec::new();
+    doc.save_to(&mut out).map_err(|e| {
+        Error::new(
+            format!("Failed to save post-processed PDF: {:?}", e),
+            ErrorKind::PdfError("save failure".to_string()),
+        )
+    })?;
+    Ok(out)
+}
+
+/// Add link annotations to pages in the PDF
+fn add_annotations_to_pdf(doc: &mut lopdf::Document, page_annotations: Vec<Vec<LinkAnnotation>>) {
+    use lopdf::{Dictionary, Object};
+
     let pages = doc.get_pages();
     let page_ids: Vec<_> = pages.iter().map(|(_, id)| *id).collect();
 
@@ -315,15 +327,231 @@ fn add_annotations_to_pdf(
             }
         }
     }
+}
 
-    let mut out: Vec<u8> = Vec::new();
-    doc.save_to(&mut out).map_err(|e| {
-        Error::new(
-            format!("Failed to save PDF with annotations: {:?}", e),
-            ErrorKind::PdfError("save failure".to_string()),
-        )
-    })?;
-    Ok(out)
+/// Applies the image patches to the image XObjects with the given resource names.
+fn apply_image_patches(doc: &mut lopdf::Document, image_patches: Vec<(String, ImagePatch)>) {
+    use lopdf::{Dictionary, Object, Stream};
+
+    if image_patches.is_empty() {
+        return;
+    }
+    let xobjects = xobject_ids(doc);
+    // ICC profiles that are shared by several images are only embedded once
+    let mut icc_profiles = collections::HashMap::new();
+    for (name, patch) in image_patches {
+        let xobject_id = match xobjects.get(name.as_bytes()) {
+            Some(id) => *id,
+            None => continue,
+        };
+        if let Some(alt_text) = &patch.alt_text {
+            let mut dict = Dictionary::new();
+            dict.set(b"Type", Object::Name(b"Metadata".to_vec()));
+            dict.set(b"Subtype", Object::Name(b"XML".to_vec()));
+            let metadata = Stream::new(dict, xmp_description(alt_text).into_bytes());
+            let metadata_id = doc.add_object(metadata);
+            if let Ok(Object::Stream(xobject)) = doc.get_object_mut(xobject_id) {
+                xobject
+                    .dict
+                    .set(b"Metadata", Object::Reference(metadata_id));
+            }
+        }
+        if !patch.associated_files.is_empty() {
+            let files = patch
+                .associated_files
+                .iter()
+                .map(|file| Object::Reference(add_associated_file(doc, file)))
+                .collect();
+            if let Ok(Object::Stream(xobject)) = doc.get_object_mut(xobject_id) {
+                xobject.dict.set(b"AF", Object::Array(files));
+            }
+        }
+        let image_id = match find_image(doc, xobject_id) {
+            Some(id) => id,
+            None => continue,
+        };
+        if let Some(smask) = patch.smask {
+            let mut dict = Dictionary::new();
+            dict.set(b"Type", Object::Name(b"XObject".to_vec()));
+            dict.set(b"Subtype", Object::Name(b"Image".to_vec()));
+            dict.set(b"Width", Object::Integer(smask.width as i64));
+            dict.set(b"Height", Object::Integer(smask.height as i64));
+            dict.set(b"ColorSpace", Object::Name(b"DeviceGray".to_vec()));
+            dict.set(b"BitsPerComponent", Object::Integer(8));
+            let mut stream = Stream::new(dict, smask.alpha);
+            let _ = stream.compress();
+            let smask_id = doc.add_object(stream);
+            if let Ok(Object::Stream(image)) = doc.get_object_mut(image_id) {
+                image.dict.set(b"SMask", Object::Reference(smask_id));
+            }
+        }
+        if let Some(jpeg) = patch.jpeg {
+            if let Ok(Object::Stream(image)) = doc.get_object_mut(image_id) {
+                image.set_plain_content(jpeg.data.to_vec());
+                image.allows_compression = false;
+                let dict = &mut image.dict;
+                dict.set(b"Width", Object::Integer(jpeg.width as i64));
+                dict.set(b"Height", Object::Integer(jpeg.height as i64));
+                dict.set(b"Filter", Object::Name(b"DCTDecode".to_vec()));
+                dict.set(b"ColorSpace", Object::Name(jpeg.color_space().to_vec()));
+                dict.set(b"BitsPerComponent", Object::Integer(8));
+                dict.remove(b"Decode");
+                if jpeg.inverted {
+                    let decode = [1i64, 0].repeat(usize::from(jpeg.components));
+                    dict.set(
+                        b"Decode",
+                        Object::Array(decode.into_iter().map(Object::Integer).collect()),
+                    );
+                }
+            }
+        }
+        if let Some(cmyk) = patch.cmyk {
+            if let Ok(Object::Stream(image)) = doc.get_object_mut(image_id) {
+                image.set_plain_content(cmyk.data.to_vec());
+                let dict = &mut image.dict;
+                dict.set(b"Width", Object::Integer(cmyk.width as i64));
+                dict.set(b"Height", Object::Integer(cmyk.height as i64));
+                dict.set(b"ColorSpace", Object::Name(b"DeviceCMYK".to_vec()));
+                dict.set(b"BitsPerComponent", Object::Integer(8));
+                let _ = image.compress();
+            }
+        }
+        if let Some(profile) = patch.icc_profile {
+            let profile_id = *icc_profiles.entry(profile.data.clone()).or_insert_with(|| {
+                let mut dict = Dictionary::new();
+                dict.set(b"N", Object::Integer(i64::from(profile.components)));
+                dict.set(b"Alternate", Object::Name(profile.device_space().to_vec()));
+                let mut stream = Stream::new(dict, profile.data.to_vec());
+                let _ = stream.compress();
+                doc.add_object(stream)
+            });
+            if let Ok(Object::Stream(image)) = doc.get_object_mut(image_id) {
+                image.dict.set(
+                    b"ColorSpace",
+                    Object::Array(vec![
+                        Object::Name(b"ICCBased".to_vec()),
+                        Object::Reference(profile_id),
+                    ]),
+                );
+            }
+        }
+    }
+}
+
+/// Embeds the given file and returns the ID of its file specification for an `/AF` array.
+fn add_associated_file(doc: &mut lopdf::Document, file: &AssociatedFile) -> lopdf::ObjectId {
+    use lopdf::{Dictionary, Object, Stream};
+
+    let mut params = Dictionary::new();
+    params.set(b"Size", Object::Integer(file.data.len() as i64));
+    let mut dict = Dictionary::new();
+    dict.set(b"Type", Object::Name(b"EmbeddedFile".to_vec()));
+    dict.set(b"Subtype", Object::Name(file.mime_type.as_bytes().to_vec()));
+    dict.set(b"Params", Object::Dictionary(params));
+    let mut stream = Stream::new(dict, file.data.clone());
+    let _ = stream.compress();
+    let stream_id = doc.add_object(stream);
+
+    let mut embedded_files = Dictionary::new();
+    embedded_files.set(b"F", Object::Reference(stream_id));
+    embedded_files.set(b"UF", Object::Reference(stream_id));
+    let mut filespec = Dictionary::new();
+    filespec.set(b"Type", Object::Name(b"Filespec".to_vec()));
+    filespec.set(b"F", Object::string_literal(file.name.as_str()));
+    filespec.set(b"UF", Object::string_literal(file.name.as_str()));
+    filespec.set(b"Desc", Object::string_literal(file.description.as_str()));
+    filespec.set(b"AFRelationship", Object::Name(b"Supplement".to_vec()));
+    filespec.set(b"EF", Object::Dictionary(embedded_files));
+    doc.add_object(filespec)
+}
+
+/// Maps the names of all XObject resources in the document to their object IDs.
+fn xobject_ids(doc: &lopdf::Document) -> collections::HashMap<Vec<u8>, lopdf::ObjectId> {
+    use lopdf::Object;
+
+    let mut ids = collections::HashMap::new();
+    for obj in doc.objects.values() {
+        let dict = match obj {
+            Object::Dictionary(dict) => dict,
+            Object::Stream(stream) => &stream.dict,
+            _ => continue,
+        };
+        // Resource dictionaries can be stored inline or as separate objects.
+        let resources = dict
+            .get(b"Resources")
+            .ok()
+            .and_then(|obj| resolve_dict(doc, obj));
+        for dict in std::iter::once(dict).chain(resources) {
+            if let Some(xobjects) = dict
+                .get(b"XObject")
+                .ok()
+                .and_then(|obj| resolve_dict(doc, obj))
+            {
+                for (name, obj) in xobjects.iter() {
+                    if let Ok(id) = obj.as_reference() {
+                        ids.insert(name.clone(), id);
+                    }
+                }
+            }
+        }
+    }
+    ids
+}
+
+/// Returns the ID of the image XObject with the given ID, or of the first image drawn by the form
+/// XObject with the given ID.
+fn find_image(doc: &lopdf::Document, id: lopdf::ObjectId) -> Option<lopdf::ObjectId> {
+    let stream = doc.get_object(id).ok()?.as_stream().ok()?;
+    match stream.dict.get(b"Subtype").ok()?.as_name().ok()? {
+        b"Image" => Some(id),
+        b"Form" => {
+            let resources = resolve_dict(doc, stream.dict.get(b"Resources").ok()?)?;
+            let xobjects = resolve_dict(doc, resources.get(b"XObject").ok()?)?;
+            xobjects
+                .iter()
+                .filter_map(|(_, obj)| obj.as_reference().ok())
+                .filter(|inner| *inner != id)
+                .find_map(|inner| find_image(doc, inner))
+        }
+        _ => None,
+    }
+}
+
+/// Returns an XMP metadata packet with the given description (`dc:description`).
+fn xmp_description(description: &str) -> String {
+    let mut escaped = String::with_capacity(description.len());
+    for c in description.chars() {
+        match c {
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '&' => escaped.push_str("&amp;"),
+            c => escaped.push(c),
+        }
+    }
+    format!(
+        concat!(
+            "<?xpacket begin=\"\u{FEFF}\" id=\"W5M0MpCehiHzreSzNTczkc9d\"?>\n",
+            "<x:xmpmeta xmlns:x=\"adobe:ns:meta/\">\n",
+            "<rdf:RDF xmlns:rdf=\"http://www.w3.org/1999/02/22-rdf-syntax-ns#\">\n",
+            "<rdf:Description rdf:about=\"\" xmlns:dc=\"http://purl.org/dc/elements/1.1/\">\n",
+            "<dc:description><rdf:Alt><rdf:li xml:lang=\"x-default\">{}</rdf:li></rdf:Alt>",
+            "</dc:description>\n",
+            "</rdf:Description>\n",
+            "</rdf:RDF>\n",
+            "</x:xmpmeta>\n",
+            "<?xpacket end=\"r\"?>"
+        ),
+        escaped
+    )
+}
+
+fn resolve_dict<'a>(
+    doc: &'a lopdf::Document,
+    obj: &'a lopdf::Object,
+) -> Option<&'a lopdf::Dictionary> {
+    doc.dereference(obj)
+        .ok()
+        .and_then(|(_, obj)| obj.as_dict().ok())
 }
 
 /// Compatibility wrapper for a font reference (either builtin or external) to adapt to
@@ -357,6 +585,10 @@ impl IndirectFontRef {
 use crate::Scale;
 #[cfg(feature = "images")]
 use image::GenericImageView;
+#[cfg(feature = "images")]
+use sha2::Digest as _;
+#[cfg(feature = "images")]
+use std::hash::{Hash, Hasher};
 
 /// Represents a link annotation to be added to a PDF
 #[derive(Debug, Clone)]
@@ -367,6 +599,318 @@ pub(crate) struct LinkAnnotation {
     pub uri: String,
 }
 
+/// The SHA-256 digest of the content of an image or SVG.
+///
+/// The digest is long enough to treat images with equal digests as identical, unlike a 64-bit
+/// hash value where a collision would silently replace one image with another.
+pub(crate) type ContentDigest = [u8; 32];
+
+/// Maps the content digests of images and SVGs to the XObjects that were created for them so that
+/// identical images are only embedded once per document.
+type ImageCache = rc::Rc<cell::RefCell<collections::HashMap<ContentDigest, printpdf::XObjectId>>>;
+
+/// Computes the content digest used as the key of the [`ImageCache`].
+#[cfg(feature = "images")]
+fn content_digest(f: impl FnOnce(&mut DigestHasher)) -> ContentDigest {
+    let mut hasher = DigestHasher(sha2::Sha256::new());
+    f(&mut hasher);
+    hasher.0.finalize().into()
+}
+
+/// Computes the digest of the source of an SVG image for [`Area::add_svg_with_digest`][].
+#[cfg(feature = "images")]
+pub(crate) fn svg_digest(source: &str) -> ContentDigest {
+    content_digest(|h| source.hash(h))
+}
+
+/// Feeds values that implement `Hash` into a SHA-256 digest, see [`content_digest`][].
+#[cfg(feature = "images")]
+struct DigestHasher(sha2::Sha256);
+
+#[cfg(feature = "images")]
+impl Hasher for DigestHasher {
+    fn write(&mut self, bytes: &[u8]) {
+        self.0.update(bytes);
+    }
+
+    fn finish(&self) -> u64 {
+        let digest = self.0.clone().finalize();
+        u64::from_le_bytes(digest[..8].try_into().expect("SHA-256 digest is too short"))
+    }
+}
+
+/// Returns a blank 1×1 grayscale image whose content and size are replaced by an
+/// [`ImagePatch`][] when the document is written, see [`Layer::use_placeholder`][].
+#[cfg(feature = "images")]
+fn placeholder_image() -> printpdf::XObject {
+    printpdf::XObject::Image(printpdf::RawImage {
+        pixels: printpdf::RawImageData::U8(vec![0]),
+        width: 1,
+        height: 1,
+        data_format: printpdf::RawImageFormat::R8,
+        tag: Vec::new(),
+    })
+}
+
+/// Changes to an image XObject that cannot be expressed with `printpdf` and that are applied to
+/// the serialized document instead.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct ImagePatch {
+    /// The soft mask (alpha channel) of the image
+    pub smask: Option<SoftMask>,
+    /// The encoded JPEG data that replaces the placeholder pixels of the image
+    pub jpeg: Option<JpegImage>,
+    /// The CMYK pixels that replace the placeholder pixels of the image
+    pub cmyk: Option<CmykImage>,
+    /// The ICC profile that is used as the color space of the image
+    pub icc_profile: Option<IccProfile>,
+    /// The alternative text that is stored in the metadata of the XObject
+    pub alt_text: Option<String>,
+    /// The files that are associated with the XObject
+    pub associated_files: Vec<AssociatedFile>,
+}
+
+impl ImagePatch {
+    fn is_empty(&self) -> bool {
+        self.smask.is_none()
+            && self.jpeg.is_none()
+            && self.cmyk.is_none()
+            && self.icc_profile.is_none()
+            && self.alt_text.is_none()
+            && self.associated_files.is_empty()
+    }
+}
+
+/// A file that is embedded in the document and associated with an image XObject (`/AF`), for
+/// example the MathML source of a formula.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(not(feature = "images"), allow(dead_code))]
+pub(crate) struct AssociatedFile {
+    /// The file name
+    pub name: String,
+    /// The MIME type of the file
+    pub mime_type: String,
+    /// The description of the file
+    pub description: String,
+    /// The content of the file
+    pub data: Vec<u8>,
+}
+
+/// The alpha channel of an image, stored as one byte per pixel.
+#[derive(Debug, Clone)]
+#[cfg_attr(not(feature = "images"), allow(dead_code))]
+pub(crate) struct SoftMask {
+    pub width: usize,
+    pub height: usize,
+    pub alpha: Vec<u8>,
+}
+
+/// A JPEG image that is embedded with its original encoded data (`/DCTDecode`) instead of being
+/// decoded and re-encoded.
+#[derive(Debug, Clone)]
+#[cfg_attr(not(feature = "images"), allow(dead_code))]
+pub(crate) struct JpegImage {
+    data: sync::Arc<Vec<u8>>,
+    width: usize,
+    height: usize,
+    components: u8,
+    /// Adobe CMYK JPEGs store inverted color values.
+    inverted: bool,
+}
+
+#[cfg_attr(not(feature = "images"), allow(dead_code))]
+impl JpegImage {
+    /// Reads the frame header of the given JPEG data.
+    ///
+    /// Returns the data as an error if it is not a JPEG file that can be embedded directly, for
+    /// example because it uses arithmetic coding or a precision other than 8 bits.
+    pub fn parse(data: Vec<u8>) -> Result<Self, Vec<u8>> {
+        match Self::read_header(&data) {
+            Some((width, height, components, inverted)) => Ok(Self {
+                data: sync::Arc::new(data),
+                width,
+                height,
+                components,
+                inverted,
+            }),
+            None => Err(data),
+        }
+    }
+
+    fn read_header(data: &[u8]) -> Option<(usize, usize, u8, bool)> {
+        let mut adobe = false;
+        for (marker, segment) in jpeg_segments(data) {
+            match marker {
+                0xEE if segment.starts_with(b"Adobe") => adobe = true,
+                // baseline, extended sequential and progressive Huffman-coded frames
+                0xC0..=0xC2 => {
+                    if segment.len() < 6 || segment[0] != 8 {
+                        return None;
+                    }
+                    let height = u16::from_be_bytes([segment[1], segment[2]]) as usize;
+                    let width = u16::from_be_bytes([segment[3], segment[4]]) as usize;
+                    let components = segment[5];
+                    if width == 0 || height == 0 || !matches!(components, 1 | 3 | 4) {
+                        return None;
+                    }
+                    return Some((width, height, components, adobe && components == 4));
+                }
+                // lossless, hierarchical and arithmetic-coded frames, or a scan without a frame
+                0xC3 | 0xC5..=0xC7 | 0xC9..=0xCB | 0xCD..=0xCF | 0xDA => return None,
+                _ => {}
+            }
+        }
+        None
+    }
+
+    /// Returns the ICC profile stored in the APP2 segments of this JPEG file, if any.
+    pub fn icc_profile(&self) -> Option<IccProfile> {
+        let mut chunks: Vec<(u8, &[u8])> = jpeg_segments(&self.data)
+            .filter(|(marker, _)| *marker == 0xE2)
+            .filter_map(|(_, segment)| segment.strip_prefix(b"ICC_PROFILE\0"))
+            // sequence number and number of chunks
+            .filter(|chunk| chunk.len() > 2)
+            .map(|chunk| (chunk[0], &chunk[2..]))
+            .collect();
+        if chunks.is_empty() {
+            return None;
+        }
+        chunks.sort_by_key(|(seq, _)| *seq);
+        let data: Vec<&[u8]> = chunks.into_iter().map(|(_, chunk)| chunk).collect();
+        IccProfile::parse(data.concat())
+    }
+
+    /// Returns the width of the image in pixels.
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// Returns the height of the image in pixels.
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    fn color_space(&self) -> &'static [u8] {
+        device_space(self.components)
+    }
+}
+
+/// Iterates over the marker segments of the given JPEG data up to the first scan.
+fn jpeg_segments(data: &[u8]) -> impl Iterator<Item = (u8, &[u8])> {
+    let mut pos = if data.starts_with(&[0xFF, 0xD8]) {
+        2
+    } else {
+        data.len()
+    };
+    std::iter::from_fn(move || {
+        while pos + 4 <= data.len() {
+            if data[pos] != 0xFF {
+                return None;
+            }
+            let marker = data[pos + 1];
+            if marker == 0xFF {
+                // fill byte
+                pos += 1;
+                continue;
+            }
+            if marker == 0x01 || (0xD0..=0xD7).contains(&marker) {
+                // markers without a segment
+                pos += 2;
+                continue;
+            }
+            let len = u16::from_be_bytes([data[pos + 2], data[pos + 3]]) as usize;
+            let segment = data.get(pos + 4..pos + 2 + len)?;
+            pos += 2 + len;
+            return Some((marker, segment));
+        }
+        None
+    })
+}
+
+/// Returns the name of the device color space with the given number of components.
+fn device_space(components: u8) -> &'static [u8] {
+    match components {
+        1 => b"DeviceGray",
+        4 => b"DeviceCMYK",
+        _ => b"DeviceRGB",
+    }
+}
+
+/// Raw CMYK pixel data with one byte per component that is embedded as a `/DeviceCMYK` image.
+#[derive(Debug, Clone)]
+#[cfg_attr(not(feature = "images"), allow(dead_code))]
+pub(crate) struct CmykImage {
+    data: sync::Arc<Vec<u8>>,
+    width: usize,
+    height: usize,
+}
+
+#[cfg_attr(not(feature = "images"), allow(dead_code))]
+impl CmykImage {
+    /// Creates a new CMYK image.  Returns `None` if the length of the data does not match the
+    /// dimensions.
+    pub fn new(width: usize, height: usize, data: Vec<u8>) -> Option<Self> {
+        let len = width.checked_mul(height).and_then(|n| n.checked_mul(4));
+        if width == 0 || height == 0 || len != Some(data.len()) {
+            return None;
+        }
+        Some(Self {
+            data: sync::Arc::new(data),
+            width,
+            height,
+        })
+    }
+
+    /// Returns the width of the image in pixels.
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// Returns the height of the image in pixels.
+    pub fn height(&self) -> usize {
+        self.height
+    }
+}
+
+/// An ICC color profile that is embedded as an `/ICCBased` color space.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(not(feature = "images"), allow(dead_code))]
+pub(crate) struct IccProfile {
+    data: sync::Arc<Vec<u8>>,
+    components: u8,
+}
+
+#[cfg_attr(not(feature = "images"), allow(dead_code))]
+impl IccProfile {
+    /// Reads the header of the given ICC profile.
+    ///
+    /// Returns `None` if the data is not an ICC profile for a gray, RGB or CMYK color space.
+    pub fn parse(data: Vec<u8>) -> Option<Self> {
+        if data.len() < 128 || &data[36..40] != b"acsp" {
+            return None;
+        }
+        let components = match &data[16..20] {
+            b"GRAY" => 1,
+            b"RGB " => 3,
+            b"CMYK" => 4,
+            _ => return None,
+        };
+        Some(Self {
+            data: sync::Arc::new(data),
+            components,
+        })
+    }
+
+    /// Returns the number of color components of the profile's color space.
+    pub fn components(&self) -> u8 {
+        self.components
+    }
+
+    fn device_space(&self) -> &'static [u8] {
+        device_space(self.components)
+    }
+}
+
 /// A position relative to the top left corner of a layer.
 struct LayerPosition(Position);
 
@@ -415,6 +959,8 @@ pub struct Renderer {
     modification_date: Option<printpdf::OffsetDateTime>,
     // invariant: pages.len() >= 1
     pages: Vec<Page>,
+    // image XObjects shared by all pages
+    images: ImageCache,
 }
 
 impl Renderer {
@@ -478,7 +1024,8 @@ impl Renderer {
         doc.pages.push(page);
 
         let page_ref = doc.pages.len() - 1;
-        let page = Page::new(page_ref, layer_id, size);
+        let images = ImageCache::default();
+        let page = Page::new(page_ref, layer_id, size, images.clone());
 
         Ok(Renderer {
             doc,
@@ -486,6 +1033,7 @@ impl Renderer {
             creation_date: None,
             modification_date: None,
             pages: vec![page],
+            images,
         })
     }
 
@@ -529,7 +1077,8 @@ impl Renderer {
         let page = printpdf::PdfPage::new(size.width.into(), size.height.into(), ops);
         self.doc.pages.push(page);
         let page_idx = self.doc.pages.len() - 1;
-        self.pages.push(Page::new(page_idx, layer_id, size))
+        self.pages
+            .push(Page::new(page_idx, layer_id, size, self.images.clone()))
     }
 
     /// Returns the number of pages in the current document.
@@ -629,6 +1178,7 @@ impl Renderer {
     pub fn write(mut self, w: impl io::Write) -> Result<(), Error> {
         // Collect annotations from all layers before pages are assembled
         let mut page_annotations: Vec<Vec<LinkAnnotation>> = vec![];
+        let mut image_patches: Vec<(String, ImagePatch)> = Vec::new();
 
         // Assemble pages from our internal representation into the PDF document
         for page in &self.pages {
@@ -642,6 +1192,12 @@ impl Renderer {
                 let mut layer = layer_rc.borrow_mut();
                 // Collect annotations from this layer
                 page_annots.extend(layer.annotations.clone());
+                image_patches.extend(
+                    layer
+                        .image_patches
+                        .iter()
+                        .map(|(id, patch)| (id.0.clone(), patch.clone())),
+                );
 
                 // register layer object in document resources if present
                 if let Some(layer_obj) = layer.layer_obj.take() {
@@ -704,7 +1260,7 @@ impl Renderer {
 
         // Post-process the raw PDF bytes to replace our GENPDFI_CPK markers with proper
         // TJ operators, attach a basic ToUnicode CMap for the used glyphs, and add annotations.
-        match postprocess_pdf(&buf_vec, page_annotations) {
+        match postprocess_pdf(&buf_vec, page_annotations, image_patches) {
             Ok(processed) => {
                 // write final bytes to the provided writer
                 let mut writer = io::BufWriter::new(w);
@@ -753,14 +1309,21 @@ pub struct Page {
     page_idx: usize,
     size: Size,
     layers: Layers,
+    images: ImageCache,
 }
 
 impl Page {
-    fn new(page_idx: usize, layer_id: printpdf::LayerInternalId, size: Size) -> Page {
+    fn new(
+        page_idx: usize,
+        layer_id: printpdf::LayerInternalId,
+        size: Size,
+        images: ImageCache,
+    ) -> Page {
         Page {
             page_idx,
             size,
             layers: Layers::new(layer_id),
+            images,
         }
     }
 
@@ -970,10 +1533,14 @@ impl<'p> Layer<'p> {
     /// reserved XObject id.
     ///
     /// Notes:
-    /// - Images with alpha channels are ignored (alpha handling is not supported).
+    /// - The alpha channel of an image is split off and embedded as a soft mask (`/SMask`) when
+    ///   the document is written.  Fully opaque alpha channels are dropped.
     /// - The actual `XObject` is kept in `LayerData::xobjects` until document serialization,
     ///   at which point it is registered into the document resources so the `UseXobject` op
     ///   references a valid resource id.
+    /// - If an image with the same content has already been added to any page of the document,
+    ///   its XObject is reused.
+    /// - The ICC profile is only used if it matches the color space of the embedded image.
     #[cfg(feature = "images")]
     fn add_image(
         &self,
@@ -982,77 +1549,155 @@ impl<'p> Layer<'p> {
         scale: Scale,
         rotation: Rotation,
         dpi: Option<f32>,
+        icc_profile: Option<&IccProfile>,
     ) {
-        // Convert the dynamic image into a printpdf::RawImage and keep it in the layer until
-        // serialization. We reject images with alpha earlier in Image::from_dynamic_image, but
-        // check defensively here as well.
-        if image.color().has_alpha() {
-            return; // silently ignore / don't embed alpha images
-        }
-
         // Obtain pixel data and format
         let (width, height) = image.dimensions();
         let width = width as usize;
         let height = height as usize;
+        let is_gray = matches!(image.color(), image::ColorType::L8 | image::ColorType::La8);
+        let icc_profile = icc_profile.filter(|p| p.components() == if is_gray { 1 } else { 3 });
+
+        let key = content_digest(|h| {
+            "raster".hash(h);
+            (width, height, image.color()).hash(h);
+            image.as_bytes().hash(h);
+            icc_profile.hash(h);
+        });
+        let xobj_id = self.cached_xobject(Some(key), || {
+            // Prefer RGB8 if possible, otherwise grayscale
+            let (pixels, format) = match image.color() {
+                image::ColorType::L8 | image::ColorType::La8 => {
+                    // grayscale
+                    let gray = image.to_luma8();
+                    (
+                        printpdf::RawImageData::U8(gray.into_raw()),
+                        printpdf::RawImageFormat::R8,
+                    )
+                }
+                _ => {
+                    // Use RGB8
+                    let rgb = image.to_rgb8();
+                    (
+                        printpdf::RawImageData::U8(rgb.into_raw()),
+                        printpdf::RawImageFormat::RGB8,
+                    )
+                }
+            };
 
-        // Prefer RGB8 if possible, otherwise grayscale
-        let (pixels, format) = match image.color() {
-            image::ColorType::L8 | image::ColorType::La8 => {
-                // grayscale
-                let gray = image.to_luma8();
-                (
-                    printpdf::RawImageData::U8(gray.into_raw()),
-                    printpdf::RawImageFormat::R8,
-                )
-            }
-            _ => {
-                // Use RGB8
-                let rgb = image.to_rgb8();
-                (
-                    printpdf::RawImageData::U8(rgb.into_raw()),
-                    printpdf::RawImageFormat::RGB8,
-                )
-            }
-        };
-
-        let raw = printpdf::RawImage {
-            pixels,
-            width,
-            height,
-            data_format: format,
-            tag: Vec::new(),
-        };
-
-        // Create an XObject and store it with a new id on the layer for later registration
-        let xobj = printpdf::XObject::Image(raw);
-        let xobj_id = printpdf::XObjectId::new();
-        self.data
-            .borrow_mut()
-            .xobjects
-            .push((xobj_id.clone(), xobj));
-
-        // Compute the transform: translate to user-space (lower-left origin), scale and rotate
-        let pdf_point: printpdf::Point = self.transform_position(position).into();
+            // PDF images have no alpha channel, so it is attached as a separate soft mask image
+            let alpha: Option<Vec<u8>> = if image.color().has_alpha() {
+                Some(image.to_rgba8().pixels().map(|p| p[3]).collect())
+            } else {
+                None
+            };
+            let smask = alpha
+                .filter(|alpha| alpha.iter().any(|a| *a != u8::MAX))
+                .map(|alpha| SoftMask {
+                    width,
+                    height,
+                    alpha,
+                });
 
-        let rotate = printpdf::XObjectRotation {
-            angle_ccw_degrees: -rotation.degrees, // our Rotation is clockwise; XObjectRotation uses CCW
-            rotation_center_x: printpdf::Px(width / 2),
-            rotation_center_y: printpdf::Px(height / 2),
-        };
+            let raw = printpdf::RawImage {
+                pixels,
+                width,
+                height,
+                data_format: format,
+                tag: Vec::new(),
+            };
+            let patch = ImagePatch {
+                smask,
+                icc_profile: icc_profile.cloned(),
+                ..Default::default()
+            };
+            (printpdf::XObject::Image(raw), patch)
+        });
+        self.use_xobject(xobj_id, (width, height), position, scale, rotation, dpi);
+    }
 
-        let transform = printpdf::XObjectTransform {
-            translate_x: Some(pdf_point.x),
-            translate_y: Some(pdf_point.y),
-            rotate: Some(rotate),
-            scale_x: Some(scale.x),
-            scale_y: Some(scale.y),
-            dpi,
-        };
+    /// Adds a JPEG image to this layer without decoding it.
+    ///
+    /// `printpdf` only embeds decoded pixels, so a blank grayscale placeholder is added instead and
+    /// its content and size are replaced with the JPEG data when the document is written.
+    #[cfg(feature = "images")]
+    fn add_jpeg(
+        &self,
+        jpeg: &JpegImage,
+        position: LayerPosition,
+        scale: Scale,
+        rotation: Rotation,
+        dpi: Option<f32>,
+        icc_profile: Option<&IccProfile>,
+    ) {
+        let icc_profile = icc_profile.filter(|p| p.components() == jpeg.components);
+        let key = content_digest(|h| {
+            "jpeg".hash(h);
+            jpeg.data.hash(h);
+            icc_profile.hash(h);
+        });
+        let xobj_id = self.cached_xobject(Some(key), || {
+            let patch = ImagePatch {
+                jpeg: Some(jpeg.clone()),
+                icc_profile: icc_profile.cloned(),
+                ..Default::default()
+            };
+            (placeholder_image(), patch)
+        });
+        let size = (jpeg.width, jpeg.height);
+        self.use_placeholder(xobj_id, size, position, scale, rotation, dpi);
+    }
 
-        self.data.borrow_mut().ops.push(printpdf::Op::UseXobject {
-            id: xobj_id.clone(),
-            transform,
+    /// Adds a CMYK image to this layer.
+    ///
+    /// Like for JPEG images, a placeholder is added and its content is replaced with the CMYK
+    /// pixels when the document is written.
+    #[cfg(feature = "images")]
+    fn add_cmyk(
+        &self,
+        cmyk: &CmykImage,
+        position: LayerPosition,
+        scale: Scale,
+        rotation: Rotation,
+        dpi: Option<f32>,
+        icc_profile: Option<&IccProfile>,
+    ) {
+        let icc_profile = icc_profile.filter(|p| p.components() == 4);
+        let key = content_digest(|h| {
+            "cmyk".hash(h);
+            (cmyk.width, cmyk.height).hash(h);
+            cmyk.data.hash(h);
+            icc_profile.hash(h);
+        });
+        let xobj_id = self.cached_xobject(Some(key), || {
+            let patch = ImagePatch {
+                cmyk: Some(cmyk.clone()),
+                icc_profile: icc_profile.cloned(),
+                ..Default::default()
+            };
+            (placeholder_image(), patch)
         });
+        let size = (cmyk.width, cmyk.height);
+        self.use_placeholder(xobj_id, size, position, scale, rotation, dpi);
+    }
+
+    /// Emits a `UseXobject` operation for a placeholder XObject that is replaced with an image of
+    /// the given size in pixels when the document is written.
+    ///
+    /// `printpdf` scales an XObject by its own size, so the scale is multiplied with the size of
+    /// the image to draw the 1×1 placeholder with the size of the image.
+    #[cfg(feature = "images")]
+    fn use_placeholder(
+        &self,
+        xobj_id: printpdf::XObjectId,
+        (width, height): (usize, usize),
+        position: LayerPosition,
+        scale: Scale,
+        rotation: Rotation,
+        dpi: Option<f32>,
+    ) {
+        let scale = Scale::new(scale.x * width as f32, scale.y * height as f32);
+        self.use_xobject(xobj_id, (width, height), position, scale, rotation, dpi);
     }
 
     /// Adds an SVG to this layer by storing it as an XObject and emitting a UseXobject operation.
@@ -1064,6 +1709,7 @@ impl<'p> Layer<'p> {
     /// # Arguments
     ///
     /// * `svg` - A parsed SVG ExternalXObject from printpdf
+    /// * `source` - The digest of the SVG source, used to reuse the XObject of identical SVGs
     /// * `position` - The position on the layer in layer coordinates
     /// * `scale` - Scaling factors for width and height
     /// * `rotation` - Clockwise rotation in degrees
@@ -1076,29 +1722,92 @@ impl<'p> Layer<'p> {
     fn add_svg(
         &self,
         svg: &printpdf::ExternalXObject,
+        source: Option<ContentDigest>,
         position: LayerPosition,
         scale: Scale,
         rotation: Rotation,
     ) {
+        let key = source.map(|source| {
+            content_digest(|h| {
+                "svg".hash(h);
+                source.hash(h);
+            })
+        });
         // Create an XObject from the SVG and store it with a new id on the layer
-        let xobj = printpdf::XObject::External(svg.clone());
-        let xobj_id = printpdf::XObjectId::new();
-        self.data
-            .borrow_mut()
-            .xobjects
-            .push((xobj_id.clone(), xobj));
-
-        // Compute the transform: translate to user-space (lower-left origin), scale and rotate
-        let pdf_point: printpdf::Point = self.transform_position(position).into();
+        let xobj_id = self.cached_xobject(key, || {
+            (
+                printpdf::XObject::External(svg.clone()),
+                ImagePatch::default(),
+            )
+        });
 
         // For SVG, we use the ExternalXObject's inherent width and height
         let svg_width_px = svg.width.map(|px| px.0).unwrap_or(100);
         let svg_height_px = svg.height.map(|px| px.0).unwrap_or(100);
 
+        // DPI doesn't apply to vector graphics
+        let size = (svg_width_px, svg_height_px);
+        self.use_xobject(xobj_id, size, position, scale, rotation, None);
+    }
+
+    /// Returns the ID of the XObject for the content with the given digest, creating and storing
+    /// it on this layer if it has not been added to the document yet.
+    ///
+    /// If no digest is given, a new XObject is always created.
+    #[cfg(feature = "images")]
+    fn cached_xobject(
+        &self,
+        key: Option<ContentDigest>,
+        create: impl FnOnce() -> (printpdf::XObject, ImagePatch),
+    ) -> printpdf::XObjectId {
+        // Images with different alternative texts or associated files need separate XObjects
+        let alt_text = self.data.borrow().alt_text.clone();
+        let associated_files = self.data.borrow().associated_files.clone();
+        let key = key.map(|key| {
+            content_digest(|h| {
+                key.hash(h);
+                alt_text.hash(h);
+                associated_files.hash(h);
+            })
+        });
+        if let Some(xobj_id) = key.and_then(|key| self.page.images.borrow().get(&key).cloned()) {
+            return xobj_id;
+        }
+
+        // Create an XObject and store it with a new id on the layer for later registration
+        let (xobj, mut patch) = create();
+        patch.alt_text = alt_text;
+        patch.associated_files = associated_files;
+        let xobj_id = printpdf::XObjectId::new();
+        let mut data = self.data.borrow_mut();
+        data.xobjects.push((xobj_id.clone(), xobj));
+        if !patch.is_empty() {
+            data.image_patches.push((xobj_id.clone(), patch));
+        }
+        if let Some(key) = key {
+            self.page.images.borrow_mut().insert(key, xobj_id.clone());
+        }
+        xobj_id
+    }
+
+    /// Emits a `UseXobject` operation for the XObject with the given ID and size in pixels.
+    #[cfg(feature = "images")]
+    fn use_xobject(
+        &self,
+        xobj_id: printpdf::XObjectId,
+        (width, height): (usize, usize),
+        position: LayerPosition,
+        scale: Scale,
+        rotation: Rotation,
+        dpi: Option<f32>,
+    ) {
+        // Compute the transform: translate to user-space (lower-left origin), scale and rotate
+        let pdf_point: printpdf::Point = self.transform_position(position).into();
+
         let rotate = printpdf::XObjectRotation {
             angle_ccw_degrees: -rotation.degrees, // our Rotation is clockwise; XObjectRotation uses CCW
-            rotation_center_x: printpdf::Px(svg_width_px / 2),
-            rotation_center_y: printpdf::Px(svg_height_px / 2),
+            rotation_center_x: printpdf::Px(width / 2),
+            rotation_center_y: printpdf::Px(height / 2),
         };
 
         let transform = printpdf::XObjectTransform {
@@ -1107,11 +1816,11 @@ impl<'p> Layer<'p> {
             rotate: Some(rotate),
             scale_x: Some(scale.x),
             scale_y: Some(scale.y),
-            dpi: None, // DPI doesn't apply to vector graphics
+            dpi,
         };
 
         self.data.borrow_mut().ops.push(printpdf::Op::UseXobject {
-            id: xobj_id.clone(),
+            id: xobj_id,
             transform,
         });
     }
@@ -1137,6 +1846,50 @@ impl<'p> Layer<'p> {
             .push(printpdf::Op::DrawLine { line });
     }
 
+    /// Saves the graphics state and restricts all following drawing operations to the given
+    /// rectangle until [`end_clip`](#method.end_clip) is called with the returned settings.
+    fn start_clip(&self, position: LayerPosition, size: Size) -> (Color, Color, Mm) {
+        let corners = [
+            Position::new(0, 0),
+            Position::new(size.width, 0),
+            Position::new(size.width, size.height),
+            Position::new(0, size.height),
+        ];
+        let points = corners
+            .iter()
+            .map(|corner| printpdf::LinePoint {
+                p: self
+                    .transform_position(LayerPosition(position.0 + *corner))
+                    .into(),
+                bezier: false,
+            })
+            .collect();
+        let polygon = printpdf::Polygon {
+            rings: vec![printpdf::PolygonRing { points }],
+            mode: printpdf::PaintMode::Clip,
+            winding_order: printpdf::WindingOrder::NonZero,
+        };
+
+        let mut data = self.data.borrow_mut();
+        data.ops.push(printpdf::Op::SaveGraphicsState);
+        data.ops.push(printpdf::Op::DrawPolygon { polygon });
+        (
+            data.fill_color.get(),
+            data.outline_color.get(),
+            data.outline_thickness.get(),
+        )
+    }
+
+    /// Restores the graphics state saved by [`start_clip`](#method.start_clip).
+    fn end_clip(&self, (fill_color, outline_color, outline_thickness): (Color, Color, Mm)) {
+        let mut data = self.data.borrow_mut();
+        data.ops.push(printpdf::Op::RestoreGraphicsState);
+        // The cached settings have to match the restored graphics state.
+        data.fill_color.set(fill_color);
+        data.outline_color.set(outline_color);
+        data.outline_thickness.set(outline_thickness);
+    }
+
     fn set_fill_color(&self, color: Option<Color>) {
         if self.data.borrow().update_fill_color(color) {
             self.data.borrow_mut().ops.push(printpdf::Op::SetFillColor {
@@ -1285,6 +2038,12 @@ struct LayerData {
     xobjects: Vec<(printpdf::XObjectId, printpdf::XObject)>,
     /// Link annotations that need to be added to the page
     annotations: Vec<LinkAnnotation>,
+    /// Post-processing steps for the images in `xobjects`
+    image_patches: Vec<(printpdf::XObjectId, ImagePatch)>,
+    /// The alternative text of the images that are currently added, see `Area::described`
+    alt_text: Option<String>,
+    /// The associated files of the images that are currently added, see `Area::described`
+    associated_files: Vec<AssociatedFile>,
     fill_color: cell::Cell<Color>,
     outline_color: cell::Cell<Color>,
     outline_thickness: cell::Cell<Mm>,
@@ -1298,6 +2057,9 @@ impl LayerData {
             ops: Vec::new(),
             xobjects: Vec::new(),
             annotations: Vec::new(),
+            image_patches: Vec::new(),
+            alt_text: None,
+            associated_files: Vec::new(),
             fill_color: Color::Rgb(0, 0, 0).into(),
             outline_color: Color::Rgb(0, 0, 0).into(),
             outline_thickness: Mm::from(printpdf::Pt(1.0)).into(),
@@ -1311,6 +2073,9 @@ impl LayerData {
             ops: Vec::new(),
             xobjects: Vec::new(),
             annotations: Vec::new(),
+            image_patches: Vec::new(),
+            alt_text: None,
+            associated_files: Vec::new(),
             fill_color: Color::Rgb(0, 0, 0).into(),
             outline_color: Color::Rgb(0, 0, 0).into(),
             outline_thickness: Mm::from(printpdf::Pt(1.0)).into(),
@@ -1473,7 +2238,61 @@ impl<'p> Area<'p> {
         dpi: Option<f32>,
     ) {
         self.layer
-            .add_image(image, self.position(position), scale, rotation, dpi);
+            .add_image(image, self.position(position), scale, rotation, dpi, None);
+    }
+
+    /// Inserts an image into the document and uses the given ICC profile as its color space.
+    ///
+    /// See [`add_image`](#method.add_image) for the meaning of the other arguments.
+    #[cfg(feature = "images")]
+    pub(crate) fn add_image_with_profile(
+        &self,
+        image: &image::DynamicImage,
+        position: Position,
+        scale: Scale,
+        rotation: Rotation,
+        dpi: Option<f32>,
+        icc_profile: Option<&IccProfile>,
+    ) {
+        let position = self.position(position);
+        self.layer
+            .add_image(image, position, scale, rotation, dpi, icc_profile);
+    }
+
+    /// Inserts a JPEG image into the document, embedding the encoded data as is.
+    ///
+    /// See [`add_image`](#method.add_image) for the meaning of the arguments.
+    #[cfg(feature = "images")]
+    pub(crate) fn add_jpeg(
+        &self,
+        jpeg: &JpegImage,
+        position: Position,
+        scale: Scale,
+        rotation: Rotation,
+        dpi: Option<f32>,
+        icc_profile: Option<&IccProfile>,
+    ) {
+        let position = self.position(position);
+        self.layer
+            .add_jpeg(jpeg, position, scale, rotation, dpi, icc_profile);
+    }
+
+    /// Inserts a CMYK image into the document as a `/DeviceCMYK` image.
+    ///
+    /// See [`add_image`](#method.add_image) for the meaning of the arguments.
+    #[cfg(feature = "images")]
+    pub(crate) fn add_cmyk(
+        &self,
+        cmyk: &CmykImage,
+        position: Position,
+        scale: Scale,
+        rotation: Rotation,
+        dpi: Option<f32>,
+        icc_profile: Option<&IccProfile>,
+    ) {
+        let position = self.position(position);
+        self.layer
+            .add_cmyk(cmyk, position, scale, rotation, dpi, icc_profile);
     }
 
     /// Inserts an SVG image into the document.
@@ -1495,6 +2314,9 @@ impl<'p> Area<'p> {
     /// * `scale` - Scaling factors for width and height
     /// * `rotation` - Clockwise rotation in degrees
     ///
+    /// Every call embeds a new copy of the SVG.  [`Image`][] elements embed identical SVGs only
+    /// once per document.
+    ///
     /// [`Image`]: ../elements/struct.Image.html
     #[cfg(feature = "images")]
     pub fn add_svg(
@@ -1505,7 +2327,25 @@ impl<'p> Area<'p> {
         rotation: Rotation,
     ) {
         self.layer
-            .add_svg(svg, self.position(position), scale, rotation);
+            .add_svg(svg, None, self.position(position), scale, rotation);
+    }
+
+    /// Inserts an SVG image into the document and reuses the XObject of a previously inserted SVG
+    /// with the same source digest.
+    ///
+    /// See [`add_svg`](#method.add_svg) for the meaning of the other arguments.
+    #[cfg(feature = "images")]
+    pub(crate) fn add_svg_with_digest(
+        &self,
+        svg: &printpdf::ExternalXObject,
+        source: ContentDigest,
+        position: Position,
+        scale: Scale,
+        rotation: Rotation,
+    ) {
+        let position = self.position(position);
+        self.layer
+            .add_svg(svg, Some(source), position, scale, rotation);
     }
 
     /// Adds a clickable link annotation for an image area.
@@ -1553,6 +2393,58 @@ impl<'p> Area<'p> {
         );
     }
 
+    /// Calls the given function and clips everything it draws on this area to the rectangle with
+    /// the given upper left corner and size.
+    ///
+    /// The position is relative to the upper left corner of the area.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use genpdfi_extended::render::Renderer;
+    /// use genpdfi_extended::style::LineStyle;
+    /// use genpdfi_extended::{Position, Size};
+    /// let r = Renderer::new(Size::new(210.0, 297.0), "clip").expect("renderer");
+    /// let area = r.first_page().first_layer().area();
+    /// // only the part of the line within the 10x10 mm square is visible
+    /// area.clipped(Position::new(5, 5), Size::new(10, 10), || {
+    ///     area.draw_line(vec![Position::new(0, 0), Position::new(20, 20)], LineStyle::new());
+    /// });
+    /// ```
+    pub fn clipped<T>(&self, position: Position, size: Size, f: impl FnOnce() -> T) -> T {
+        let state = self.layer.start_clip(self.position(position), size);
+        let result = f();
+        self.layer.end_clip(state);
+        result
+    }
+
+    /// Calls the given function and sets the given alternative text and associated files for all
+    /// images that it adds to this area.
+    ///
+    /// The alternative text is stored as the description (`dc:description`) in the XMP metadata
+    /// of the image XObjects.  The associated files are embedded and referenced in the `/AF`
+    /// array of the image XObjects.
+    #[cfg(feature = "images")]
+    pub(crate) fn described<T>(
+        &self,
+        alt_text: Option<&str>,
+        associated_files: &[AssociatedFile],
+        f: impl FnOnce() -> T,
+    ) -> T {
+        let previous = {
+            let mut data = self.layer.data.borrow_mut();
+            (
+                std::mem::replace(&mut data.alt_text, alt_text.map(ToOwned::to_owned)),
+                std::mem::replace(&mut data.associated_files, associated_files.to_vec()),
+            )
+        };
+        let result = f();
+        let mut data = self.layer.data.borrow_mut();
+        data.alt_text = previous.0;
+        data.associated_files = previous.1;
+        result
+    }
+
     /// Draws a line with the given points and the given line style.
     ///
     /// The points are relative to the upper left corner of the area.
@@ -3142,11 +4034,120 @@ mod tests {
 
     #[cfg(feature = "images")]
     #[test]
-    fn test_add_image_alpha_rejected_and_grayscale_ok() {
+    fn test_identical_images_are_embedded_once() {
+        use image::{DynamicImage, Rgb, RgbImage};
+
+        let logo = DynamicImage::ImageRgb8(RgbImage::from_pixel(4, 4, Rgb([10, 20, 30])));
+        let other = DynamicImage::ImageRgb8(RgbImage::from_pixel(4, 4, Rgb([30, 20, 10])));
+        let source = r#"<svg width="10" height="10" xmlns="http://www.w3.org/2000/svg"><rect width="10" height="10" fill="red"/></svg>"#;
+        let svg = printpdf::Svg::parse(source, &mut Vec::new()).expect("svg");
+
+        let mut r = Renderer::new(Size::new(210.0, 297.0), "dedupe").expect("renderer");
+        r.add_page(Size::new(210.0, 297.0));
+        for page in 0..2 {
+            let area = r.get_page(page).unwrap().first_layer().area();
+            for image in [&logo, &logo, &other] {
+                area.add_image(
+                    image,
+                    Position::new(Mm::from(10.0), Mm::from(10.0)),
+                    Scale::new(1.0, 1.0),
+                    Rotation::from_degrees(0.0),
+                    None,
+                );
+            }
+            area.add_svg_with_digest(
+                &svg,
+                svg_digest(source),
+                Position::new(Mm::from(50.0), Mm::from(10.0)),
+                Scale::new(1.0, 1.0),
+                Rotation::from_degrees(0.0),
+            );
+        }
+        assert_eq!(r.images.borrow().len(), 3);
+
+        let mut buf = Vec::new();
+        r.write(&mut buf).expect("write");
+        let doc = lopdf::Document::load_mem(&buf).expect("parse");
+        let images = doc
+            .objects
+            .values()
+            .filter_map(|obj| obj.as_stream().ok())
+            .filter(|stream| {
+                stream
+                    .dict
+                    .get(b"Subtype")
+                    .and_then(|obj| obj.as_name())
+                    .ok()
+                    == Some(&b"Image"[..])
+            })
+            .count();
+        assert_eq!(images, 2);
+    }
+
+    #[cfg(feature = "images")]
+    #[test]
+    fn test_jpeg_header_parsing() {
+        // SOI, Adobe APP14, SOF0 for a 2x3 CMYK image, EOI
+        let mut data = vec![0xFF, 0xD8];
+        data.extend_from_slice(&[0xFF, 0xEE, 0x00, 0x0E]);
+        data.extend_from_slice(b"Adobe\x00\x64\x00\x00\x00\x00\x02");
+        data.extend_from_slice(&[0xFF, 0xC0, 0x00, 0x14, 0x08, 0x00, 0x03, 0x00, 0x02, 0x04]);
+        data.extend_from_slice(&[0; 12]);
+        data.extend_from_slice(&[0xFF, 0xD9]);
+        let jpeg = JpegImage::parse(data).expect("jpeg");
+        assert_eq!((jpeg.width(), jpeg.height()), (2, 3));
+        assert_eq!(jpeg.color_space(), b"DeviceCMYK");
+        assert!(jpeg.inverted);
+
+        // 12-bit precision cannot be embedded directly
+        let data = vec![
+            0xFF, 0xD8, 0xFF, 0xC1, 0x00, 0x0B, 0x0C, 0x00, 0x01, 0x00, 0x01, 0x01, 0, 0, 0,
+        ];
+        assert!(JpegImage::parse(data).is_err());
+        assert!(JpegImage::parse(b"\x89PNG".to_vec()).is_err());
+    }
+
+    #[test]
+    fn test_jpeg_icc_profile_chunks() {
+        let mut profile = vec![0u8; 200];
+        profile[16..20].copy_from_slice(b"RGB ");
+        profile[36..40].copy_from_slice(b"acsp");
+        profile[150] = 42;
+
+        // SOI, the profile split into two APP2 chunks in reverse order, SOF0 for a 1x1 RGB image
+        let mut data = vec![0xFF, 0xD8];
+        for (seq, chunk) in [(2u8, &profile[100..]), (1, &profile[..100])] {
+            let len = (2 + 12 + 2 + chunk.len()) as u16;
+            data.extend_from_slice(&[0xFF, 0xE2]);
+            data.extend_from_slice(&len.to_be_bytes());
+            data.extend_from_slice(b"ICC_PROFILE\0");
+            data.extend_from_slice(&[seq, 2]);
+            data.extend_from_slice(chunk);
+        }
+        data.extend_from_slice(&[0xFF, 0xC0, 0x00, 0x11, 0x08, 0x00, 0x01, 0x00, 0x01, 0x03]);
+        data.extend_from_slice(&[0; 9]);
+        data.extend_from_slice(&[0xFF, 0xD9]);
+
+        let jpeg = JpegImage::parse(data).expect("jpeg");
+        let icc = jpeg.icc_profile().expect("icc profile");
+        assert_eq!(icc.components(), 3);
+        assert_eq!(*icc.data, profile);
+
+        // Profiles for other color spaces and invalid data are rejected
+        profile[16..20].copy_from_slice(b"Lab ");
+        assert!(IccProfile::parse(profile).is_none());
+        assert!(IccProfile::parse(vec![0; 10]).is_none());
+        assert!(CmykImage::new(2, 2, vec![0; 15]).is_none());
+        assert!(CmykImage::new(2, 2, vec![0; 16]).is_some());
+    }
+
+    #[cfg(feature = "images")]
+    #[test]
+    fn test_add_image_alpha_soft_mask_and_grayscale_ok() {
         use image::{DynamicImage, GrayImage, Luma, Rgba, RgbaImage};
         use printpdf::PdfParseOptions;
 
-        // alpha image should be ignored
+        // alpha image should be embedded with a soft mask
         let aimg = DynamicImage::ImageRgba8(RgbaImage::from_pixel(2, 2, Rgba([1, 2, 3, 4])));
         let mut r = Renderer::new(Size::new(210.0, 297.0), "imgalpha").expect("renderer");
         let area = r.first_page().first_layer().area();
@@ -3159,17 +4160,29 @@ mod tests {
         );
         let mut buf = Vec::new();
         r.write(&mut buf).expect("write");
-        let mut warnings = Vec::new();
-        let parsed = printpdf::PdfDocument::parse(&buf, &PdfParseOptions::default(), &mut warnings)
-            .expect("parse");
-        // no xobjects expected
-        assert!(
-            parsed.resources.xobjects.map.is_empty()
-                || !parsed.pages.iter().any(|p| p
-                    .ops
-                    .iter()
-                    .any(|op| matches!(op, printpdf::Op::UseXobject { .. })))
+        let doc = lopdf::Document::load_mem(&buf).expect("parse");
+        let smask_id = doc
+            .objects
+            .values()
+            .filter_map(|obj| obj.as_stream().ok())
+            .find_map(|stream| stream.dict.get(b"SMask").ok())
+            .and_then(|obj| obj.as_reference().ok())
+            .expect("image with soft mask");
+        let mut smask = doc
+            .get_object(smask_id)
+            .and_then(|obj| obj.as_stream())
+            .expect("soft mask stream")
+            .clone();
+        assert_eq!(
+            smask
+                .dict
+                .get(b"ColorSpace")
+                .and_then(|obj| obj.as_name())
+                .ok(),
+            Some(&b"DeviceGray"[..])
         );
+        let _ = smask.decompress();
+        assert_eq!(smask.content, vec![4; 4]);
 
         // grayscale should be accepted
         let gimg = DynamicImage::ImageLuma8(GrayImage::from_pixel(3, 3, Luma([128])));
@@ -100,6 +100,8 @@ pub enum Effect {
 /// - a line spacing factor, with 1 meaning single line spacing (defaults to 1)
 /// - an outline color, see [`Color`][] (defaults to black)
 /// - a combination of text effects, see [`Effect`][] (defaults to none)
+/// - a language that selects the hyphenator (defaults to the document hyphenator, only available
+///   if the `hyphenation` feature is enabled)
 ///
 /// All properties are optional.  If they are not set, they can be inferred from parent styles or
 /// from the defaults.
@@ -119,6 +121,8 @@ pub struct Style {
     /// Optional font override for special rendering contexts (e.g., code blocks in monospace).
     /// This is used to render specific text with a different font than the document default.
     font_override: Option<fonts::FontFamily<fonts::Font>>,
+    #[cfg(feature = "hyphenation")]
+    language: Option<hyphenation::Language>,
 }
 
 impl Style {
@@ -157,6 +161,10 @@ impl Style {
         if let Some(font_override) = style.font_override {
             self.font_override = Some(font_override);
         }
+        #[cfg(feature = "hyphenation")]
+        if let Some(language) = style.language {
+            self.language = Some(language);
+        }
     }
 
     /// Combines this style and the given style and returns the result.
@@ -327,6 +335,36 @@ impl Style {
         self.font_override
     }
 
+    /// Sets the language for this style.
+    ///
+    /// *Only available if the `hyphenation` feature is enabled.*
+    ///
+    /// The language selects the hyphenator that is used to split words of this style, see
+    /// [`Document::add_hyphenator`][].
+    ///
+    /// [`Document::add_hyphenator`]: ../struct.Document.html#method.add_hyphenator
+    #[cfg(feature = "hyphenation")]
+    pub fn set_language(&mut self, language: hyphenation::Language) {
+        self.language = Some(language);
+    }
+
+    /// Sets the language for this style and returns it.
+    ///
+    /// *Only available if the `hyphenation` feature is enabled.*
+    #[cfg(feature = "hyphenation")]
+    pub fn with_language(mut self, language: hyphenation::Language) -> Style {
+        self.set_language(language);
+        self
+    }
+
+    /// Returns the language for this style, if set.
+    ///
+    /// *Only available if the `hyphenation` feature is enabled.*
+    #[cfg(feature = "hyphenation")]
+    pub fn language(&self) -> Option<hyphenation::Language> {
+        self.language
+    }
+
     /// Calculates the width of the given character with this style using the data in the given
     /// font cache.
     ///
@@ -0,0 +1,53 @@
+// Copyright (c) 2026 Ronan Le Meillat - SCTG Development
+//
+// SPDX-License-Identifier: MIT OR Apache-2.0
+// Licensed under the MIT License or the Apache License, Version 2.0
+
+//! Fixtures shared by the unit tests.
+
+use crate::fonts::{FontCache, FontData, FontFamily};
+use crate::render::Renderer;
+use crate::{Context, Size};
+
+/// Returns a font family that uses the bundled Noto Sans font for all font styles.
+pub fn font_family() -> FontFamily<FontData> {
+    let data = include_bytes!(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/fonts/NotoSans-Regular.ttf"
+    ))
+    .to_vec();
+    let fd = FontData::new(data, None).expect("font data");
+    FontFamily {
+        regular: fd.clone(),
+        bold: fd.clone(),
+        italic: fd.clone(),
+        bold_italic: fd,
+    }
+}
+
+/// Returns a font cache with the fonts of [`font_family`][].
+///
+/// [`font_family`]: fn.font_family.html
+pub fn font_cache() -> FontCache {
+    FontCache::new(font_family())
+}
+
+/// Returns a context with the fonts of [`font_cache`][].
+///
+/// The fonts are not loaded into a renderer, so the context can be used to measure text and to
+/// render elements that don't print text.  Use [`renderer`][] to print text.
+///
+/// [`font_cache`]: fn.font_cache.html
+/// [`renderer`]: fn.renderer.html
+pub fn context() -> Context {
+    Context::new(font_cache())
+}
+
+/// Returns a renderer with a single page of the given size and a context whose fonts are loaded
+/// into the renderer.
+pub fn renderer(size: impl Into<Size>) -> (Renderer, Context) {
+    let mut cache = font_cache();
+    let mut r = Renderer::new(size, "t").expect("renderer");
+    cache.load_pdf_fonts(&mut r).expect("load fonts");
+    (r, Context::new(cache))
+}
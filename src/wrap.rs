@@ -6,162 +6,442 @@
 
 //! Utilities for text wrapping.
 
+use std::borrow::Cow;
 use std::mem;
 
+use crate::elements::WordOverflow;
 use crate::style;
 use crate::Context;
 use crate::Mm;
 
+/// The string appended to words truncated with [`WordOverflow::Clip`][].
+///
+/// [`WordOverflow::Clip`]: ../elements/enum.WordOverflow.html#variant.Clip
+const ELLIPSIS: &str = "…";
+
+/// The maximum number of characters of a word that is quoted in a warning.  Longer words, for
+/// example URLs or encoded data, are truncated with an ellipsis.
+const WARNING_WORD_CHARS: usize = 32;
+
+/// The soft hyphen that marks a preferred break point within a word.  It is only rendered (as a
+/// regular hyphen) if the word is actually split at this point.
+const SOFT_HYPHEN: char = '\u{AD}';
+
+/// A segment of the text of a paragraph: either a styled string or an inline element.
+///
+/// Inline elements are kept as a separate segment kind so that they can be wrapped together with
+/// the text without being confused with the characters of the text.
+#[derive(Clone, Debug)]
+pub enum Segment<S> {
+    /// A styled string.
+    Text(S),
+    /// The inline element with the given index.
+    Inline(usize),
+}
+
+impl<S> Segment<S> {
+    /// Returns the styled string if this segment is a string.
+    pub fn text(&self) -> Option<&S> {
+        match self {
+            Segment::Text(s) => Some(s),
+            Segment::Inline(_) => None,
+        }
+    }
+}
+
+impl Segment<style::StyledString> {
+    /// Returns the length of this segment as counted by the [`Wrapper`][]:  the number of bytes of
+    /// a string, or one for an inline element.
+    ///
+    /// [`Wrapper`]: struct.Wrapper.html
+    pub fn len(&self) -> usize {
+        match self {
+            Segment::Text(s) => s.s.len(),
+            Segment::Inline(_) => 1,
+        }
+    }
+}
+
 /// Combines a sequence of styled words into lines with a maximum width.
 ///
 /// If a word does not fit into a line, the wrapper tries to split it using the `split` function.
-pub struct Wrapper<'c, 's, I: Iterator<Item = style::StyledStr<'s>>> {
+/// If the word is longer than a complete line, it is handled according to the configured
+/// [`WordOverflow`][] strategy.
+///
+/// Inline elements of a paragraph are represented by [`Segment::Inline`][] and are never split.
+///
+/// [`WordOverflow`]: ../elements/enum.WordOverflow.html
+/// [`Segment::Inline`]: enum.Segment.html#variant.Inline
+pub struct Wrapper<'c, 's, I: Iterator<Item = Segment<style::StyledStr<'s>>>> {
     iter: I,
     context: &'c Context,
     width: Mm,
+    overflow: WordOverflow,
+    inline_widths: &'s [Mm],
     x: Mm,
-    buf: Vec<style::StyledCow<'s>>,
+    buf: Vec<Segment<style::StyledCow<'s>>>,
+    // The length of the input segments that are represented by the segments in buf.
+    buf_len: usize,
+    // The remainder of a word that has been split or a segment that has been moved to the next
+    // line and still has to be placed.
+    pending: Option<Segment<style::StyledStr<'s>>>,
+    // Whether the pending remainder stems from a word that has already been reported as too long.
+    pending_overflowed: bool,
     has_overflowed: bool,
 }
 
-impl<'c, 's, I: Iterator<Item = style::StyledStr<'s>>> Wrapper<'c, 's, I> {
+impl<'c, 's, I: Iterator<Item = Segment<style::StyledStr<'s>>>> Wrapper<'c, 's, I> {
     /// Creates a new wrapper for the given word sequence and with the given maximum width.
-    pub fn new(iter: I, context: &'c Context, width: Mm) -> Wrapper<'c, 's, I> {
+    ///
+    /// Words that are longer than `width` are handled according to the `overflow` strategy.
+    pub fn new(
+        iter: I,
+        context: &'c Context,
+        width: Mm,
+        overflow: WordOverflow,
+    ) -> Wrapper<'c, 's, I> {
         Wrapper {
             iter,
             context,
             width,
+            overflow,
+            inline_widths: &[],
             x: Mm(0.0),
             buf: Vec::new(),
+            buf_len: 0,
+            pending: None,
+            pending_overflowed: false,
             has_overflowed: false,
         }
     }
 
+    /// Sets the widths of the inline elements referenced by the word sequence and returns the
+    /// wrapper.
+    pub(crate) fn with_inline_widths(mut self, widths: &'s [Mm]) -> Self {
+        self.inline_widths = widths;
+        self
+    }
+
+    /// Returns the width of the inline element with the given index.
+    fn inline_width(&self, idx: usize) -> Mm {
+        self.inline_widths.get(idx).copied().unwrap_or_default()
+    }
+
     /// Returns true if this wrapper has overflowed, i. e. if it encountered a word that it could
     /// not split so that it would fit into a line.
+    ///
+    /// This only happens with the [`WordOverflow::Error`][] strategy, all other strategies make
+    /// the word fit and report a warning to the [`Context`][] instead.
+    ///
+    /// [`WordOverflow::Error`]: ../elements/enum.WordOverflow.html#variant.Error
+    /// [`Context`]: ../struct.Context.html
     pub fn has_overflowed(&self) -> bool {
         self.has_overflowed
     }
-}
 
-impl<'c, 's, I: Iterator<Item = style::StyledStr<'s>>> Iterator for Wrapper<'c, 's, I> {
-    // This iterator yields pairs of lines and the length difference between the input words and
-    // the line.
-    type Item = (Vec<style::StyledCow<'s>>, usize);
+    fn push(&mut self, s: style::StyledCow<'s>, len: usize) {
+        let s = remove_soft_hyphens(s);
+        self.x += s.width(&self.context.font_cache);
+        self.buf.push(Segment::Text(s));
+        self.buf_len += len;
+    }
 
-    fn next(&mut self) -> Option<(Vec<style::StyledCow<'s>>, usize)> {
-        // Append words to self.buf until the maximum line length is reached
-        while let Some(s) = self.iter.next() {
-            let mut width = s.width(&self.context.font_cache);
-
-            if self.x + width > self.width {
-                // The word does not fit into the current line (at least not completely)
-
-                let mut delta = 0;
-                // Try to split the word so that the first part fits into the current line
-                let s = if let Some((start, end)) = split(self.context, s, self.width - self.x) {
-                    // Calculate the number of bytes that we added to the string when splitting it
-                    // (for the hyphen, if required).
-                    delta = start.s.len() + end.s.len() - s.s.len();
-                    self.buf.push(start);
-                    width = end.width(&self.context.font_cache);
-                    end
+    fn push_inline(&mut self, idx: usize) {
+        self.x += self.inline_width(idx);
+        self.buf.push(Segment::Inline(idx));
+        self.buf_len += 1;
+    }
+
+    fn take_line(&mut self) -> (Vec<Segment<style::StyledCow<'s>>>, usize) {
+        self.x = Mm(0.0);
+        (mem::take(&mut self.buf), mem::take(&mut self.buf_len))
+    }
+
+    /// Places a word that is longer than a complete line on the (empty) current line according to
+    /// the overflow strategy.  Returns `false` if the word could not be placed.
+    fn place_overflowing(&mut self, s: style::StyledStr<'s>) -> bool {
+        if self.overflow != WordOverflow::Error && !self.pending_overflowed {
+            self.context.warn(format!(
+                "The word '{}' is wider than the available width of {:.1} mm ({:?})",
+                truncate_word(s.s),
+                self.width.0,
+                self.overflow
+            ));
+        }
+
+        match self.overflow {
+            WordOverflow::Error => {
+                self.has_overflowed = true;
+                false
+            }
+            WordOverflow::Break => {
+                self.force_break(s);
+                true
+            }
+            WordOverflow::Shrink => {
+                if let Some(shrunk) = shrink(self.context, s, self.width) {
+                    self.push(shrunk, s.s.len());
                 } else {
-                    s.into()
-                };
-
-                if width > self.width {
-                    // The remainder of the word is longer than the current page – we will never be
-                    // able to render it completely.
-                    // TODO: handle gracefully, emit warning
-                    self.has_overflowed = true;
-                    return None;
+                    self.force_break(s);
                 }
+                true
+            }
+            WordOverflow::Clip => {
+                let idx = fitting_prefix(self.context, s, self.width, ELLIPSIS);
+                let clipped = style::StyledCow::new(
+                    format!("{}{}", &s.s[..idx], ELLIPSIS),
+                    s.style,
+                    s.link.map(ToOwned::to_owned),
+                );
+                self.push(clipped, s.s.len());
+                true
+            }
+        }
+    }
 
-                // Return the current line and add the word that did not fit to the next line
-                let v = std::mem::take(&mut self.buf);
-                self.buf.push(s);
-                self.x = width;
-                return Some((v, delta));
+    /// Breaks the given word at the last character boundary that fits into the line and keeps the
+    /// remainder for the next line.
+    fn force_break(&mut self, s: style::StyledStr<'s>) {
+        let mut idx = fitting_prefix(self.context, s, self.width, "");
+        if idx == 0 {
+            // Always consume at least one character so that we make progress.
+            idx = s.s.chars().next().map(char::len_utf8).unwrap_or_default();
+        }
+        let (start, end) = s.s.split_at(idx);
+        self.push(style::StyledStr::new(start, s.style, s.link).into(), idx);
+        if !end.is_empty() {
+            self.pending = Some(Segment::Text(style::StyledStr::new(end, s.style, s.link)));
+            self.pending_overflowed = true;
+        }
+    }
+}
+
+impl<'c, 's, I: Iterator<Item = Segment<style::StyledStr<'s>>>> Iterator for Wrapper<'c, 's, I> {
+    // This iterator yields pairs of lines and the length of the input segments that are
+    // represented by the line, i. e. the number of bytes of the strings plus one for each inline
+    // element.  These numbers can differ from the length of the line, for example if a hyphen has
+    // been inserted or if a word has been clipped.
+    type Item = (Vec<Segment<style::StyledCow<'s>>>, usize);
+
+    fn next(&mut self) -> Option<(Vec<Segment<style::StyledCow<'s>>>, usize)> {
+        // Append words to self.buf until the maximum line length is reached
+        loop {
+            let s = if let Some(s) = self.pending.take() {
+                s
+            } else if let Some(s) = self.iter.next() {
+                self.pending_overflowed = false;
+                s
             } else {
+                break;
+            };
+            let s = match s {
+                Segment::Text(s) => s,
+                Segment::Inline(idx) => {
+                    // Inline elements cannot be split, so we move them to the next line or place
+                    // them on their own line if they are wider than a complete line.
+                    if self.x + self.inline_width(idx) > self.width {
+                        if !self.buf.is_empty() {
+                            self.pending = Some(Segment::Inline(idx));
+                            return Some(self.take_line());
+                        }
+                        if self.overflow == WordOverflow::Error {
+                            self.has_overflowed = true;
+                            return None;
+                        }
+                        self.context.warn(format!(
+                            "An inline element is wider than the available width of {:.1} mm",
+                            self.width.0
+                        ));
+                    }
+                    self.push_inline(idx);
+                    continue;
+                }
+            };
+
+            if self.x + visible_width(self.context, s.style, s.s) <= self.width {
                 // The word fits in the current line, so just append it
-                self.buf.push(s.into());
-                self.x += width;
+                self.push(s.into(), s.s.len());
+                continue;
+            }
+
+            // The word does not fit into the current line (at least not completely).  Try to split
+            // the word so that the first part fits into the current line.
+            if let Some((start, end)) = split(self.context, s, self.width - self.x) {
+                self.push(start, s.s.len() - end.s.len());
+                self.pending = Some(Segment::Text(end));
+                return Some(self.take_line());
+            }
+
+            if !self.buf.is_empty() {
+                // Return the current line and add the word that did not fit to the next line
+                self.pending = Some(Segment::Text(s));
+                return Some(self.take_line());
+            }
+
+            // The word is longer than a complete line – we will never be able to render it
+            // completely without applying the overflow strategy.
+            if !self.place_overflowing(s) {
+                return None;
+            }
+            if self.pending.is_some() {
+                return Some(self.take_line());
             }
         }
 
         if self.buf.is_empty() {
             None
         } else {
-            Some((mem::take(&mut self.buf), 0))
+            Some(self.take_line())
         }
     }
 }
 
-#[cfg(not(feature = "hyphenation"))]
-fn split<'s>(
-    _context: &Context,
-    _s: style::StyledStr<'s>,
-    _len: Mm,
-) -> Option<(style::StyledCow<'s>, style::StyledCow<'s>)> {
+/// Returns the byte index of the end of the longest prefix of the given string that fits into the
+/// given width together with the given suffix.
+fn fitting_prefix(context: &Context, s: style::StyledStr<'_>, width: Mm, suffix: &str) -> usize {
+    let suffix_width = s.style.str_width(&context.font_cache, suffix);
+    let mut fitting = 0;
+    for (idx, c) in s.s.char_indices() {
+        let end = idx + c.len_utf8();
+        if visible_width(context, s.style, &s.s[..end]) + suffix_width > width {
+            break;
+        }
+        fitting = end;
+    }
+    fitting
+}
+
+/// Truncates the given word to [`WARNING_WORD_CHARS`][] characters for a warning.
+///
+/// [`WARNING_WORD_CHARS`]: constant.WARNING_WORD_CHARS.html
+fn truncate_word(word: &str) -> Cow<'_, str> {
+    match word.char_indices().nth(WARNING_WORD_CHARS) {
+        Some((idx, _)) => Cow::Owned(format!("{}{}", &word[..idx], ELLIPSIS)),
+        None => Cow::Borrowed(word),
+    }
+}
+
+/// Tries to reduce the font size of the given string so that it fits into the given width.
+fn shrink<'s>(
+    context: &Context,
+    s: style::StyledStr<'s>,
+    width: Mm,
+) -> Option<style::StyledCow<'s>> {
+    let font_size = s.style.font_size();
+    let ratio = width.0 / visible_width(context, s.style, s.s).0;
+    let mut size = (f32::from(font_size) * ratio)
+        .floor()
+        .min(f32::from(font_size)) as u8;
+    while size > 0 {
+        let style = s.style.with_font_size(size);
+        if visible_width(context, style, s.s) <= width {
+            let link = s.link.map(ToOwned::to_owned);
+            return Some(style::StyledCow::new(s.s, style, link));
+        }
+        size -= 1;
+    }
     None
 }
 
+/// Calculates the width of the given string without its soft hyphens.
+fn visible_width(context: &Context, style: style::Style, s: &str) -> Mm {
+    if s.contains(SOFT_HYPHEN) {
+        style.str_width(&context.font_cache, &s.replace(SOFT_HYPHEN, ""))
+    } else {
+        style.str_width(&context.font_cache, s)
+    }
+}
+
+/// Removes the soft hyphens from the given string as they are only rendered at line breaks.
+fn remove_soft_hyphens(s: style::StyledCow<'_>) -> style::StyledCow<'_> {
+    if s.s.contains(SOFT_HYPHEN) {
+        let text = s.s.replace(SOFT_HYPHEN, "");
+        style::StyledCow::new(text, s.style, s.link)
+    } else {
+        s
+    }
+}
+
 /// Tries to split the given string into two parts so that the first part is shorter than the given
 /// width.
-#[cfg(feature = "hyphenation")]
+///
+/// Soft hyphens in the string are preferred as break points.  If the string does not contain soft
+/// hyphens, the hyphenator for the language of the string is used (if the `hyphenation` feature is
+/// enabled).
 fn split<'s>(
     context: &Context,
     s: style::StyledStr<'s>,
     width: Mm,
-) -> Option<(style::StyledCow<'s>, style::StyledCow<'s>)> {
-    use hyphenation::{Hyphenator, Iter};
-
-    let hyphenator = if let Some(hyphenator) = &context.hyphenator {
-        hyphenator
+) -> Option<(style::StyledCow<'s>, style::StyledStr<'s>)> {
+    let breaks: Vec<_> =
+        s.s.match_indices(SOFT_HYPHEN)
+            .map(|(idx, _)| idx + SOFT_HYPHEN.len_utf8())
+            .collect();
+    if breaks.is_empty() {
+        split_at(context, s, width, &hyphenation_breaks(context, s))
     } else {
-        return None;
-    };
+        split_at(context, s, width, &breaks)
+    }
+}
 
+/// Splits the given string at the last of the given break points so that the first part (and the
+/// hyphen) are shorter than or equal to the given width.
+fn split_at<'s>(
+    context: &Context,
+    s: style::StyledStr<'s>,
+    width: Mm,
+    breaks: &[usize],
+) -> Option<(style::StyledCow<'s>, style::StyledStr<'s>)> {
     let mark = "-";
     let mark_width = s.style.str_width(&context.font_cache, mark);
 
-    let hyphenated = hyphenator.hyphenate(s.s);
-    let segments: Vec<_> = hyphenated.iter().segments().collect();
-
-    // Find the hyphenation with the longest first part so that the first part (and the hyphen) are
-    // shorter than or equals to the required width.
-    let idx = segments
+    let idx = breaks
         .iter()
-        .scan(Mm(0.0), |acc, t| {
-            *acc += s.style.str_width(&context.font_cache, t);
-            Some(*acc)
-        })
-        .position(|w| w + mark_width > width)
-        .unwrap_or_default();
-    if idx > 0 {
-        let idx = hyphenated.breaks[idx - 1];
-        let start = s.s[..idx].to_owned() + mark;
-        let end = &s.s[idx..];
-        Some((
-            style::StyledCow::new(start, s.style, None),
-            style::StyledCow::new(end, s.style, None),
-        ))
+        .copied()
+        .filter(|&idx| idx > 0 && idx < s.s.len())
+        .take_while(|&idx| visible_width(context, s.style, &s.s[..idx]) + mark_width <= width)
+        .last()?;
+    let start = s.s[..idx].replace(SOFT_HYPHEN, "") + mark;
+    let end = &s.s[idx..];
+    Some((
+        style::StyledCow::new(start, s.style, s.link.map(ToOwned::to_owned)),
+        style::StyledStr::new(end, s.style, s.link),
+    ))
+}
+
+#[cfg(not(feature = "hyphenation"))]
+fn hyphenation_breaks(_context: &Context, _s: style::StyledStr<'_>) -> Vec<usize> {
+    Vec::new()
+}
+
+/// Returns the hyphenation points of the given string using the hyphenator for the language of the
+/// string, or the default hyphenator if there is no language-specific hyphenator.
+#[cfg(feature = "hyphenation")]
+fn hyphenation_breaks(context: &Context, s: style::StyledStr<'_>) -> Vec<usize> {
+    use hyphenation::Hyphenator;
+
+    let hyphenator = s
+        .style
+        .language()
+        .and_then(|language| context.hyphenators.get(&language))
+        .or(context.hyphenator.as_ref());
+    if let Some(hyphenator) = hyphenator {
+        hyphenator.hyphenate(s.s).breaks
     } else {
-        None
+        Vec::new()
     }
 }
 
-/// Splits a sequence of styled strings into words.
-pub struct Words<I: Iterator<Item = style::StyledString>> {
+/// Splits a sequence of styled strings into words.  Inline elements are passed through unchanged.
+pub struct Words<I: Iterator<Item = Segment<style::StyledString>>> {
     iter: I,
     s: Option<style::StyledString>,
     link: Option<String>,
 }
 
-impl<I: Iterator<Item = style::StyledString>> Words<I> {
+impl<I: Iterator<Item = Segment<style::StyledString>>> Words<I> {
     /// Creates a new words iterator.
-    pub fn new<IntoIter: IntoIterator<Item = style::StyledString, IntoIter = I>>(
+    pub fn new<IntoIter: IntoIterator<Item = Segment<style::StyledString>, IntoIter = I>>(
         iter: IntoIter,
     ) -> Words<I> {
         Words {
@@ -172,14 +452,21 @@ impl<I: Iterator<Item = style::StyledString>> Words<I> {
     }
 }
 
-impl<I: Iterator<Item = style::StyledString>> Iterator for Words<I> {
-    type Item = style::StyledString;
+impl<I: Iterator<Item = Segment<style::StyledString>>> Iterator for Words<I> {
+    type Item = Segment<style::StyledString>;
 
-    fn next(&mut self) -> Option<style::StyledString> {
+    fn next(&mut self) -> Option<Segment<style::StyledString>> {
         if self.s.as_ref().map(|s| s.s.is_empty()).unwrap_or(true) {
-            self.s = self.iter.next();
-            if let Some(s) = &self.s {
-                self.link = s.link.clone();
+            match self.iter.next() {
+                Some(Segment::Text(s)) => {
+                    self.link = s.link.clone();
+                    self.s = Some(s);
+                }
+                Some(Segment::Inline(idx)) => {
+                    self.s = None;
+                    return Some(Segment::Inline(idx));
+                }
+                None => self.s = None,
             }
         }
 
@@ -188,7 +475,11 @@ impl<I: Iterator<Item = style::StyledString>> Iterator for Words<I> {
             let n = s.s.find(' ').map(|i| i + 1).unwrap_or_else(|| s.s.len());
             let mut tmp = s.s.split_off(n);
             mem::swap(&mut tmp, &mut s.s);
-            Some(style::StyledString::new(tmp, s.style, self.link.clone()))
+            Some(Segment::Text(style::StyledString::new(
+                tmp,
+                s.style,
+                self.link.clone(),
+            )))
         } else {
             None
         }
@@ -200,6 +491,7 @@ mod tests {
     use super::*;
     use crate::fonts::{self, FontCache, FontData, FontFamily};
     use crate::style::{Style, StyledString};
+    use crate::test_util;
     use crate::Context;
     use std::path::PathBuf;
 
@@ -226,14 +518,29 @@ mod tests {
             Style::new(),
             None,
         )];
-        let mut words = Words::new(input.into_iter());
+        let mut words = Words::new(input.into_iter().map(Segment::Text));
         let w1 = words.next().unwrap();
-        assert_eq!(w1.s, "Hello ");
+        assert_eq!(w1.text().unwrap().s, "Hello ");
         let w2 = words.next().unwrap();
-        assert_eq!(w2.s, "world!");
+        assert_eq!(w2.text().unwrap().s, "world!");
         assert!(words.next().is_none());
     }
 
+    #[test]
+    fn test_words_iterator_passes_inline_elements_through() {
+        let input = vec![
+            Segment::Text(StyledString::new("Icon: ", Style::new(), None)),
+            Segment::Inline(0),
+            Segment::Text(StyledString::new(" done", Style::new(), None)),
+        ];
+        let words: Vec<_> = Words::new(input).collect();
+        assert_eq!(words.len(), 4);
+        assert_eq!(words[0].text().unwrap().s, "Icon: ");
+        assert!(matches!(words[1], Segment::Inline(0)));
+        assert_eq!(words[2].text().unwrap().s, " ");
+        assert_eq!(words[3].text().unwrap().s, "done");
+    }
+
     #[test]
     fn test_wrapper_overflow_sets_flag() {
         // Build a context with a real font to get realistic widths
@@ -255,8 +562,200 @@ mod tests {
 
         let binding = "a".repeat(200);
         let long_word = style::StyledStr::new(&binding, Style::new(), None);
-        let mut wrapper = Wrapper::new(std::iter::once(long_word), &context, Mm(0.1));
+        let mut wrapper = Wrapper::new(
+            std::iter::once(Segment::Text(long_word)),
+            &context,
+            Mm(0.1),
+            WordOverflow::Error,
+        );
+        assert!(wrapper.next().is_none());
+        assert!(wrapper.has_overflowed());
+        assert!(context.take_warnings().is_empty());
+    }
+
+    fn text<'a, 's>(segment: &'a Segment<style::StyledCow<'s>>) -> &'a style::StyledCow<'s> {
+        segment.text().expect("text segment")
+    }
+
+    #[test]
+    fn test_wrapper_overflow_break_splits_word() {
+        let context = test_util::context();
+        let binding = "abcdefghij".repeat(10);
+        let long_word = style::StyledStr::new(&binding, Style::new(), None);
+        let width = Style::new().str_width(&context.font_cache, "abcdefghij");
+        let wrapper = Wrapper::new(
+            std::iter::once(Segment::Text(long_word)),
+            &context,
+            width,
+            WordOverflow::Break,
+        );
+
+        let lines: Vec<_> = wrapper.collect();
+        assert!(lines.len() >= 10);
+        let mut joined = String::new();
+        let mut consumed = 0;
+        for (line, len) in &lines {
+            assert_eq!(line.len(), 1);
+            assert!(text(&line[0]).width(&context.font_cache) <= width);
+            joined.push_str(&text(&line[0]).s);
+            consumed += len;
+        }
+        assert_eq!(joined, binding);
+        assert_eq!(consumed, binding.len());
+        // The word is only reported once even though it spans several lines, and it is truncated.
+        let warnings = context.take_warnings();
+        assert_eq!(warnings.len(), 1);
+        let prefix = &binding[..WARNING_WORD_CHARS];
+        assert!(warnings[0].contains(&format!("'{}{}'", prefix, ELLIPSIS)));
+    }
+
+    #[test]
+    fn test_wrapper_overflow_clip_appends_ellipsis() {
+        let context = test_util::context();
+        let binding = "a".repeat(100);
+        let words = vec![
+            style::StyledStr::new(&binding, Style::new(), None),
+            style::StyledStr::new("next", Style::new(), None),
+        ];
+        let width = Style::new().str_width(&context.font_cache, "aaaaaaaaaa");
+        let mut wrapper = Wrapper::new(
+            words.into_iter().map(Segment::Text),
+            &context,
+            width,
+            WordOverflow::Clip,
+        );
+
+        let (line, len) = wrapper.next().expect("clipped line");
+        assert_eq!(line.len(), 1);
+        assert!(text(&line[0]).s.ends_with(ELLIPSIS));
+        assert!(text(&line[0]).width(&context.font_cache) <= width);
+        assert_eq!(len, binding.len());
+
+        let (line, len) = wrapper.next().expect("second line");
+        assert_eq!(text(&line[0]).s, "next");
+        assert_eq!(len, 4);
+        assert!(wrapper.next().is_none());
+        assert!(!wrapper.has_overflowed());
+        assert_eq!(context.take_warnings().len(), 1);
+    }
+
+    #[test]
+    fn test_wrapper_overflow_shrink_reduces_font_size() {
+        let context = test_util::context();
+        let long_word = style::StyledStr::new("Supercalifragilistic", Style::new(), None);
+        let width = long_word.width(&context.font_cache) / 2.0;
+        let mut wrapper = Wrapper::new(
+            std::iter::once(Segment::Text(long_word)),
+            &context,
+            width,
+            WordOverflow::Shrink,
+        );
+
+        let (line, len) = wrapper.next().expect("shrunk line");
+        assert_eq!(line.len(), 1);
+        assert_eq!(text(&line[0]).s, "Supercalifragilistic");
+        assert!(text(&line[0]).style.font_size() < Style::new().font_size());
+        assert!(text(&line[0]).width(&context.font_cache) <= width);
+        assert_eq!(len, long_word.s.len());
+        assert!(wrapper.next().is_none());
+    }
+
+    #[test]
+    fn test_wrapper_prefers_soft_hyphens() {
+        let context = test_util::context();
+        let word = "Donau\u{AD}dampf\u{AD}schiff";
+        let style = Style::new();
+
+        // The complete word fits: the soft hyphens are not rendered.
+        let wide = style.str_width(&context.font_cache, "Donaudampfschiff") + Mm(1.0);
+        let words = std::iter::once(Segment::Text(style::StyledStr::new(word, style, None)));
+        let lines: Vec<_> = Wrapper::new(words, &context, wide, WordOverflow::Error).collect();
+        assert_eq!(lines.len(), 1);
+        assert_eq!(text(&lines[0].0[0]).s, "Donaudampfschiff");
+        assert_eq!(lines[0].1, word.len());
+
+        // The word is split at the last soft hyphen that fits.
+        let narrow = style.str_width(&context.font_cache, "Donaudampf-") + Mm(0.5);
+        let words = std::iter::once(Segment::Text(style::StyledStr::new(word, style, None)));
+        let lines: Vec<_> = Wrapper::new(words, &context, narrow, WordOverflow::Error).collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(text(&lines[0].0[0]).s, "Donaudampf-");
+        assert_eq!(text(&lines[1].0[0]).s, "schiff");
+        assert_eq!(lines[0].1 + lines[1].1, word.len());
+    }
+
+    #[test]
+    fn test_wrapper_moves_inline_elements_to_next_line() {
+        let context = test_util::context();
+        let style = Style::new();
+
+        let width = style.str_width(&context.font_cache, "Icon: ") + Mm(10.0);
+        let widths = [Mm(1.0), Mm(12.0)];
+        let words = vec![
+            Segment::Text(style::StyledStr::new("Icon: ", style, None)),
+            Segment::Inline(1),
+        ];
+        let lines: Vec<_> = Wrapper::new(words.into_iter(), &context, width, WordOverflow::Error)
+            .with_inline_widths(&widths)
+            .collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(text(&lines[0].0[0]).s, "Icon: ");
+        assert!(matches!(lines[1].0[0], Segment::Inline(1)));
+        assert_eq!(lines[1].1, 1);
+
+        // Inline elements that are wider than a line are never split.
+        let words = std::iter::once(Segment::Inline(1));
+        let mut wrapper =
+            Wrapper::new(words, &context, Mm(5.0), WordOverflow::Break).with_inline_widths(&widths);
+        let (line, _) = wrapper.next().expect("line");
+        assert!(matches!(line[0], Segment::Inline(1)));
+        assert!(wrapper.next().is_none());
+        assert_eq!(context.take_warnings().len(), 1);
+    }
+
+    #[test]
+    fn test_wrapper_keeps_private_use_characters_as_text() {
+        // Characters from the private use areas, for example from icon fonts, are regular text.
+        let context = test_util::context();
+        let icon = "\u{F0001}";
+        let widths = [Mm(1.0), Mm(12.0)];
+        let words = std::iter::once(Segment::Text(style::StyledStr::new(
+            icon,
+            Style::new(),
+            None,
+        )));
+        let lines: Vec<_> = Wrapper::new(words, &context, Mm(100.0), WordOverflow::Error)
+            .with_inline_widths(&widths)
+            .collect();
+        assert_eq!(lines.len(), 1);
+        assert_eq!(text(&lines[0].0[0]).s, icon);
+        assert_eq!(lines[0].1, icon.len());
+    }
+
+    #[test]
+    #[cfg(feature = "hyphenation")]
+    fn test_wrapper_uses_hyphenator_for_language() {
+        use hyphenation::{Language, Load, Standard};
+
+        let mut context = test_util::context();
+        let en = Standard::from_embedded(Language::EnglishUS).expect("embedded dictionary");
+        context.hyphenators.insert(Language::EnglishUS, en);
+
+        let word = "hyphenation";
+        let plain = Style::new();
+        let english = Style::new().with_language(Language::EnglishUS);
+        let width = plain.str_width(&context.font_cache, "hyphen-") + Mm(0.5);
+
+        // Without a language (and without a default hyphenator), the word cannot be split.
+        let words = std::iter::once(Segment::Text(style::StyledStr::new(word, plain, None)));
+        let mut wrapper = Wrapper::new(words, &context, width, WordOverflow::Error);
         assert!(wrapper.next().is_none());
         assert!(wrapper.has_overflowed());
+
+        let words = std::iter::once(Segment::Text(style::StyledStr::new(word, english, None)));
+        let lines: Vec<_> = Wrapper::new(words, &context, width, WordOverflow::Error).collect();
+        assert!(lines.len() > 1);
+        assert!(text(&lines[0].0[0]).s.ends_with('-'));
+        assert_eq!(lines.iter().map(|(_, len)| len).sum::<usize>(), word.len());
     }
 }